@@ -0,0 +1,334 @@
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::Sub;
+
+use crate::{
+    Clock, ClockSource, ClockState, FixedOffsetPolicy, OffsetLimiter, OffsetPolicy, OffsetStats,
+    Result, Timestamp,
+};
+
+/// A clock source that must be read asynchronously, such as a timing
+/// service queried over a socket. See `ClockSource`.
+///
+/// The returned future must be `Send`, so that clocks built on the source
+/// may be used from multi-threaded executors. Implementations may use
+/// `async fn now(&mut self)`.
+pub trait AsyncClockSource {
+    /// Represents the described clock time.
+    type Time: Ord + Copy + Sub<Output = Self::Delta> + fmt::Debug;
+    /// The difference between two timestamps.
+    type Delta: Ord;
+
+    /// Returns the current clock time.
+    fn now(&mut self) -> impl Future<Output = Result<Self::Time>> + Send;
+}
+
+/// Adapts a synchronous `ClockSource` into an `AsyncClockSource`, whose
+/// readings are ready immediately.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncSource<S>(pub S);
+
+impl<S: ClockSource> AsyncClockSource for SyncSource<S>
+where
+    S::Time: Send,
+{
+    type Time = S::Time;
+    type Delta = S::Delta;
+    fn now(&mut self) -> impl Future<Output = Result<Self::Time>> + Send {
+        std::future::ready(self.0.now())
+    }
+}
+
+// Replays the reading most recently fetched from an `AsyncClockSource`, so
+// that the synchronous clock logic may be shared.
+struct Fetched<T, D> {
+    reading: Option<T>,
+    delta: PhantomData<fn() -> D>,
+}
+
+impl<T: fmt::Debug, D> fmt::Debug for Fetched<T, D> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Fetched")
+            .field("reading", &self.reading)
+            .finish()
+    }
+}
+
+impl<T: Ord + Copy + Sub<Output = D> + fmt::Debug, D: Ord> ClockSource for Fetched<T, D> {
+    type Time = T;
+    type Delta = D;
+    fn now(&mut self) -> Result<T> {
+        Ok(self.reading.expect("reading fetched before use"))
+    }
+}
+
+type FetchedFor<S> = Fetched<<S as AsyncClockSource>::Time, <S as AsyncClockSource>::Delta>;
+
+/// A hybrid logical clock driven by an `AsyncClockSource`, with the same
+/// semantics as `Clock`.
+#[derive(Debug)]
+pub struct AsyncClock<S: AsyncClockSource> {
+    src: S,
+    clock: Clock<FetchedFor<S>>,
+}
+
+impl<S: AsyncClockSource> AsyncClock<S> {
+    /// Creates a clock with `src` as the time provider.
+    pub async fn new(mut src: S) -> Result<Self> {
+        let reading = src.now().await?;
+        let clock = Clock::new(Fetched {
+            reading: Some(reading),
+            delta: PhantomData,
+        })?;
+        Ok(AsyncClock { src, clock })
+    }
+
+    /// Creates a clock with `src` as the time provider, resuming from a
+    /// previously saved `state`. See `Clock::from_state`.
+    pub fn from_state(src: S, state: ClockState<S::Time>) -> Self {
+        let fetched = Fetched {
+            reading: None,
+            delta: PhantomData,
+        };
+        AsyncClock {
+            src,
+            clock: Clock::from_state(fetched, state),
+        }
+    }
+
+    /// Returns the clock's current state. See `Clock::state`.
+    pub fn state(&self) -> ClockState<S::Time> {
+        self.clock.state()
+    }
+
+    /// Sets the epoch applied to physical clock readings. See
+    /// `Clock::set_epoch`.
+    pub fn set_epoch(&mut self, epoch: u32) {
+        self.clock.set_epoch(epoch)
+    }
+
+    /// Sets the epoch, unless that would move it backwards. See
+    /// `Clock::try_set_epoch`.
+    pub fn try_set_epoch(&mut self, epoch: u32) -> Result<()> {
+        self.clock.try_set_epoch(epoch)
+    }
+
+    /// Limits the logical counter. See `Clock::set_max_count`.
+    pub fn set_max_count(&mut self, max_count: u32) {
+        self.clock.set_max_count(max_count)
+    }
+
+    /// Wraps this clock in an `AsyncOffsetLimiter` that refuses timestamps
+    /// more than `max_offset` ahead of our physical time.
+    pub fn with_max_diff(self, max_offset: S::Delta) -> AsyncOffsetLimiter<S> {
+        AsyncOffsetLimiter {
+            src: self.src,
+            limiter: OffsetLimiter::new(self.clock, max_offset),
+        }
+    }
+
+    /// Wraps this clock in an `AsyncOffsetLimiter` that uses `policy` to
+    /// decide which observed timestamps to accept.
+    pub fn with_offset_policy<P: OffsetPolicy<S::Time, S::Delta>>(
+        self,
+        policy: P,
+    ) -> AsyncOffsetLimiter<S, P> {
+        AsyncOffsetLimiter {
+            src: self.src,
+            limiter: OffsetLimiter::with_policy(self.clock, policy),
+        }
+    }
+
+    /// Creates a unique monotonic timestamp suitable for annotating messages
+    /// we send. See `Clock::now`.
+    pub async fn now(&mut self) -> Result<Timestamp<S::Time>> {
+        self.fetch().await?;
+        self.clock.now()
+    }
+
+    /// Accepts a timestamp from an incoming message, without reading the
+    /// clock source. See `Clock::observe`.
+    pub fn observe(&mut self, msg: &Timestamp<S::Time>) -> Result<()> {
+        self.clock.observe(msg)
+    }
+
+    /// Accepts a timestamp from an incoming message using the paper's
+    /// receive rule. See `Clock::receive`.
+    pub async fn receive(&mut self, msg: &Timestamp<S::Time>) -> Result<Timestamp<S::Time>> {
+        self.fetch().await?;
+        self.clock.receive(msg)
+    }
+
+    /// Get a reference to the clock source.
+    pub fn source(&self) -> &S {
+        &self.src
+    }
+
+    /// Get a mutable reference to the clock source.
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.src
+    }
+
+    async fn fetch(&mut self) -> Result<()> {
+        self.clock.src.reading = Some(self.src.now().await?);
+        Ok(())
+    }
+}
+
+/// A wrapper around `AsyncClock` that will refuse updates outside of our
+/// tolerance, with the same semantics as `OffsetLimiter`.
+pub struct AsyncOffsetLimiter<
+    S: AsyncClockSource,
+    P = FixedOffsetPolicy<<S as AsyncClockSource>::Delta>,
+> {
+    src: S,
+    limiter: OffsetLimiter<FetchedFor<S>, P>,
+}
+
+impl<S: AsyncClockSource + fmt::Debug, P: fmt::Debug> fmt::Debug for AsyncOffsetLimiter<S, P>
+where
+    S::Delta: fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("AsyncOffsetLimiter")
+            .field("src", &self.src)
+            .field("limiter", &self.limiter)
+            .finish()
+    }
+}
+
+impl<S: AsyncClockSource, P> AsyncOffsetLimiter<S, P> {
+    /// Returns statistics about the timestamps observed so far.
+    pub fn stats(&self) -> OffsetStats {
+        self.limiter.stats()
+    }
+
+    /// Get a reference to the offset policy
+    pub fn policy(&self) -> &P {
+        self.limiter.policy()
+    }
+
+    /// Get a mutable reference to the offset policy
+    pub fn policy_mut(&mut self) -> &mut P {
+        self.limiter.policy_mut()
+    }
+
+    /// Returns the clock's current state. See `Clock::state`.
+    pub fn state(&self) -> ClockState<S::Time> {
+        self.limiter.inner().state()
+    }
+
+    /// Extract the inner `AsyncClock`
+    pub fn into_inner(self) -> AsyncClock<S> {
+        AsyncClock {
+            src: self.src,
+            clock: self.limiter.into_inner(),
+        }
+    }
+
+    async fn fetch(&mut self) -> Result<()> {
+        self.limiter.inner_mut().src.reading = Some(self.src.now().await?);
+        Ok(())
+    }
+}
+
+impl<S: AsyncClockSource, P: OffsetPolicy<S::Time, S::Delta>> AsyncOffsetLimiter<S, P> {
+    /// Creates a unique monotonic timestamp suitable for annotating messages
+    /// we send. See `OffsetLimiter::now`.
+    pub async fn now(&mut self) -> Result<Timestamp<S::Time>> {
+        self.fetch().await?;
+        self.limiter.now()
+    }
+
+    /// Accepts a timestamp from an incoming message, unless it is too far
+    /// from our physical time. See `OffsetLimiter::observe`.
+    pub async fn observe(&mut self, msg: &Timestamp<S::Time>) -> Result<()> {
+        self.fetch().await?;
+        self.limiter.observe(msg)
+    }
+
+    /// Accepts a timestamp from an incoming message using the paper's
+    /// receive rule, unless it is too far from our physical time. See
+    /// `OffsetLimiter::receive`.
+    pub async fn receive(&mut self, msg: &Timestamp<S::Time>) -> Result<Timestamp<S::Time>> {
+        self.fetch().await?;
+        self.limiter.receive(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, ManualClock, ManualT, MockClock};
+    use std::sync::Arc;
+
+    struct Remote(Arc<ManualClock>);
+
+    impl AsyncClockSource for Remote {
+        type Time = ManualT;
+        type Delta = u64;
+        async fn now(&mut self) -> Result<ManualT> {
+            tokio::task::yield_now().await;
+            Ok(ManualT::from(self.0.time()))
+        }
+    }
+
+    #[tokio::test]
+    async fn should_issue_increasing_timestamps() -> Result<()> {
+        let mut clock = AsyncClock::new(SyncSource(MockClock::stepping(10, 0))).await?;
+        let a = clock.now().await?;
+        let b = clock.now().await?;
+        assert!(a < b, "{} < {}", a, b);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_follow_remote_source() -> Result<()> {
+        let time = Arc::new(ManualClock::new(10));
+        let mut clock = AsyncClock::new(Remote(time.clone())).await?;
+        time.set_time(20);
+        assert_eq!(clock.now().await?.time, ManualT::from(20));
+
+        let msg = Timestamp::new(0, ManualT::from(30), 4);
+        clock.observe(&msg)?;
+        assert_eq!(
+            clock.receive(&msg).await?,
+            Timestamp::new(0, ManualT::from(30), 5)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_resume_from_state() -> Result<()> {
+        let time = Arc::new(ManualClock::new(10));
+        let mut clock = AsyncClock::new(Remote(time.clone())).await?;
+        let a = clock.now().await?;
+        time.set_time(0);
+        let mut resumed = AsyncClock::from_state(Remote(time), clock.state());
+        assert!(a < resumed.now().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn limiter_should_refuse_distant_timestamps() -> Result<()> {
+        let time = Arc::new(ManualClock::new(10));
+        let mut clock = AsyncClock::new(Remote(time.clone()))
+            .await?
+            .with_max_diff(5);
+        clock
+            .observe(&Timestamp::new(0, ManualT::from(15), 0))
+            .await?;
+        let err = clock
+            .observe(&Timestamp::new(0, ManualT::from(16), 0))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::OffsetTooGreat { .. }), "{:?}", err);
+        time.set_time(11);
+        clock
+            .observe(&Timestamp::new(0, ManualT::from(16), 0))
+            .await?;
+        assert_eq!(clock.stats().rejected, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use portable_atomic::AtomicU128;
+
+use crate::{batch_end, Clock, ClockMetrics, Result, SharedClockSource, Timestamp, U64Time};
+
+/// A clock that may be used from many threads at once without locking.
+///
+/// The last observed timestamp is packed into a single 128-bit atomic, so
+/// that `now` and `observe` only need a shared reference. This requires that
+/// the source can be read through a shared reference, and that its times fit
+/// into 64 bits.
+pub struct AtomicClock<S: SharedClockSource> {
+    src: S,
+    epoch: AtomicU32,
+    last_observed: AtomicU128,
+}
+
+fn pack<T: U64Time>(ts: Timestamp<T>) -> u128 {
+    u128::from(ts.epoch) << 96 | u128::from(ts.time.as_u64()) << 32 | u128::from(ts.count)
+}
+
+fn unpack<T: U64Time>(val: u128) -> Timestamp<T> {
+    Timestamp {
+        epoch: (val >> 96) as u32,
+        time: T::of_u64((val >> 32) as u64),
+        count: val as u32,
+    }
+}
+
+impl<S: SharedClockSource> AtomicClock<S>
+where
+    S::Time: U64Time,
+{
+    /// Creates a clock with `src` as the time provider.
+    pub fn new(src: S) -> Result<Self> {
+        let init = src.now_shared()?;
+        let clock = AtomicClock {
+            src,
+            epoch: AtomicU32::new(0),
+            last_observed: AtomicU128::new(pack(Timestamp {
+                epoch: 0,
+                time: init,
+                count: 0,
+            })),
+        };
+        Ok(clock)
+    }
+
+    /// Used to create a new "epoch" of clock times. See `Clock::set_epoch`.
+    pub fn set_epoch(&self, epoch: u32) {
+        self.epoch.store(epoch, Ordering::SeqCst);
+    }
+
+    /// Creates a unique monotonic timestamp suitable for annotating messages we send.
+    pub fn now(&self) -> Result<Timestamp<S::Time>> {
+        let pt = self.read_pt()?;
+        self.do_observe(&pt, 1)
+    }
+
+    /// Returns the most recently issued or observed timestamp. See `Clock::peek`.
+    pub fn peek(&self) -> Timestamp<S::Time> {
+        unpack(self.last_observed.load(Ordering::SeqCst))
+    }
+
+    /// Reserves `n` strictly increasing timestamps with a single atomic
+    /// update. See `Clock::now_batch`.
+    pub fn now_batch(&self, n: usize) -> Result<Vec<Timestamp<S::Time>>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let pt = self.read_pt()?;
+        let first = self.do_observe(&pt, n)?;
+        let batch = (0..n as u32)
+            .map(|i| Timestamp {
+                count: first.count + i,
+                ..first
+            })
+            .collect();
+        Ok(batch)
+    }
+
+    /// Accepts a timestamp from an incoming message, and updates the clock
+    /// so that further calls to `now` will always return a timestamp that
+    /// `happens-after` either locally generated timestamps or that of the
+    /// input message.
+    pub fn observe(&self, msg: &Timestamp<S::Time>) -> Result<()> {
+        self.do_observe(msg, 1)?;
+        Ok(())
+    }
+
+    fn read_pt(&self) -> Result<Timestamp<S::Time>> {
+        Ok(Timestamp {
+            epoch: self.epoch.load(Ordering::SeqCst),
+            time: self.src.now_shared()?,
+            count: 0,
+        })
+    }
+
+    // Merges `observation` into the clock, reserving a batch of `n` counter
+    // values starting at the resulting timestamp, which is returned.
+    fn do_observe(&self, observation: &Timestamp<S::Time>, n: usize) -> Result<Timestamp<S::Time>> {
+        let mut current = self.last_observed.load(Ordering::SeqCst);
+        loop {
+            let next = unpack::<S::Time>(current).merge(observation)?;
+            let last = Timestamp {
+                count: batch_end(next.count, n)?,
+                ..next
+            };
+            match self.last_observed.compare_exchange_weak(
+                current,
+                pack(last),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return Ok(next),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Get a reference to the clock source.
+    pub fn source(&self) -> &S {
+        &self.src
+    }
+
+    /// Converts this into a `Clock` with the same state.
+    pub fn into_clock(self) -> Clock<S> {
+        let last_observed = unpack(self.last_observed.into_inner());
+        Clock {
+            src: self.src,
+            epoch: self.epoch.into_inner(),
+            last_observed,
+            max_count: u32::MAX,
+            last_reading: None,
+            metrics: ClockMetrics::default(),
+        }
+    }
+}
+
+impl<S: SharedClockSource + fmt::Debug> fmt::Debug for AtomicClock<S>
+where
+    S::Time: U64Time,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("AtomicClock")
+            .field("src", &self.src)
+            .field("epoch", &self.epoch.load(Ordering::SeqCst))
+            .field(
+                "last_observed",
+                &unpack::<S::Time>(self.last_observed.load(Ordering::SeqCst)),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::{Error, ManualClock, ManualT, WallNS};
+    use std::sync::Arc;
+    use std::thread;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    #[test]
+    fn packing_should_round_trip() {
+        property(timestamps(manualts())).check(|ts| unpack::<ManualT>(pack(ts)) == ts);
+    }
+
+    #[test]
+    fn packing_should_order_as_timestamps() {
+        property((timestamps(manualts()), timestamps(manualts())))
+            .check(|(ta, tb)| ta.cmp(&tb) == pack(ta).cmp(&pack(tb)));
+    }
+
+    #[test]
+    fn should_be_larger_than_observed_time() {
+        property((u64s(), timestamps(manualts()))).check(|(t0, observed)| -> Result<()> {
+            let clock = AtomicClock::new(ManualClock::new(t0))?;
+            clock.observe(&observed)?;
+            let t = clock.now()?;
+            assert!(t > observed, "{:?} > {:?}", t, observed);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn should_refuse_to_overflow_counter() -> Result<()> {
+        let clock = AtomicClock::new(ManualClock::new(10))?;
+        let exhausted = Timestamp {
+            epoch: 0,
+            time: ManualT::from(10),
+            count: u32::MAX,
+        };
+        assert!(matches!(
+            clock.observe(&exhausted),
+            Err(Error::CounterExhausted)
+        ));
+        assert_eq!(clock.peek().count, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn should_be_monotonic_under_concurrency() {
+        property((u8s(), u8s())).check(|(nthreads, iterations)| -> Result<()> {
+            let clock = Arc::new(AtomicClock::new(WallNS)?);
+            let threads = (0..nthreads % 8 + 1)
+                .map(|_| {
+                    let clock = clock.clone();
+                    thread::spawn(move || {
+                        (0..iterations)
+                            .map(|_| clock.now())
+                            .collect::<Result<Vec<_>>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut all = Vec::new();
+            for t in threads {
+                let issued = t.join().expect("join")?;
+                assert!(
+                    issued.windows(2).all(|w| w[0] < w[1]),
+                    "Strictly increasing: {:?}",
+                    issued
+                );
+                all.extend(issued);
+            }
+
+            let n = all.len();
+            all.sort();
+            all.dedup();
+            assert_eq!(all.len(), n, "All timestamps should be unique");
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn batches_should_not_overlap_under_concurrency() -> Result<()> {
+        let clock = Arc::new(AtomicClock::new(WallNS)?);
+        let threads = (0..4)
+            .map(|_| {
+                let clock = clock.clone();
+                thread::spawn(move || {
+                    (0..50)
+                        .map(|_| clock.now_batch(7))
+                        .collect::<Result<Vec<_>>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut all = Vec::new();
+        for t in threads {
+            for batch in t.join().expect("join")? {
+                assert!(batch.windows(2).all(|w| w[0] < w[1]), "{:?}", batch);
+                all.extend(batch);
+            }
+        }
+        let n = all.len();
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), n, "All timestamps should be unique");
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{Error, Result, Timestamp, U64Time};
+
+// Crockford's alphabet, which is in ascending ASCII order.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+// 128 bits at five bits per character, rounded up.
+const LEN: usize = 26;
+
+fn decode_char(c: u8) -> Option<u128> {
+    let c = match c.to_ascii_uppercase() {
+        b'O' => b'0',
+        b'I' | b'L' => b'1',
+        c => c,
+    };
+    ALPHABET.iter().position(|&a| a == c).map(|i| i as u128)
+}
+
+impl<T: U64Time> Timestamp<T> {
+    /// Encodes the 16 byte `ByteOrdered` encoding of the timestamp as 26
+    /// characters of Crockford's base32, such that the strings sort in the
+    /// same order as the timestamps. These are safe to use in filenames and
+    /// object store keys.
+    pub fn to_sortable_string(&self) -> String {
+        let val = self.to_u128();
+        (0..LEN)
+            .rev()
+            .map(|i| ALPHABET[(val >> (i * 5)) as usize & 0x1f] as char)
+            .collect()
+    }
+
+    /// Decodes a string written by `to_sortable_string`. As per Crockford's
+    /// base32, this is case insensitive, and accepts `O` for `0`, and `I` or
+    /// `L` for `1`.
+    pub fn from_sortable_string(s: &str) -> Result<Self> {
+        let invalid = || Error::Parse(s.to_owned());
+        if s.len() != LEN {
+            return Err(invalid());
+        }
+        let mut val = 0u128;
+        for (i, c) in s.bytes().enumerate() {
+            let digit = decode_char(c).ok_or_else(invalid)?;
+            // The first character carries only the top three bits.
+            if i == 0 && digit > 7 {
+                return Err(invalid());
+            }
+            val = val << 5 | digit;
+        }
+        Ok(Timestamp::from_u128(val))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::{ManualT, WallNST};
+    use suppositions::*;
+
+    #[test]
+    fn should_round_trip_via_sortable_string() {
+        property(timestamps(manualts())).check(|ts| {
+            let s = ts.to_sortable_string();
+            Timestamp::<ManualT>::from_sortable_string(&s).expect("decode") == ts
+        });
+    }
+
+    #[test]
+    fn sortable_strings_should_order_as_timestamps() {
+        property((timestamps(manualts()), timestamps(manualts())))
+            .check(|(ta, tb)| ta.cmp(&tb) == ta.to_sortable_string().cmp(&tb.to_sortable_string()));
+    }
+
+    #[test]
+    fn should_encode_known_values() -> Result<()> {
+        let ts = Timestamp::new(0, WallNST::of_u64(0), 1);
+        assert_eq!(ts.to_sortable_string(), "00000000000000000000000001");
+        let max = Timestamp::new(u32::MAX, WallNST::of_u64(u64::MAX), u32::MAX);
+        assert_eq!(max.to_sortable_string(), "7ZZZZZZZZZZZZZZZZZZZZZZZZZ");
+        assert_eq!(
+            Timestamp::<WallNST>::from_sortable_string("0000000000000000000000000l")?,
+            ts
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_invalid_strings() {
+        for s in &[
+            "",
+            "0000000000000000000000000",
+            "000000000000000000000000000",
+            "8ZZZZZZZZZZZZZZZZZZZZZZZZZ",
+            "0000000000000000000000000U",
+        ] {
+            assert!(
+                Timestamp::<WallNST>::from_sortable_string(s).is_err(),
+                "{:?}",
+                s
+            );
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{Clock, ClockSource, OffsetLimiter, Result};
+
+/// Configures a clock in one place before constructing it.
+///
+/// Building without an offset limit yields a plain `Clock`; once
+/// `max_forward_offset` has been given, building yields an `OffsetLimiter`.
+#[derive(Debug, Clone)]
+pub struct ClockBuilder<S: ClockSource, L = Unlimited> {
+    src: S,
+    epoch: u32,
+    max_count: u32,
+    limits: L,
+}
+
+/// Marks a `ClockBuilder` that has no offset limits configured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Unlimited;
+
+/// The offset limits configured on a `ClockBuilder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffsetLimits<D> {
+    max_forward: D,
+    max_backward: Option<D>,
+}
+
+impl<S: ClockSource> Clock<S> {
+    /// Returns a builder for a clock with `src` as the time provider.
+    pub fn builder(src: S) -> ClockBuilder<S> {
+        ClockBuilder {
+            src,
+            epoch: 0,
+            max_count: u32::MAX,
+            limits: Unlimited,
+        }
+    }
+}
+
+impl<S: ClockSource, L> ClockBuilder<S, L> {
+    /// Sets the initial epoch of the clock. See `Clock::set_epoch`.
+    pub fn epoch(self, epoch: u32) -> Self {
+        ClockBuilder { epoch, ..self }
+    }
+
+    /// Sets the greatest logical counter value the clock will issue or
+    /// accept. See `Clock::set_max_count`.
+    pub fn max_count(self, max_count: u32) -> Self {
+        ClockBuilder { max_count, ..self }
+    }
+
+    fn build_clock(self) -> Result<(Clock<S>, L)> {
+        let mut clock = Clock::new(self.src)?;
+        clock.set_epoch(self.epoch);
+        clock.set_max_count(self.max_count);
+        Ok((clock, self.limits))
+    }
+}
+
+impl<S: ClockSource> ClockBuilder<S, Unlimited> {
+    /// Refuse to observe timestamps more than `max_offset` ahead of our
+    /// physical time. See `Clock::with_max_diff`.
+    pub fn max_forward_offset(
+        self,
+        max_offset: S::Delta,
+    ) -> ClockBuilder<S, OffsetLimits<S::Delta>> {
+        ClockBuilder {
+            src: self.src,
+            epoch: self.epoch,
+            max_count: self.max_count,
+            limits: OffsetLimits {
+                max_forward: max_offset,
+                max_backward: None,
+            },
+        }
+    }
+
+    /// Constructs the configured `Clock`.
+    pub fn build(self) -> Result<Clock<S>> {
+        let (clock, Unlimited) = self.build_clock()?;
+        Ok(clock)
+    }
+}
+
+impl<S: ClockSource> ClockBuilder<S, OffsetLimits<S::Delta>> {
+    /// Refuse to observe timestamps more than `max_offset` behind our
+    /// physical time. See `OffsetLimiter::with_max_diff_backward`.
+    pub fn max_backward_offset(self, max_offset: S::Delta) -> Self {
+        ClockBuilder {
+            limits: OffsetLimits {
+                max_backward: Some(max_offset),
+                ..self.limits
+            },
+            ..self
+        }
+    }
+
+    /// Constructs the configured `OffsetLimiter`.
+    pub fn build(self) -> Result<OffsetLimiter<S>> {
+        let (clock, limits) = self.build_clock()?;
+        let limiter = clock.with_max_diff(limits.max_forward);
+        Ok(match limits.max_backward {
+            Some(max_offset) => limiter.with_max_diff_backward(max_offset),
+            None => limiter,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, ManualClock, ManualT, Timestamp};
+
+    #[test]
+    fn should_build_clock_with_epoch() -> Result<()> {
+        let mut clock = Clock::builder(ManualClock::new(10)).epoch(3).build()?;
+        assert_eq!(
+            clock.now()?,
+            Timestamp {
+                epoch: 3,
+                time: ManualT::from(10),
+                count: 0,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_build_clock_with_max_count() -> Result<()> {
+        let mut clock = Clock::builder(ManualClock::new(10)).max_count(2).build()?;
+        assert_eq!(clock.now()?.count, 1);
+        assert_eq!(clock.now()?.count, 2);
+        assert!(matches!(
+            clock.now(),
+            Err(Error::CountLimitExceeded { count: 3, limit: 2 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn should_build_offset_limiter() -> Result<()> {
+        let mut clock = Clock::builder(ManualClock::new(0))
+            .max_forward_offset(10)
+            .epoch(2)
+            .build()?;
+        assert!(clock
+            .observe(&Timestamp {
+                epoch: 2,
+                time: ManualT::from(11),
+                count: 0,
+            })
+            .is_err());
+        assert_eq!(clock.now()?.epoch, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn should_build_offset_limiter_with_backward_limit() -> Result<()> {
+        let mut clock = Clock::builder(ManualClock::new(100))
+            .max_forward_offset(10)
+            .max_backward_offset(20)
+            .build()?;
+        let at = |time: u64| Timestamp {
+            epoch: 0,
+            time: ManualT::from(time),
+            count: 0,
+        };
+        assert!(matches!(
+            clock.observe(&at(79)),
+            Err(Error::OffsetTooFarBehind { .. })
+        ));
+        clock.observe(&at(80))?;
+        Ok(())
+    }
+}
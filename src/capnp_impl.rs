@@ -0,0 +1,112 @@
+use std::convert::TryFrom;
+
+use crate::hybrid_clocks_capnp::{timestamp, SourceKind};
+use crate::{ByteOrdered, Error, ManualT, Result, Timestamp, U64Time};
+use crate::{WallMST, WallMillisT, WallNST, WallUST};
+
+/// Time types that may be carried in a Cap'n Proto `Timestamp`, as defined
+/// in `proto/hybrid_clocks.capnp`.
+pub trait CapnpTime: U64Time {
+    /// The kind of source recorded for this time type.
+    const KIND: SourceKind;
+}
+
+impl CapnpTime for WallNST {
+    const KIND: SourceKind = SourceKind::WallNs;
+}
+impl CapnpTime for WallMST {
+    const KIND: SourceKind = SourceKind::WallMs;
+}
+impl CapnpTime for WallUST {
+    const KIND: SourceKind = SourceKind::WallUs;
+}
+impl CapnpTime for WallMillisT {
+    const KIND: SourceKind = SourceKind::WallMillis;
+}
+impl CapnpTime for ManualT {
+    const KIND: SourceKind = SourceKind::Manual;
+}
+
+impl<T: CapnpTime> Timestamp<T> {
+    /// Writes this timestamp into a Cap'n Proto `Timestamp`, including its
+    /// byte-ordered form.
+    pub fn write_capnp(&self, mut builder: timestamp::Builder<'_>) {
+        builder.set_epoch(self.epoch);
+        builder.set_ticks(self.time.as_u64());
+        builder.set_count(self.count);
+        builder.set_source(T::KIND);
+        builder.set_ordered(&self.to_ordered_bytes());
+    }
+
+    /// Reads a timestamp from a Cap'n Proto `Timestamp`. Fails with
+    /// `Error::SourceKindMismatch` if it was not issued by a source of this
+    /// time type.
+    pub fn read_capnp(reader: timestamp::Reader<'_>) -> Result<Self> {
+        let found = reader.get_source().map_or_else(|e| e.0, |k| k as u16);
+        if found != T::KIND as u16 {
+            return Err(Error::SourceKindMismatch {
+                expected: i32::from(T::KIND as u16),
+                found: i32::from(found),
+            });
+        }
+        Ok(Timestamp {
+            epoch: reader.get_epoch(),
+            time: T::of_u64(reader.get_ticks()),
+            count: reader.get_count(),
+        })
+    }
+}
+
+impl<'a, T: CapnpTime> TryFrom<timestamp::Reader<'a>> for Timestamp<T> {
+    type Error = Error;
+    fn try_from(reader: timestamp::Reader<'a>) -> Result<Self> {
+        Timestamp::read_capnp(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use capnp::message::{Builder, ReaderOptions};
+    use capnp::serialize;
+    use suppositions::*;
+
+    fn encode<T: CapnpTime>(ts: &Timestamp<T>) -> Vec<u8> {
+        let mut message = Builder::new_default();
+        ts.write_capnp(message.init_root());
+        serialize::write_message_to_words(&message)
+    }
+
+    #[test]
+    fn should_round_trip_via_capnp() {
+        property(timestamps(manualts())).check(|ts| {
+            let bytes = encode(&ts);
+            let message =
+                serialize::read_message_from_flat_slice(&mut &bytes[..], ReaderOptions::new())
+                    .expect("read message");
+            let reader = message.get_root::<timestamp::Reader>().expect("get root");
+            assert_eq!(
+                reader.get_ordered().expect("ordered"),
+                &ts.to_ordered_bytes()
+            );
+            Timestamp::<ManualT>::try_from(reader).expect("convert") == ts
+        });
+    }
+
+    #[test]
+    fn should_refuse_mismatched_source() {
+        let bytes = encode(&Timestamp::new(0, WallMST::of_u64(1), 0));
+        let message =
+            serialize::read_message_from_flat_slice(&mut &bytes[..], ReaderOptions::new())
+                .expect("read message");
+        let reader = message.get_root().expect("get root");
+        assert!(matches!(
+            Timestamp::<WallNST>::read_capnp(reader),
+            Err(Error::SourceKindMismatch {
+                expected: 1,
+                found: 2
+            })
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+use std::cmp::Ordering;
+
+use crate::hvc::VectorTimestamp;
+use crate::Timestamp;
+
+/// The latest timestamp observed from each peer, as used for CRDT metadata
+/// and anti-entropy: an event from a peer is known to us if its timestamp
+/// is at or before the one we hold for that peer. This is a
+/// `VectorTimestamp` viewed as a set of known events.
+///
+/// With the `serialization` feature, this is serialized as a sequence of
+/// `(peer, timestamp)` pairs, rather than as a map, which is more compact
+/// in binary formats.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CausalContext<T, Id: Ord> {
+    vector: VectorTimestamp<T, Id>,
+}
+
+impl<T: Ord + Copy, Id: Ord + Clone> CausalContext<T, Id> {
+    /// Returns an empty context, which knows of no events.
+    pub fn new() -> Self {
+        CausalContext {
+            vector: VectorTimestamp::new(),
+        }
+    }
+
+    /// Records that we have seen `timestamp` from `peer`, returning true if
+    /// that advanced our knowledge of the peer.
+    pub fn observe(&mut self, peer: Id, timestamp: Timestamp<T>) -> bool {
+        let advanced = !self.contains(&peer, &timestamp);
+        self.vector.update(peer, timestamp);
+        advanced
+    }
+
+    /// Returns the latest timestamp observed from `peer`.
+    pub fn get(&self, peer: &Id) -> Option<&Timestamp<T>> {
+        self.vector.get(peer)
+    }
+
+    /// Returns true if the event at `timestamp` from `peer` is known to us.
+    pub fn contains(&self, peer: &Id, timestamp: &Timestamp<T>) -> bool {
+        self.get(peer).is_some_and(|latest| latest >= timestamp)
+    }
+
+    /// Returns true if every event known to `other` is known to us.
+    pub fn dominates(&self, other: &Self) -> bool {
+        matches!(
+            self.vector.partial_cmp_causal(&other.vector),
+            Some(Ordering::Greater | Ordering::Equal)
+        )
+    }
+
+    /// Takes the latest timestamp from either side for each peer.
+    pub fn merge(&mut self, other: &Self) {
+        self.vector.merge(&other.vector);
+    }
+
+    /// Iterates over the latest timestamp observed from each peer, in order
+    /// of peer id.
+    pub fn iter(&self) -> impl Iterator<Item = (&Id, &Timestamp<T>)> {
+        self.vector.iter()
+    }
+
+    /// Returns the number of peers observed.
+    pub fn len(&self) -> usize {
+        self.vector.len()
+    }
+
+    /// Returns true if no peers have been observed.
+    pub fn is_empty(&self) -> bool {
+        self.vector.is_empty()
+    }
+
+    /// Returns the underlying vector timestamp.
+    pub fn as_vector(&self) -> &VectorTimestamp<T, Id> {
+        &self.vector
+    }
+}
+
+impl<T, Id: Ord> From<VectorTimestamp<T, Id>> for CausalContext<T, Id> {
+    fn from(vector: VectorTimestamp<T, Id>) -> Self {
+        CausalContext { vector }
+    }
+}
+
+impl<T, Id: Ord> From<CausalContext<T, Id>> for VectorTimestamp<T, Id> {
+    fn from(ctx: CausalContext<T, Id>) -> Self {
+        ctx.vector
+    }
+}
+
+impl<T: Ord + Copy, Id: Ord + Clone> Default for CausalContext<T, Id> {
+    fn default() -> Self {
+        CausalContext::new()
+    }
+}
+
+impl<T: Ord + Copy, Id: Ord + Clone> Extend<(Id, Timestamp<T>)> for CausalContext<T, Id> {
+    fn extend<I: IntoIterator<Item = (Id, Timestamp<T>)>>(&mut self, iter: I) {
+        for (peer, ts) in iter {
+            self.observe(peer, ts);
+        }
+    }
+}
+
+impl<T: Ord + Copy, Id: Ord + Clone> std::iter::FromIterator<(Id, Timestamp<T>)>
+    for CausalContext<T, Id>
+{
+    fn from_iter<I: IntoIterator<Item = (Id, Timestamp<T>)>>(iter: I) -> Self {
+        let mut ctx = CausalContext::new();
+        ctx.extend(iter);
+        ctx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::ManualT;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    fn contexts() -> Box<dyn GeneratorObject<Item = CausalContext<ManualT, u8>>> {
+        vecs((u8s().map(|p| p % 4), timestamps(manualts())))
+            .map(|entries| entries.into_iter().collect())
+            .boxed()
+    }
+
+    fn ts(t: u64) -> Timestamp<ManualT> {
+        Timestamp::new(0, ManualT::from(t), 0)
+    }
+
+    #[test]
+    fn should_keep_latest_per_peer() {
+        let mut ctx = CausalContext::new();
+        assert!(ctx.observe("a", ts(5)));
+        assert!(!ctx.observe("a", ts(3)));
+        assert!(ctx.observe("b", ts(1)));
+        assert_eq!(ctx.get(&"a"), Some(&ts(5)));
+        assert!(ctx.contains(&"a", &ts(4)));
+        assert!(!ctx.contains(&"b", &ts(2)));
+        assert!(!ctx.contains(&"c", &ts(0)));
+    }
+
+    #[test]
+    fn merge_should_dominate_both_sides() {
+        property((contexts(), contexts())).check(|(a, b)| {
+            let mut merged = a.clone();
+            merged.merge(&b);
+            merged.dominates(&a) && merged.dominates(&b) && merged.dominates(&merged)
+        });
+    }
+
+    #[test]
+    fn dominance_should_be_refused_for_unseen_events() {
+        let a: CausalContext<_, _> = vec![("a", ts(5))].into_iter().collect();
+        let b: CausalContext<_, _> = vec![("a", ts(4)), ("b", ts(1))].into_iter().collect();
+        assert!(!a.dominates(&b) && !b.dominates(&a));
+        assert!(a.dominates(&CausalContext::new()));
+    }
+
+    #[test]
+    fn should_dominate_iff_merge_adds_nothing() {
+        property((contexts(), contexts())).check(|(a, b)| {
+            let mut merged = a.clone();
+            merged.merge(&b);
+            a.dominates(&b) == (merged == a)
+        });
+    }
+
+    #[cfg(feature = "serialization")]
+    #[test]
+    fn should_round_trip_via_serde() {
+        property(contexts()).check(|ctx| {
+            let bytes = bincode::serialize(&ctx).expect("to-bincode");
+            let s = serde_json::to_string(&ctx).expect("to-json");
+            bincode::deserialize::<CausalContext<ManualT, u8>>(&bytes).expect("from-bincode") == ctx
+                && serde_json::from_str::<CausalContext<ManualT, u8>>(&s).expect("from-json") == ctx
+        });
+    }
+}
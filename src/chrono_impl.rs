@@ -0,0 +1,123 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::{Error, Result, Timestamp, WallMST, WallNST};
+
+// Returns the time since the unix epoch, failing for earlier times.
+fn since_epoch<Tz: TimeZone>(dt: &DateTime<Tz>) -> Result<Duration> {
+    let secs = u64::try_from(dt.timestamp())?;
+    Ok(Duration::new(secs, dt.timestamp_subsec_nanos()))
+}
+
+fn to_datetime(since_epoch: Duration) -> Option<DateTime<Utc>> {
+    let secs = i64::try_from(since_epoch.as_secs()).ok()?;
+    DateTime::from_timestamp(secs, since_epoch.subsec_nanos())
+}
+
+impl WallNST {
+    /// Returns the `WallNST` for `dt`. Fails if it is before the unix epoch,
+    /// or after 2554.
+    pub fn from_datetime<Tz: TimeZone>(dt: &DateTime<Tz>) -> Result<Self> {
+        WallNST::from_since_epoch(since_epoch(dt)?)
+    }
+}
+
+impl From<WallNST> for DateTime<Utc> {
+    fn from(t: WallNST) -> Self {
+        to_datetime(t.duration_since_epoch()).expect("WallNST within chrono's range")
+    }
+}
+
+/// Discards the epoch and count.
+impl From<Timestamp<WallNST>> for DateTime<Utc> {
+    fn from(ts: Timestamp<WallNST>) -> Self {
+        ts.time.into()
+    }
+}
+
+impl WallMST {
+    /// Returns the `WallMST` for `dt`, rounded up to a whole tick, so that
+    /// converting a `WallMST` to a `DateTime` and back is lossless. Fails if
+    /// it is before the unix epoch.
+    pub fn from_datetime<Tz: TimeZone>(dt: &DateTime<Tz>) -> Result<Self> {
+        let since_epoch = since_epoch(dt)?;
+        WallMST::of_u64(0)
+            .checked_add_duration(since_epoch)
+            .ok_or(Error::SupportedTime(since_epoch.as_nanos()))
+    }
+}
+
+/// Fails with `Error::SupportedTime` for times beyond chrono's range.
+impl TryFrom<WallMST> for DateTime<Utc> {
+    type Error = Error;
+    fn try_from(t: WallMST) -> Result<Self> {
+        to_datetime(t.duration_since_epoch()).ok_or(Error::SupportedTime(u128::from(t.as_u64())))
+    }
+}
+
+/// Discards the epoch and count.
+impl TryFrom<Timestamp<WallMST>> for DateTime<Utc> {
+    type Error = Error;
+    fn try_from(ts: Timestamp<WallMST>) -> Result<Self> {
+        DateTime::try_from(ts.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    #[test]
+    fn wall_ns_should_round_trip_via_datetime() {
+        property(u64s()).check(|nanos| {
+            let t = WallNST::of_u64(nanos);
+            WallNST::from_datetime(&DateTime::from(t)).expect("from-datetime") == t
+        });
+    }
+
+    #[test]
+    fn wall_ms_should_round_trip_via_datetime() {
+        property(u64s()).check(|ticks| {
+            let t = WallMST::of_u64(ticks >> 16);
+            let dt = DateTime::try_from(t).expect("to-datetime");
+            WallMST::from_datetime(&dt).expect("from-datetime") == t
+        });
+    }
+
+    #[test]
+    fn wall_ms_should_keep_subsecond_precision() -> Result<()> {
+        // 2024-07-01T15:32:06.5Z, plus one tick.
+        let t = WallMST::of_u64((1_719_847_926 << 16) + (1 << 15) + 1);
+        let dt = DateTime::try_from(t)?;
+        assert_eq!(dt.to_rfc3339(), "2024-07-01T15:32:06.500015258+00:00");
+        assert_eq!(WallMST::from_datetime(&dt)?, t);
+        Ok(())
+    }
+
+    #[test]
+    fn should_drop_count_when_converting_timestamps() -> Result<()> {
+        let t = WallNST::of_u64(1_719_847_926_000_000_001);
+        let dt = DateTime::<Utc>::from(Timestamp::new(2, t, 3));
+        assert_eq!(dt, DateTime::<Utc>::from(t));
+        let t = WallMST::of_u64(1_719_847_926 << 16);
+        assert_eq!(
+            DateTime::try_from(Timestamp::new(2, t, 3))?,
+            DateTime::try_from(t)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_times_out_of_range() {
+        let before_epoch = DateTime::from_timestamp(-1, 0).expect("datetime");
+        assert!(WallNST::from_datetime(&before_epoch).is_err());
+        assert!(WallMST::from_datetime(&before_epoch).is_err());
+        let far_future = DateTime::from_timestamp(1 << 36, 0).expect("datetime");
+        assert!(WallNST::from_datetime(&far_future).is_err());
+        assert!(DateTime::try_from(WallMST::of_u64(u64::MAX)).is_err());
+    }
+}
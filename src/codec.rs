@@ -0,0 +1,225 @@
+//! A compact encoding for sequences of timestamps, such as those in a
+//! write-ahead log, where successive timestamps are usually close together.
+//!
+//! Each timestamp is written relative to the previous one (or to a zero
+//! timestamp, for the first), as LEB128 varints: the zigzag-encoded
+//! difference in time ticks, then the count shifted left by one, with the
+//! low bit set if the epoch changed, in which case the new epoch follows.
+//! Mostly-increasing timestamps thus typically take three or four bytes,
+//! rather than sixteen.
+
+use std::convert::TryFrom;
+use std::io;
+use std::marker::PhantomData;
+
+use crate::{Timestamp, U64Time};
+
+/// Writes a sequence of timestamps to an `io::Write`.
+#[derive(Debug)]
+pub struct DeltaEncoder<W, T> {
+    wr: W,
+    prev_epoch: u32,
+    prev_time: u64,
+    time: PhantomData<T>,
+}
+
+/// Reads a sequence of timestamps written by a `DeltaEncoder`. Decoders are
+/// also iterators, which end at a clean end of input.
+#[derive(Debug)]
+pub struct DeltaDecoder<R, T> {
+    rd: R,
+    prev_epoch: u32,
+    prev_time: u64,
+    time: PhantomData<T>,
+}
+
+impl<W: io::Write, T: U64Time> DeltaEncoder<W, T> {
+    /// Creates an encoder writing to `wr`.
+    pub fn new(wr: W) -> Self {
+        DeltaEncoder {
+            wr,
+            prev_epoch: 0,
+            prev_time: 0,
+            time: PhantomData,
+        }
+    }
+
+    /// Writes `ts`, relative to the previously written timestamp.
+    pub fn encode(&mut self, ts: &Timestamp<T>) -> io::Result<()> {
+        let time = ts.time.as_u64();
+        let delta = time.wrapping_sub(self.prev_time) as i64;
+        let epoch_changed = ts.epoch != self.prev_epoch;
+        write_varint(&mut self.wr, zigzag(delta))?;
+        write_varint(
+            &mut self.wr,
+            u64::from(ts.count) << 1 | u64::from(epoch_changed),
+        )?;
+        if epoch_changed {
+            write_varint(&mut self.wr, u64::from(ts.epoch))?;
+        }
+        self.prev_epoch = ts.epoch;
+        self.prev_time = time;
+        Ok(())
+    }
+
+    /// Extract the inner writer
+    pub fn into_inner(self) -> W {
+        self.wr
+    }
+}
+
+impl<R: io::Read, T: U64Time> DeltaDecoder<R, T> {
+    /// Creates a decoder reading from `rd`.
+    pub fn new(rd: R) -> Self {
+        DeltaDecoder {
+            rd,
+            prev_epoch: 0,
+            prev_time: 0,
+            time: PhantomData,
+        }
+    }
+
+    /// Reads the next timestamp, or returns `None` at a clean end of input.
+    /// Input that ends part way through a timestamp is an error.
+    pub fn decode(&mut self) -> io::Result<Option<Timestamp<T>>> {
+        let mut first = [0u8];
+        if self.rd.read(&mut first)? == 0 {
+            return Ok(None);
+        }
+        let delta = unzigzag(read_varint_from(first[0], &mut self.rd)?);
+        let count_flag = read_varint(&mut self.rd)?;
+        let epoch = if count_flag & 1 != 0 {
+            u32::try_from(read_varint(&mut self.rd)?).map_err(invalid)?
+        } else {
+            self.prev_epoch
+        };
+        let count = u32::try_from(count_flag >> 1).map_err(invalid)?;
+        let time = self.prev_time.wrapping_add(delta as u64);
+        self.prev_epoch = epoch;
+        self.prev_time = time;
+        Ok(Some(Timestamp {
+            epoch,
+            time: T::of_u64(time),
+            count,
+        }))
+    }
+
+    /// Extract the inner reader
+    pub fn into_inner(self) -> R {
+        self.rd
+    }
+}
+
+impl<R: io::Read, T: U64Time> Iterator for DeltaDecoder<R, T> {
+    type Item = io::Result<Timestamp<T>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.decode().transpose()
+    }
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+pub(crate) fn write_varint<W: io::Write>(mut wr: W, mut n: u64) -> io::Result<()> {
+    let mut buf = [0u8; 10];
+    let mut len = 0;
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    wr.write_all(&buf[..len])
+}
+
+pub(crate) fn read_varint<R: io::Read>(mut rd: R) -> io::Result<u64> {
+    let mut first = [0u8];
+    rd.read_exact(&mut first)?;
+    read_varint_from(first[0], rd)
+}
+
+// Reads the remainder of a varint whose first byte is `first`.
+fn read_varint_from<R: io::Read>(first: u8, mut rd: R) -> io::Result<u64> {
+    let mut n = u64::from(first & 0x7f);
+    let mut byte = first;
+    let mut shift = 7;
+    while byte & 0x80 != 0 {
+        if shift >= 64 {
+            return Err(invalid("varint too long"));
+        }
+        let mut buf = [0u8];
+        rd.read_exact(&mut buf)?;
+        byte = buf[0];
+        n |= u64::from(byte & 0x7f) << shift;
+        shift += 7;
+    }
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::ManualT;
+    use std::io::Cursor;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    #[test]
+    fn should_round_trip_sequences() {
+        property(vecs(timestamps(manualts()))).check(|tss| {
+            let mut enc = DeltaEncoder::new(Vec::new());
+            for ts in &tss {
+                enc.encode(ts).expect("encode");
+            }
+            let bytes = enc.into_inner();
+            let decoded = DeltaDecoder::<_, ManualT>::new(Cursor::new(bytes))
+                .collect::<io::Result<Vec<_>>>()
+                .expect("decode");
+            decoded == tss
+        })
+    }
+
+    #[test]
+    fn should_be_compact_for_increasing_timestamps() -> io::Result<()> {
+        let mut enc = DeltaEncoder::new(Vec::new());
+        for i in 0..100u64 {
+            enc.encode(&Timestamp::new(
+                0,
+                ManualT::from(1_600_000_000_000 + i * 100),
+                0,
+            ))?;
+        }
+        // The first timestamp takes a few bytes; the rest take three each.
+        assert!(enc.into_inner().len() < 310);
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_truncated_input() -> io::Result<()> {
+        let mut enc = DeltaEncoder::new(Vec::new());
+        enc.encode(&Timestamp::new(3, ManualT::from(1_000), 2))?;
+        let mut bytes = enc.into_inner();
+        bytes.pop();
+        let mut dec = DeltaDecoder::<_, ManualT>::new(Cursor::new(bytes));
+        assert_eq!(
+            dec.decode().map_err(|e| e.kind()),
+            Err(io::ErrorKind::UnexpectedEof)
+        );
+        Ok(())
+    }
+}
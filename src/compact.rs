@@ -0,0 +1,162 @@
+use std::fmt;
+
+use crate::{ByteOrdered, Clock, ClockSource, Error, Result, Timestamp, U64Time};
+
+/// A timestamp without an epoch, as issued by a `CompactClock`.
+///
+/// These are ordered by time, then by count, exactly as `Timestamp`s within
+/// a single epoch are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompactTimestamp<T> {
+    /// The Wall-clock time as returned by the clock source.
+    pub time: T,
+    /// A Lamport clock used to disambiguate events that are given the same
+    /// Wall-clock time. See `Timestamp::count`.
+    pub count: u32,
+}
+
+/// The length of the byte encoding of a `CompactTimestamp`.
+pub const COMPACT_TIMESTAMP_LEN: usize = 12;
+
+impl<T: U64Time> CompactTimestamp<T> {
+    /// Encodes the timestamp as big-endian time then count, such that the
+    /// encodings sort in the same order as the timestamps.
+    pub fn to_bytes(&self) -> [u8; COMPACT_TIMESTAMP_LEN] {
+        self.to_ordered_bytes()
+    }
+
+    /// Decodes a timestamp written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8; COMPACT_TIMESTAMP_LEN]) -> Self {
+        Self::from_ordered_bytes(bytes)
+    }
+}
+
+impl<T> From<CompactTimestamp<T>> for Timestamp<T> {
+    /// Places the timestamp in epoch zero.
+    fn from(ts: CompactTimestamp<T>) -> Self {
+        Timestamp {
+            epoch: 0,
+            time: ts.time,
+            count: ts.count,
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for CompactTimestamp<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}+{}", self.time, self.count)
+    }
+}
+
+/// A clock for deployments that never use epochs, which issues
+/// `CompactTimestamp`s. The underlying clock always stays in epoch zero.
+#[derive(Debug, Clone)]
+pub struct CompactClock<S: ClockSource> {
+    clock: Clock<S>,
+}
+
+impl<S: ClockSource> CompactClock<S> {
+    /// Creates a clock with `src` as the time provider.
+    pub fn new(src: S) -> Result<Self> {
+        Ok(CompactClock {
+            clock: Clock::new(src)?,
+        })
+    }
+
+    /// Creates a unique monotonic timestamp suitable for annotating messages we send.
+    pub fn now(&mut self) -> Result<CompactTimestamp<S::Time>> {
+        Ok(compact(self.clock.now()?))
+    }
+
+    /// Returns the most recently issued or observed timestamp. See `Clock::peek`.
+    pub fn peek(&self) -> CompactTimestamp<S::Time> {
+        compact(self.clock.peek())
+    }
+
+    /// Accepts a timestamp from an incoming message. See `Clock::observe`.
+    pub fn observe(&mut self, msg: &CompactTimestamp<S::Time>) -> Result<()> {
+        self.clock.observe(&(*msg).into())
+    }
+
+    /// Extract the inner `Clock`
+    pub fn into_inner(self) -> Clock<S> {
+        self.clock
+    }
+
+    /// Get a reference to the inner `Clock`
+    pub fn inner(&self) -> &Clock<S> {
+        &self.clock
+    }
+}
+
+impl<S: ClockSource> Clock<S> {
+    /// Converts this into a `CompactClock`, provided that it has never used
+    /// an epoch other than zero.
+    pub fn into_compact(self) -> Result<CompactClock<S>> {
+        let current = self.epoch.max(self.last_observed.epoch);
+        if current != 0 {
+            return Err(Error::EpochRegression {
+                current,
+                requested: 0,
+            });
+        }
+        Ok(CompactClock { clock: self })
+    }
+}
+
+fn compact<T>(ts: Timestamp<T>) -> CompactTimestamp<T> {
+    debug_assert_eq!(ts.epoch, 0);
+    CompactTimestamp {
+        time: ts.time,
+        count: ts.count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::manualts;
+    use crate::{ManualClock, ManualT};
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    fn compact_timestamps() -> Box<dyn GeneratorObject<Item = CompactTimestamp<ManualT>>> {
+        (manualts(), u32s())
+            .map(|(time, count)| CompactTimestamp { time, count })
+            .boxed()
+    }
+
+    #[test]
+    fn bytes_should_round_trip() {
+        property(compact_timestamps())
+            .check(|ts| CompactTimestamp::<ManualT>::from_bytes(&ts.to_bytes()) == ts);
+    }
+
+    #[test]
+    fn bytes_should_order_as_timestamps() {
+        property((compact_timestamps(), compact_timestamps()))
+            .check(|(a, b)| a.cmp(&b) == a.to_bytes().cmp(&b.to_bytes()));
+    }
+
+    #[test]
+    fn should_be_larger_than_observed_time() {
+        property((u64s(), compact_timestamps())).check(|(t0, observed)| -> Result<()> {
+            let mut clock = CompactClock::new(ManualClock::new(t0))?;
+            if observed.count == u32::MAX {
+                return Ok(());
+            }
+            clock.observe(&observed)?;
+            let t = clock.now()?;
+            assert!(t > observed, "{} > {}", t, observed);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn should_refuse_clocks_with_epochs() -> Result<()> {
+        let mut clock = Clock::new(ManualClock::new(0))?;
+        clock.set_epoch(1);
+        assert!(clock.into_compact().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use crate::packed::check_width;
+use crate::{Error, Result, Timestamp, WallNST};
+
+// CockroachDB writes the logical part of a decimal timestamp as a ten digit
+// fraction.
+const DECIMAL_LOGICAL_DIGITS: usize = 10;
+
+impl Timestamp<WallNST> {
+    /// Formats the timestamp in CockroachDB's `<wall>,<logical>` text form,
+    /// with the wall time in nanoseconds since the unix epoch.
+    ///
+    /// Fails with `Error::PackOverflow` if the epoch is not zero, as
+    /// CockroachDB has no equivalent.
+    pub fn to_crdb_string(&self) -> Result<String> {
+        check_width("epoch", u64::from(self.epoch), 0)?;
+        Ok(format!("{},{}", self.time.as_u64(), self.count))
+    }
+
+    /// Parses a CockroachDB HLC timestamp, in epoch zero. This accepts the
+    /// `<wall>,<logical>` form, the `<secs>.<nanos>,<logical>` form that
+    /// CockroachDB uses in logs and errors, and the `<wall>.<logical>`
+    /// decimal returned by `cluster_logical_timestamp()` and written to
+    /// change-feed payloads.
+    pub fn parse_crdb(s: &str) -> Result<Self> {
+        let invalid = || Error::Parse(s.to_owned());
+        let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        let (wall, logical) = match s.split_once(',') {
+            Some((wall, logical)) => {
+                let wall = match wall.split_once('.') {
+                    Some((secs, nanos)) if is_digits(secs) && is_digits(nanos) => {
+                        let secs = secs.parse::<u64>().map_err(|_| invalid())?;
+                        let nanos = format!("{:0<9}", nanos)
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|&n| n < 1_000_000_000)
+                            .ok_or_else(invalid)?;
+                        secs.checked_mul(1_000_000_000)
+                            .and_then(|n| n.checked_add(u64::from(nanos)))
+                            .ok_or_else(invalid)?
+                    }
+                    None if is_digits(wall) => wall.parse().map_err(|_| invalid())?,
+                    _ => return Err(invalid()),
+                };
+                if !is_digits(logical) {
+                    return Err(invalid());
+                }
+                (wall, logical.parse().map_err(|_| invalid())?)
+            }
+            None => {
+                let (wall, logical) = s.split_once('.').unwrap_or((s, "0"));
+                if !is_digits(wall) || !is_digits(logical) || logical.len() > DECIMAL_LOGICAL_DIGITS
+                {
+                    return Err(invalid());
+                }
+                let logical = format!("{:0<width$}", logical, width = DECIMAL_LOGICAL_DIGITS);
+                (
+                    wall.parse().map_err(|_| invalid())?,
+                    logical.parse().map_err(|_| invalid())?,
+                )
+            }
+        };
+        Ok(Timestamp::new(0, WallNST::of_u64(wall), logical))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    fn crdb_timestamps() -> Box<dyn GeneratorObject<Item = Timestamp<WallNST>>> {
+        (u64s(), u32s())
+            .map(|(wall, logical)| Timestamp::new(0, WallNST::of_u64(wall), logical))
+            .boxed()
+    }
+
+    #[test]
+    fn should_round_trip_via_crdb_string() {
+        property(crdb_timestamps()).check(|ts| {
+            let s = ts.to_crdb_string().expect("format");
+            Timestamp::parse_crdb(&s).expect("parse") == ts
+        });
+    }
+
+    #[test]
+    fn should_parse_crdb_forms() -> Result<()> {
+        let ts = Timestamp::new(0, WallNST::of_u64(1_556_116_588_736_372_000), 1);
+        assert_eq!(ts.to_crdb_string()?, "1556116588736372000,1");
+        assert_eq!(Timestamp::parse_crdb("1556116588736372000,1")?, ts);
+        assert_eq!(Timestamp::parse_crdb("1556116588.736372000,1")?, ts);
+        assert_eq!(Timestamp::parse_crdb("1556116588736372000.0000000001")?, ts);
+        assert_eq!(
+            Timestamp::parse_crdb("1556116588736372000")?,
+            Timestamp::new(0, ts.time, 0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_malformed_crdb_strings() {
+        for s in &[
+            "",
+            ",1",
+            "1,",
+            "1,-1",
+            "1.2.3,1",
+            "1.00000000001",
+            "1.9999999999",
+            "x,1",
+        ] {
+            assert!(Timestamp::parse_crdb(s).is_err(), "{:?}", s);
+        }
+        let ts = Timestamp::new(1, WallNST::of_u64(0), 0);
+        assert!(matches!(
+            ts.to_crdb_string(),
+            Err(Error::PackOverflow { field: "epoch", .. })
+        ));
+    }
+}
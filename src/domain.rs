@@ -0,0 +1,279 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Sub;
+use std::time::Duration;
+
+use crate::{AsDuration, ClockSource, Result, SharedClockSource, Timestamp, U64Time};
+
+/// Marks an independent clock domain, such as a cluster.
+///
+/// Timestamps issued in different domains have no causal relationship, so
+/// tagging them with their domain stops them being compared or observed
+/// across domains by accident.
+pub trait DomainTag {
+    /// A human readable name for the domain.
+    const NAME: &'static str;
+}
+
+/// Permits timestamps from the domain `D` to be converted into this domain.
+///
+/// Without an implementation of this trait, there is no way to move a
+/// tagged timestamp between domains short of explicitly removing the tag.
+pub trait FromDomain<D: DomainTag>: DomainTag {
+    /// Checks (and possibly adjusts) a timestamp as it is moved out of `D`.
+    /// The default implementation accepts every timestamp unchanged.
+    fn convert<T>(ts: Timestamp<T>) -> Result<Timestamp<T>> {
+        Ok(ts)
+    }
+}
+
+/// A clock time of type `T` that was issued in the domain `D`.
+pub struct InDomain<T, D> {
+    time: T,
+    domain: PhantomData<fn() -> D>,
+}
+
+/// A clock source whose times are tagged with the domain `D`.
+pub struct DomainSource<S, D> {
+    src: S,
+    domain: PhantomData<fn() -> D>,
+}
+
+impl<T, D> InDomain<T, D> {
+    /// Tags `time` as belonging to the domain `D`.
+    pub fn new(time: T) -> Self {
+        InDomain {
+            time,
+            domain: PhantomData,
+        }
+    }
+
+    /// Returns the untagged time.
+    pub fn into_inner(self) -> T {
+        self.time
+    }
+
+    /// Returns a reference to the untagged time.
+    pub fn inner(&self) -> &T {
+        &self.time
+    }
+}
+
+impl<T> Timestamp<T> {
+    /// Tags this timestamp as having been issued in the domain `D`.
+    pub fn in_domain<D: DomainTag>(self) -> Timestamp<InDomain<T, D>> {
+        Timestamp {
+            epoch: self.epoch,
+            time: InDomain::new(self.time),
+            count: self.count,
+        }
+    }
+}
+
+impl<T, D: DomainTag> Timestamp<InDomain<T, D>> {
+    /// Removes the domain tag from this timestamp.
+    pub fn untagged(self) -> Timestamp<T> {
+        Timestamp {
+            epoch: self.epoch,
+            time: self.time.into_inner(),
+            count: self.count,
+        }
+    }
+
+    /// Moves this timestamp into the domain `E`, as permitted and checked by
+    /// `E`'s implementation of `FromDomain<D>`.
+    pub fn into_domain<E: FromDomain<D>>(self) -> Result<Timestamp<InDomain<T, E>>> {
+        Ok(E::convert(self.untagged())?.in_domain())
+    }
+}
+
+impl<S, D> DomainSource<S, D> {
+    /// Wraps `src` so that the times it returns are tagged with `D`.
+    pub fn new(src: S) -> Self {
+        DomainSource {
+            src,
+            domain: PhantomData,
+        }
+    }
+
+    /// Extract the inner clock source.
+    pub fn into_inner(self) -> S {
+        self.src
+    }
+}
+
+impl<S: ClockSource, D: DomainTag> ClockSource for DomainSource<S, D> {
+    type Time = InDomain<S::Time, D>;
+    type Delta = S::Delta;
+    fn now(&mut self) -> Result<Self::Time> {
+        Ok(InDomain::new(self.src.now()?))
+    }
+    fn uncertainty(&self) -> Option<Self::Delta> {
+        self.src.uncertainty()
+    }
+}
+
+impl<S: SharedClockSource, D: DomainTag> SharedClockSource for DomainSource<S, D> {
+    fn now_shared(&self) -> Result<Self::Time> {
+        Ok(InDomain::new(self.src.now_shared()?))
+    }
+}
+
+impl<T: AsDuration, D> AsDuration for InDomain<T, D> {
+    fn as_duration(&self) -> Duration {
+        self.time.as_duration()
+    }
+}
+
+impl<T: U64Time, D> U64Time for InDomain<T, D> {
+    fn as_u64(self) -> u64 {
+        self.time.as_u64()
+    }
+    fn of_u64(val: u64) -> Self {
+        InDomain::new(T::of_u64(val))
+    }
+}
+
+impl<T: Clone, D> Clone for InDomain<T, D> {
+    fn clone(&self) -> Self {
+        InDomain::new(self.time.clone())
+    }
+}
+
+impl<T: Copy, D> Copy for InDomain<T, D> {}
+
+impl<T: PartialEq, D> PartialEq for InDomain<T, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.time == other.time
+    }
+}
+
+impl<T: Eq, D> Eq for InDomain<T, D> {}
+
+impl<T: PartialOrd, D> PartialOrd for InDomain<T, D> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.time.partial_cmp(&other.time)
+    }
+}
+
+impl<T: Ord, D> Ord for InDomain<T, D> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time.cmp(&other.time)
+    }
+}
+
+impl<T: Hash, D> Hash for InDomain<T, D> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.time.hash(state)
+    }
+}
+
+impl<T: Sub, D> Sub for InDomain<T, D> {
+    type Output = T::Output;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.time - rhs.time
+    }
+}
+
+impl<T: fmt::Debug, D: DomainTag> fmt::Debug for InDomain<T, D> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}::{:?}", D::NAME, self.time)
+    }
+}
+
+impl<T: fmt::Display, D> fmt::Display for InDomain<T, D> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.time.fmt(fmt)
+    }
+}
+
+impl<S: Clone, D> Clone for DomainSource<S, D> {
+    fn clone(&self) -> Self {
+        DomainSource::new(self.src.clone())
+    }
+}
+
+impl<S: fmt::Debug, D: DomainTag> fmt::Debug for DomainSource<S, D> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("DomainSource")
+            .field("src", &self.src)
+            .field("domain", &D::NAME)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Clock, Error, ManualClock, ManualT};
+
+    struct ClusterA;
+    struct ClusterB;
+    struct Archive;
+
+    impl DomainTag for ClusterA {
+        const NAME: &'static str = "cluster-a";
+    }
+    impl DomainTag for ClusterB {
+        const NAME: &'static str = "cluster-b";
+    }
+    impl DomainTag for Archive {
+        const NAME: &'static str = "archive";
+    }
+
+    impl FromDomain<ClusterA> for Archive {}
+    impl FromDomain<ClusterB> for Archive {
+        fn convert<T>(ts: Timestamp<T>) -> Result<Timestamp<T>> {
+            if ts.epoch == 0 {
+                Ok(ts)
+            } else {
+                Err(Error::DomainConversion {
+                    from: ClusterB::NAME,
+                    to: Archive::NAME,
+                })
+            }
+        }
+    }
+
+    #[test]
+    fn should_issue_tagged_timestamps() -> Result<()> {
+        let mut clock = Clock::new(DomainSource::<_, ClusterA>::new(ManualClock::new(10)))?;
+        let a = clock.now()?;
+        clock.observe(&a)?;
+        let b = clock.now()?;
+        assert!(a < b);
+        assert_eq!(
+            b.untagged(),
+            Timestamp {
+                epoch: 0,
+                time: ManualT::from(10),
+                count: 3,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_convert_between_permitted_domains() -> Result<()> {
+        let ts = Timestamp {
+            epoch: 0,
+            time: ManualT::from(3),
+            count: 1,
+        };
+        let archived = ts.in_domain::<ClusterA>().into_domain::<Archive>()?;
+        assert_eq!(archived.untagged(), ts);
+        Ok(())
+    }
+
+    #[test]
+    fn should_apply_domain_conversion_checks() {
+        let ts = Timestamp {
+            epoch: 1,
+            time: ManualT::from(3),
+            count: 1,
+        };
+        assert!(ts.in_domain::<ClusterB>().into_domain::<Archive>().is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::{Clock, ClockSource, ObservationOutcome, Result, Timestamp};
+
+/// A source of the current epoch, such as a lease generation handed out by a
+/// coordination service.
+///
+/// Any `FnMut() -> Result<u32>` is an `EpochProvider`. A shared
+/// `Arc<AtomicU32>` is one too, which allows the epoch to be pushed in from
+/// elsewhere (eg: a lease watcher) rather than polled.
+pub trait EpochProvider {
+    /// Returns the epoch that newly issued timestamps should use.
+    fn current_epoch(&mut self) -> Result<u32>;
+}
+
+impl<F: FnMut() -> Result<u32>> EpochProvider for F {
+    fn current_epoch(&mut self) -> Result<u32> {
+        self()
+    }
+}
+
+impl EpochProvider for Arc<AtomicU32> {
+    fn current_epoch(&mut self) -> Result<u32> {
+        Ok(self.load(Ordering::SeqCst))
+    }
+}
+
+/// A `Clock` that takes its epoch from an `EpochProvider`.
+///
+/// The provider is consulted before each timestamp is issued or observed,
+/// so the clock always uses the most recent epoch it has been given.
+#[derive(Debug)]
+pub struct EpochClock<S: ClockSource, P> {
+    clock: Clock<S>,
+    provider: P,
+}
+
+impl<S: ClockSource> Clock<S> {
+    /// Creates a clock with `src` as the time provider, whose epoch is taken
+    /// from `provider`.
+    pub fn with_epoch_provider<P: EpochProvider>(src: S, provider: P) -> Result<EpochClock<S, P>> {
+        EpochClock::new(Clock::new(src)?, provider)
+    }
+}
+
+impl<S: ClockSource, P: EpochProvider> EpochClock<S, P> {
+    /// Wraps `clock`, setting its epoch from `provider`.
+    pub fn new(clock: Clock<S>, provider: P) -> Result<Self> {
+        let mut res = EpochClock { clock, provider };
+        res.refresh()?;
+        Ok(res)
+    }
+
+    /// Creates a unique monotonic timestamp suitable for annotating messages
+    /// we send, in the provider's current epoch.
+    pub fn now(&mut self) -> Result<Timestamp<S::Time>> {
+        self.refresh()?;
+        self.clock.now()
+    }
+
+    /// Accepts a timestamp from an incoming message. See `Clock::observe`.
+    pub fn observe(&mut self, msg: &Timestamp<S::Time>) -> Result<()> {
+        self.refresh()?;
+        self.clock.observe(msg)
+    }
+
+    /// Accepts a timestamp from an incoming message, returning the resulting
+    /// timestamp and how it compared. See `Clock::observe_now`.
+    pub fn observe_now(
+        &mut self,
+        msg: &Timestamp<S::Time>,
+    ) -> Result<(Timestamp<S::Time>, ObservationOutcome)> {
+        self.refresh()?;
+        self.clock.observe_now(msg)
+    }
+
+    /// Returns the most recently issued or observed timestamp. See `Clock::peek`.
+    pub fn peek(&self) -> Timestamp<S::Time> {
+        self.clock.peek()
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        let epoch = self.provider.current_epoch()?;
+        self.clock.set_epoch(epoch);
+        Ok(())
+    }
+
+    /// Get a reference to the epoch provider
+    pub fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    /// Extract the inner `Clock`
+    pub fn into_inner(self) -> Clock<S> {
+        self.clock
+    }
+
+    /// Get a reference to the inner `Clock`
+    pub fn inner(&self) -> &Clock<S> {
+        &self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, ManualClock};
+
+    #[test]
+    fn should_follow_polled_epoch() -> Result<()> {
+        let mut epochs = vec![1, 2, 3].into_iter();
+        let mut clock = Clock::with_epoch_provider(ManualClock::new(10), move || {
+            epochs.next().ok_or(Error::EpochExhausted)
+        })?;
+        // The first epoch is consumed when the clock is constructed.
+        assert_eq!(clock.now()?.epoch, 2);
+        assert_eq!(clock.now()?.epoch, 3);
+        assert!(matches!(clock.now(), Err(Error::EpochExhausted)));
+        Ok(())
+    }
+
+    #[test]
+    fn should_follow_notified_epoch() -> Result<()> {
+        let epoch = Arc::new(AtomicU32::new(1));
+        let mut clock = Clock::with_epoch_provider(ManualClock::new(10), epoch.clone())?;
+        let a = clock.now()?;
+        epoch.store(2, Ordering::SeqCst);
+        let b = clock.now()?;
+        assert_eq!((a.epoch, b.epoch), (1, 2));
+        assert!(a < b);
+        Ok(())
+    }
+}
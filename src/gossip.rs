@@ -0,0 +1,285 @@
+//! Transport-agnostic clock synchronization between peers, by periodically
+//! exchanging timestamps.
+//!
+//! Each node runs a `GossipSync`, which issues a timestamp for its peers
+//! every interval, and ingests the timestamps its peers send through an
+//! `OffsetLimiter`, so that a peer whose clock is too far out cannot drag
+//! ours along with it. Along the way, it reports how far each peer's clock
+//! appears to be from ours, which is useful for monitoring skew across a
+//! cluster. Messages are passed in and out through callbacks, so any
+//! transport may be used.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::{
+    AsDuration, ClockSource, Error, FixedOffsetPolicy, OffsetLimiter, OffsetPolicy, Result, Skew,
+    Timestamp,
+};
+
+/// What we have learnt about a peer's clock from its timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerReport {
+    /// How far the physical time of the peer's latest timestamp was from
+    /// ours when it arrived. This includes the time it spent in transit.
+    pub offset: Skew<Duration>,
+    /// The number of the peer's timestamps that we have merged.
+    pub accepted: u64,
+    /// The number of the peer's timestamps refused by the offset policy.
+    pub rejected: u64,
+}
+
+/// Exchanges timestamps with peers to keep our clock in line with theirs.
+pub struct GossipSync<S: ClockSource, Id: Ord, P = FixedOffsetPolicy<<S as ClockSource>::Delta>> {
+    limiter: OffsetLimiter<S, P>,
+    interval: Duration,
+    next_send: Option<Instant>,
+    peers: BTreeMap<Id, PeerReport>,
+}
+
+impl<S, Id, P> fmt::Debug for GossipSync<S, Id, P>
+where
+    S: ClockSource + fmt::Debug,
+    S::Delta: fmt::Debug,
+    Id: Ord + fmt::Debug,
+    P: fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("GossipSync")
+            .field("limiter", &self.limiter)
+            .field("interval", &self.interval)
+            .field("next_send", &self.next_send)
+            .field("peers", &self.peers)
+            .finish()
+    }
+}
+
+impl<S: ClockSource, Id: Ord, P: OffsetPolicy<S::Time, S::Delta>> GossipSync<S, Id, P>
+where
+    S::Time: AsDuration,
+    S::Delta: AsDuration,
+{
+    /// Sends a timestamp from `limiter` every `interval`, and merges peers'
+    /// timestamps into it.
+    pub fn new(limiter: OffsetLimiter<S, P>, interval: Duration) -> Self {
+        GossipSync {
+            limiter,
+            interval,
+            next_send: None,
+            peers: BTreeMap::new(),
+        }
+    }
+
+    /// Returns true if we are due to send our timestamp at `now`.
+    pub fn is_due_at(&self, now: Instant) -> bool {
+        self.next_send.is_none_or(|next| now >= next)
+    }
+
+    /// Sends our timestamp via `send`, if we are due to. Returns the
+    /// timestamp sent, if any.
+    pub fn tick<F>(&mut self, send: F) -> Result<Option<Timestamp<S::Time>>>
+    where
+        F: FnMut(&Timestamp<S::Time>) -> Result<()>,
+    {
+        self.tick_at(Instant::now(), send)
+    }
+
+    /// As `tick`, but as if called at `now`.
+    pub fn tick_at<F>(&mut self, now: Instant, mut send: F) -> Result<Option<Timestamp<S::Time>>>
+    where
+        F: FnMut(&Timestamp<S::Time>) -> Result<()>,
+    {
+        if !self.is_due_at(now) {
+            return Ok(None);
+        }
+        let ts = self.limiter.now()?;
+        send(&ts)?;
+        self.next_send = Some(now + self.interval);
+        Ok(Some(ts))
+    }
+
+    /// Merges a timestamp received from `peer` into our clock, recording
+    /// its offset from our own. Returns an error if the offset policy
+    /// refuses it, in which case it is counted against the peer.
+    pub fn ingest(&mut self, peer: Id, msg: &Timestamp<S::Time>) -> Result<()> {
+        let pt = self.limiter.inner_mut().src.now()?;
+        let offset = if msg.time >= pt {
+            Skew::Ahead((msg.time - pt).as_duration())
+        } else {
+            Skew::Behind((pt - msg.time).as_duration())
+        };
+        let res = self.limiter.observe(msg);
+        let report = self.peers.entry(peer).or_insert(PeerReport {
+            offset,
+            accepted: 0,
+            rejected: 0,
+        });
+        report.offset = offset;
+        match res {
+            Ok(()) => report.accepted += 1,
+            Err(Error::OffsetTooGreat { .. }) | Err(Error::OffsetTooFarBehind { .. }) => {
+                report.rejected += 1
+            }
+            Err(_) => {}
+        }
+        res
+    }
+
+    /// Sends our timestamp via `send` if we are due to, then ingests every
+    /// timestamp that `recv` returns until it returns `None`. Timestamps
+    /// refused by the offset policy are counted against their peer, but do
+    /// not stop the exchange. Returns the number of timestamps merged.
+    pub fn sync<F, R>(&mut self, send: F, recv: R) -> Result<usize>
+    where
+        F: FnMut(&Timestamp<S::Time>) -> Result<()>,
+        R: FnMut() -> Option<(Id, Timestamp<S::Time>)>,
+    {
+        self.sync_at(Instant::now(), send, recv)
+    }
+
+    /// As `sync`, but as if called at `now`.
+    pub fn sync_at<F, R>(&mut self, now: Instant, send: F, mut recv: R) -> Result<usize>
+    where
+        F: FnMut(&Timestamp<S::Time>) -> Result<()>,
+        R: FnMut() -> Option<(Id, Timestamp<S::Time>)>,
+    {
+        self.tick_at(now, send)?;
+        let mut merged = 0;
+        while let Some((peer, msg)) = recv() {
+            match self.ingest(peer, &msg) {
+                Ok(()) => merged += 1,
+                Err(Error::OffsetTooGreat { .. }) | Err(Error::OffsetTooFarBehind { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(merged)
+    }
+}
+
+impl<S: ClockSource, Id: Ord, P> GossipSync<S, Id, P> {
+    /// Returns what we have learnt about `peer`'s clock, if we have heard
+    /// from it.
+    pub fn peer(&self, peer: &Id) -> Option<&PeerReport> {
+        self.peers.get(peer)
+    }
+
+    /// Iterates over each peer we have heard from, in order of peer id.
+    pub fn peers(&self) -> impl Iterator<Item = (&Id, &PeerReport)> {
+        self.peers.iter()
+    }
+
+    /// Forgets what we have learnt about `peer`.
+    pub fn remove_peer(&mut self, peer: &Id) -> Option<PeerReport> {
+        self.peers.remove(peer)
+    }
+
+    /// Returns the interval between sending our timestamp.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Extract the inner `OffsetLimiter`
+    pub fn into_inner(self) -> OffsetLimiter<S, P> {
+        self.limiter
+    }
+
+    /// Get a reference to the inner `OffsetLimiter`
+    pub fn inner(&self) -> &OffsetLimiter<S, P> {
+        &self.limiter
+    }
+
+    /// Get a mutable reference to the inner `OffsetLimiter`
+    pub fn inner_mut(&mut self) -> &mut OffsetLimiter<S, P> {
+        &mut self.limiter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Clock, ManualClock, ManualT};
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+
+    type Node = GossipSync<Arc<ManualClock>, &'static str>;
+
+    fn node(time: &Arc<ManualClock>) -> Node {
+        let clock = Clock::new(time.clone()).expect("clock").with_max_diff(100);
+        GossipSync::new(clock, Duration::from_secs(1))
+    }
+
+    #[test]
+    fn should_send_once_per_interval() -> Result<()> {
+        let time = Arc::new(ManualClock::new(10));
+        let mut a = node(&time);
+        let start = Instant::now();
+        let mut sent = Vec::new();
+        for dt in [0, 500, 999, 1000, 1500] {
+            a.tick_at(start + Duration::from_millis(dt), |ts| {
+                sent.push(*ts);
+                Ok(())
+            })?;
+        }
+        assert_eq!(sent.len(), 2, "{:?}", sent);
+        Ok(())
+    }
+
+    #[test]
+    fn should_exchange_timestamps_and_report_offsets() -> Result<()> {
+        let (ta, tb) = (
+            Arc::new(ManualClock::new(1000)),
+            Arc::new(ManualClock::new(1040)),
+        );
+        let (mut a, mut b) = (node(&ta), node(&tb));
+        let mut to_a = VecDeque::new();
+        let mut to_b = VecDeque::new();
+        let now = Instant::now();
+
+        b.sync_at(
+            now,
+            |ts| {
+                to_a.push_back(("b", *ts));
+                Ok(())
+            },
+            || None,
+        )?;
+        let merged = a.sync_at(
+            now,
+            |ts| {
+                to_b.push_back(("a", *ts));
+                Ok(())
+            },
+            || to_a.pop_front(),
+        )?;
+        assert_eq!(merged, 1);
+        assert_eq!(
+            a.peer(&"b").map(|r| r.offset),
+            Some(Skew::Ahead(Duration::from_nanos(40)))
+        );
+        b.sync_at(now, |_| Ok(()), || to_b.pop_front())?;
+        assert_eq!(
+            b.peer(&"a").map(|r| r.offset),
+            Some(Skew::Behind(Duration::from_nanos(40)))
+        );
+        assert!(a.inner_mut().now()?.time >= ManualT::from(1040));
+        Ok(())
+    }
+
+    #[test]
+    fn should_count_refused_timestamps_against_peer() -> Result<()> {
+        let time = Arc::new(ManualClock::new(1000));
+        let mut a = node(&time);
+        let mut inbox = vec![
+            ("c", Timestamp::new(0, ManualT::from(1050), 0)),
+            ("b", Timestamp::new(0, ManualT::from(5000), 0)),
+        ];
+        let merged = a.sync_at(Instant::now(), |_| Ok(()), || inbox.pop())?;
+        assert_eq!(merged, 1);
+        let b = a.peer(&"b").expect("b");
+        assert_eq!((b.accepted, b.rejected), (0, 1));
+        assert_eq!(b.offset, Skew::Ahead(Duration::from_nanos(4000)));
+        assert_eq!(a.inner().stats().rejected, 1);
+        Ok(())
+    }
+}
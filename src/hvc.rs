@@ -0,0 +1,217 @@
+//! Hybrid vector clocks, which track the latest timestamp known from each
+//! node, as described alongside hybrid logical clocks in "Logical Physical
+//! Clocks and Consistent Snapshots in Globally Distributed Databases".
+//!
+//! Unlike a single `Timestamp`, these can tell whether two events are
+//! causally related or concurrent.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::{Clock, ClockSource, Result, Timestamp};
+
+/// The latest timestamp known from each node. Nodes without an entry are
+/// treated as having issued no timestamps.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serialization",
+    serde(bound(
+        serialize = "T: serde::Serialize + Copy, Id: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, Id: serde::Deserialize<'de>"
+    ))
+)]
+pub struct VectorTimestamp<T, Id: Ord> {
+    entries: BTreeMap<Id, Timestamp<T>>,
+}
+
+impl<T: Ord + Copy, Id: Ord + Clone> VectorTimestamp<T, Id> {
+    /// Returns an empty vector timestamp, which happened before every other.
+    pub fn new() -> Self {
+        VectorTimestamp {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the latest timestamp known from `node`.
+    pub fn get(&self, node: &Id) -> Option<&Timestamp<T>> {
+        self.entries.get(node)
+    }
+
+    /// Iterates over the latest timestamp known from each node.
+    pub fn iter(&self) -> impl Iterator<Item = (&Id, &Timestamp<T>)> {
+        self.entries.iter()
+    }
+
+    /// Returns the number of nodes with an entry.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no node has an entry.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Records `timestamp` for `node`, if it is later than what we have.
+    pub fn update(&mut self, node: Id, timestamp: Timestamp<T>) {
+        let entry = self.entries.entry(node).or_insert(timestamp);
+        *entry = (*entry).max(timestamp);
+    }
+
+    /// Takes the latest timestamp from either side for each node.
+    pub fn merge(&mut self, other: &Self) {
+        for (node, ts) in other.entries.iter() {
+            self.update(node.clone(), *ts);
+        }
+    }
+
+    /// Compares two vector timestamps by causality, returning `None` if
+    /// they are concurrent.
+    pub fn partial_cmp_causal(&self, other: &Self) -> Option<Ordering> {
+        let mut res = Ordering::Equal;
+        let nodes = self.entries.keys().chain(other.entries.keys());
+        for node in nodes {
+            let ord = self.entries.get(node).cmp(&other.entries.get(node));
+            res = match (res, ord) {
+                (res, Ordering::Equal) => res,
+                (Ordering::Equal, ord) => ord,
+                (res, ord) if res == ord => res,
+                _ => return None,
+            };
+        }
+        Some(res)
+    }
+
+    /// Returns true if every event known to `self` is known to `other`, and
+    /// `other` knows of more.
+    pub fn happened_before(&self, other: &Self) -> bool {
+        self.partial_cmp_causal(other) == Some(Ordering::Less)
+    }
+
+    /// Returns true if neither happened before the other.
+    pub fn concurrent(&self, other: &Self) -> bool {
+        self.partial_cmp_causal(other).is_none()
+    }
+
+    /// Forgets entries earlier than `horizon`, which the paper does for
+    /// entries more than the maximum clock skew behind our own, as their
+    /// events are known to have happened before any new ones. This keeps
+    /// the vector small, at the cost of comparisons with old vectors.
+    pub fn prune_before(&mut self, horizon: &Timestamp<T>) {
+        self.entries.retain(|_, ts| *ts >= *horizon);
+    }
+}
+
+impl<T: Ord + Copy, Id: Ord + Clone> Default for VectorTimestamp<T, Id> {
+    fn default() -> Self {
+        VectorTimestamp::new()
+    }
+}
+
+/// A hybrid vector clock for the node `node`, which uses a `Clock` for its
+/// own entry.
+#[derive(Debug, Clone)]
+pub struct HvcClock<S: ClockSource, Id: Ord> {
+    clock: Clock<S>,
+    node: Id,
+    vector: VectorTimestamp<S::Time, Id>,
+}
+
+impl<S: ClockSource, Id: Ord + Clone> HvcClock<S, Id> {
+    /// Creates a vector clock for `node`, using `clock` for its own entry.
+    pub fn new(clock: Clock<S>, node: Id) -> Self {
+        HvcClock {
+            clock,
+            node,
+            vector: VectorTimestamp::new(),
+        }
+    }
+
+    /// Returns this node's id.
+    pub fn node(&self) -> &Id {
+        &self.node
+    }
+
+    /// Records a local event, returning the vector timestamp for it.
+    pub fn now(&mut self) -> Result<VectorTimestamp<S::Time, Id>> {
+        let ts = self.clock.now()?;
+        self.vector.update(self.node.clone(), ts);
+        Ok(self.vector.clone())
+    }
+
+    /// Merges in a vector timestamp received from another node, advancing
+    /// our clock past all of its entries.
+    pub fn observe(&mut self, msg: &VectorTimestamp<S::Time, Id>) -> Result<()> {
+        if let Some(latest) = msg.entries.values().max() {
+            self.clock.observe(latest)?;
+        }
+        self.vector.merge(msg);
+        Ok(())
+    }
+
+    /// Returns the current vector timestamp, without recording an event.
+    pub fn peek(&self) -> &VectorTimestamp<S::Time, Id> {
+        &self.vector
+    }
+
+    /// Returns the underlying clock.
+    pub fn inner(&self) -> &Clock<S> {
+        &self.clock
+    }
+
+    /// Returns the underlying clock.
+    pub fn inner_mut(&mut self) -> &mut Clock<S> {
+        &mut self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ManualClock, ManualT};
+
+    fn clock(node: u32, t: u64) -> Result<HvcClock<ManualClock, u32>> {
+        Ok(HvcClock::new(Clock::new(ManualClock::new(t))?, node))
+    }
+
+    #[test]
+    fn should_track_causality_through_messages() -> Result<()> {
+        let (mut a, mut b) = (clock(1, 10)?, clock(2, 5)?);
+        let sent = a.now()?;
+        b.observe(&sent)?;
+        let reply = b.now()?;
+        assert!(sent.happened_before(&reply));
+        assert!(!reply.happened_before(&sent));
+        assert!(!sent.concurrent(&reply));
+        assert!(reply.get(&1) == sent.get(&1));
+        Ok(())
+    }
+
+    #[test]
+    fn should_detect_concurrent_events() -> Result<()> {
+        let (mut a, mut b) = (clock(1, 10)?, clock(2, 20)?);
+        let ea = a.now()?;
+        let eb = b.now()?;
+        assert!(ea.concurrent(&eb));
+        assert!(!ea.happened_before(&eb) && !eb.happened_before(&ea));
+
+        let mut merged = ea.clone();
+        merged.merge(&eb);
+        assert!(ea.happened_before(&merged) && eb.happened_before(&merged));
+        assert_eq!(merged.partial_cmp_causal(&merged), Some(Ordering::Equal));
+        Ok(())
+    }
+
+    #[test]
+    fn should_prune_old_entries() {
+        let ts = |t: u64| Timestamp::new(0, ManualT::from(t), 0);
+        let mut v = VectorTimestamp::new();
+        v.update(1u32, ts(5));
+        v.update(2, ts(10));
+        v.update(2, ts(7));
+        assert_eq!(v.get(&2), Some(&ts(10)));
+        v.prune_before(&ts(8));
+        assert_eq!(v.iter().map(|(n, _)| *n).collect::<Vec<_>>(), vec![2]);
+    }
+}
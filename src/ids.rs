@@ -0,0 +1,129 @@
+use std::convert::TryFrom;
+
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::packed::check_width;
+use crate::{AsDuration, Clock, ClockSource, Result, Timestamp};
+
+/// The number of bits of a ULID's random component that hold the time
+/// within the millisecond, in nanoseconds.
+pub const ULID_SUBMILLI_BITS: u32 = 20;
+/// The number of bits of a ULID's random component that are actually random.
+pub const ULID_RANDOM_BITS: u32 = 80 - ULID_SUBMILLI_BITS - 32;
+
+const NANOS_PER_MILLI: u32 = 1_000_000;
+
+impl<T: AsDuration> Timestamp<T> {
+    /// Returns a ULID whose time component is the timestamp's physical time
+    /// in milliseconds. The first 52 bits of the "random" component hold the
+    /// nanoseconds within that millisecond and the count, so that ULIDs sort
+    /// in the same order as the timestamps they were made from; only the
+    /// last `ULID_RANDOM_BITS` bits are taken from `random`.
+    ///
+    /// The epoch is not represented, so ULIDs made either side of an epoch
+    /// change are ordered by time alone. Fails with `Error::PackOverflow` if
+    /// the time is beyond the year 10889.
+    pub fn to_ulid(&self, random: u32) -> Result<Ulid> {
+        let since_epoch = self.time.as_duration();
+        let millis = u64::try_from(since_epoch.as_millis()).unwrap_or(u64::MAX);
+        let millis = check_width("time", millis, u32::from(Ulid::TIME_BITS))?;
+        let submilli = since_epoch.subsec_nanos() % NANOS_PER_MILLI;
+        let random = u128::from(random) & ((1 << ULID_RANDOM_BITS) - 1);
+        let rest = (u128::from(submilli) << 32 | u128::from(self.count)) << ULID_RANDOM_BITS;
+        Ok(Ulid::from_parts(millis, rest | random))
+    }
+}
+
+impl<S: ClockSource> Clock<S>
+where
+    S::Time: AsDuration,
+{
+    /// Issues a timestamp, as per `now`, and returns it as a ULID; see
+    /// `Timestamp::to_ulid`. Successive ULIDs from a clock are strictly
+    /// increasing, even within a millisecond, and are ordered consistently
+    /// with timestamps issued by or observed by this clock.
+    pub fn next_ulid<R: RngCore>(&mut self, rng: &mut R) -> Result<Ulid> {
+        self.now()?.to_ulid(rng.next_u32())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::ManualClock;
+    use crate::tests::manualts;
+    use crate::{Error, ManualT, WallNST};
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    struct Counter(u64);
+
+    impl RngCore for Counter {
+        fn next_u32(&mut self) -> u32 {
+            self.next_u64() as u32
+        }
+        fn next_u64(&mut self) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1);
+            self.0 >> 16
+        }
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            rand_core::impls::fill_bytes_via_next(self, dest)
+        }
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand_core::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    fn ulid_timestamps() -> Box<dyn GeneratorObject<Item = Timestamp<ManualT>>> {
+        (manualts(), u32s())
+            .map(|(time, count)| Timestamp::new(0, time, count))
+            .boxed()
+    }
+
+    #[test]
+    fn ulids_should_order_as_timestamps() {
+        property((ulid_timestamps(), ulid_timestamps(), u32s(), u32s())).check(
+            |(ta, tb, ra, rb)| {
+                let (ua, ub) = (ta.to_ulid(ra).expect("ulid"), tb.to_ulid(rb).expect("ulid"));
+                ta == tb || ta.cmp(&tb) == ua.cmp(&ub)
+            },
+        );
+    }
+
+    #[test]
+    fn should_use_wall_milliseconds() -> Result<()> {
+        let ts = Timestamp::new(0, WallNST::of_u64(1_719_847_926_123_456_789), 7);
+        let ulid = ts.to_ulid(0)?;
+        assert_eq!(ulid.timestamp_ms(), 1_719_847_926_123);
+        assert_eq!(ulid.random() >> ULID_RANDOM_BITS, 456_789 << 32 | 7);
+        Ok(())
+    }
+
+    #[test]
+    fn should_be_monotonic_within_a_millisecond() -> Result<()> {
+        let mut clock = Clock::new(ManualClock::new(1_000_000_000))?;
+        let mut rng = Counter(0);
+        let mut prev = clock.next_ulid(&mut rng)?;
+        for _ in 0..100 {
+            let next = clock.next_ulid(&mut rng)?;
+            assert!(prev < next, "{} < {}", prev, next);
+            assert_eq!(next.timestamp_ms(), 1_000);
+            prev = next;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_times_beyond_ulid_range() {
+        let ts = Timestamp::new(0, std::time::Duration::from_millis(1 << 48), 0);
+        assert!(matches!(
+            ts.to_ulid(0),
+            Err(Error::PackOverflow { field: "time", .. })
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::ops::{Bound, Range, RangeBounds};
+
+use crate::Timestamp;
+
+/// A half-open interval of timestamps, which includes `start` but excludes
+/// `end`. Intervals with `start >= end` are empty.
+///
+/// This implements `RangeBounds`, so may be used directly with eg:
+/// `BTreeMap::range` to read a snapshot of timestamped entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimestampInterval<T> {
+    /// The first timestamp in the interval.
+    pub start: Timestamp<T>,
+    /// The first timestamp after the interval.
+    pub end: Timestamp<T>,
+}
+
+impl<T: Ord> TimestampInterval<T> {
+    /// Creates the interval from `start` up to, but excluding, `end`.
+    pub fn new(start: Timestamp<T>, end: Timestamp<T>) -> Self {
+        TimestampInterval { start, end }
+    }
+
+    /// Returns true if the interval contains no timestamps.
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    /// Returns true if `ts` lies within the interval.
+    pub fn contains(&self, ts: &Timestamp<T>) -> bool {
+        &self.start <= ts && ts < &self.end
+    }
+
+    /// Returns true if some timestamp lies within both intervals.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        !self.is_empty() && !other.is_empty() && self.start < other.end && other.start < self.end
+    }
+}
+
+impl<T> From<Range<Timestamp<T>>> for TimestampInterval<T> {
+    fn from(range: Range<Timestamp<T>>) -> Self {
+        TimestampInterval {
+            start: range.start,
+            end: range.end,
+        }
+    }
+}
+
+impl<T> From<TimestampInterval<T>> for Range<Timestamp<T>> {
+    fn from(interval: TimestampInterval<T>) -> Self {
+        interval.start..interval.end
+    }
+}
+
+impl<T> RangeBounds<Timestamp<T>> for TimestampInterval<T> {
+    fn start_bound(&self) -> Bound<&Timestamp<T>> {
+        Bound::Included(&self.start)
+    }
+    fn end_bound(&self) -> Bound<&Timestamp<T>> {
+        Bound::Excluded(&self.end)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for TimestampInterval<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "[{}, {})", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::ManualT;
+    use std::collections::BTreeSet;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    fn ts(time: u64, count: u32) -> Timestamp<ManualT> {
+        Timestamp {
+            epoch: 0,
+            time: time.into(),
+            count,
+        }
+    }
+
+    fn intervals() -> Box<dyn GeneratorObject<Item = TimestampInterval<ManualT>>> {
+        (timestamps(manualts()), timestamps(manualts()))
+            .map(|(start, end)| TimestampInterval::new(start, end))
+            .boxed()
+    }
+
+    #[test]
+    fn should_be_half_open() {
+        let interval = TimestampInterval::new(ts(1, 0), ts(2, 0));
+        assert!(interval.contains(&ts(1, 0)));
+        assert!(interval.contains(&ts(1, 9)));
+        assert!(!interval.contains(&ts(2, 0)));
+        assert!(!interval.overlaps(&TimestampInterval::new(ts(2, 0), ts(3, 0))));
+        assert!(interval.overlaps(&TimestampInterval::new(ts(1, 5), ts(1, 6))));
+        assert!(TimestampInterval::new(ts(2, 0), ts(2, 0)).is_empty());
+    }
+
+    #[test]
+    fn should_overlap_when_sharing_a_timestamp() {
+        property((intervals(), intervals(), timestamps(manualts()))).check(|(a, b, t)| {
+            if a.contains(&t) && b.contains(&t) {
+                assert!(a.overlaps(&b), "{} {} {}", a, b, t);
+            }
+            assert_eq!(a.overlaps(&b), b.overlaps(&a));
+        });
+    }
+
+    #[test]
+    fn should_select_same_range_as_contains() {
+        property((intervals(), vecs(timestamps(manualts())))).check(|(interval, all)| {
+            let set = all.iter().cloned().collect::<BTreeSet<_>>();
+            if interval.is_empty() {
+                return;
+            }
+            let selected = set.range(interval).cloned().collect::<Vec<_>>();
+            let expected = all
+                .iter()
+                .filter(|t| interval.contains(t))
+                .cloned()
+                .collect::<BTreeSet<_>>();
+            assert_eq!(selected, expected.into_iter().collect::<Vec<_>>());
+        });
+    }
+}
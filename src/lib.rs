@@ -13,26 +13,176 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 #[cfg(all(feature = "serialization", test))]
+extern crate bincode;
+#[cfg(all(feature = "serialization", test))]
+extern crate postcard;
+#[cfg(all(feature = "serialization", test))]
 extern crate serde_json;
 
 use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::fmt;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
 
 use thiserror::Error;
 
+#[cfg(feature = "async")]
+mod async_clock;
+#[cfg(feature = "atomic")]
+mod atomic;
+mod base32;
+mod builder;
+mod causal;
+pub mod codec;
+mod compact;
+mod crdb;
+mod domain;
+mod epoch;
+pub mod gossip;
+pub mod hvc;
+/// Cap'n Proto messages for timestamps, generated from
+/// `proto/hybrid_clocks.capnp` with `capnp compile -orust:src
+/// --src-prefix=proto proto/hybrid_clocks.capnp`. See `Timestamp::write_capnp`.
+#[cfg(feature = "capnp")]
+#[allow(clippy::all)]
+#[rustfmt::skip]
+pub mod hybrid_clocks_capnp;
+#[cfg(feature = "ulid")]
+mod ids;
+mod interval;
+mod metrics;
+mod node;
+mod ordered;
+mod packed;
+pub mod persist;
+mod policy;
+#[cfg(feature = "proto")]
+pub mod proto;
+mod raw;
+#[cfg(feature = "serialization")]
+pub mod serde_fixed;
+#[cfg(feature = "serialization")]
+pub mod serde_str;
+#[cfg(feature = "async-tokio")]
+mod service;
+mod shared;
+pub mod snapshot;
+mod snowflake;
 mod source;
+mod uncertain;
+mod watermark;
+pub mod wire;
+#[cfg(feature = "async")]
+pub use crate::async_clock::*;
+#[cfg(feature = "atomic")]
+pub use crate::atomic::*;
+pub use crate::builder::*;
+#[cfg(feature = "capnp")]
+pub use crate::capnp_impl::CapnpTime;
+pub use crate::causal::*;
+pub use crate::compact::*;
+pub use crate::domain::*;
+pub use crate::epoch::*;
+#[cfg(feature = "ulid")]
+pub use crate::ids::*;
+pub use crate::interval::*;
+pub use crate::metrics::*;
+pub use crate::node::*;
+pub use crate::ordered::*;
+pub use crate::packed::*;
+pub use crate::policy::*;
+pub use crate::raw::*;
+#[cfg(feature = "async-tokio")]
+pub use crate::service::*;
+pub use crate::shared::*;
+pub use crate::snowflake::*;
 pub use crate::source::*;
+pub use crate::uncertain::*;
+pub use crate::watermark::*;
 
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("Offset greater than limit")]
-    OffsetTooGreat,
+    #[error("Offset {observed:?} greater than limit {limit:?} for timestamp {timestamp:?}")]
+    OffsetTooGreat {
+        /// How far the timestamp was ahead of our physical time.
+        observed: Duration,
+        /// The configured limit.
+        limit: Duration,
+        /// The offending timestamp, with its time given as the duration
+        /// since the clock source's epoch.
+        timestamp: Timestamp<Duration>,
+    },
+    #[error("Offset {observed:?} behind us exceeds limit {limit:?} for timestamp {timestamp:?}")]
+    OffsetTooFarBehind {
+        /// How far the timestamp was behind our physical time.
+        observed: Duration,
+        /// The configured limit.
+        limit: Duration,
+        /// The offending timestamp, with its time given as the duration
+        /// since the clock source's epoch.
+        timestamp: Timestamp<Duration>,
+    },
+    #[error("Local clock jumped forward by {observed:?}, beyond limit {limit:?}")]
+    LocalClockJump {
+        /// How far the clock source advanced beyond the time that actually
+        /// elapsed since it was last read.
+        observed: Duration,
+        /// The configured limit.
+        limit: Duration,
+    },
     #[error("Outside of specified offset")]
     SystemTime(#[from] std::time::SystemTimeError),
     #[error("Integer conversion error")]
     FromInt(#[from] std::num::TryFromIntError),
     #[error("Outside supported time range: {0}ticks")]
     SupportedTime(u128),
+    #[error("Logical counter exhausted")]
+    CounterExhausted,
+    #[error("Logical counter {count} exceeds limit of {limit}")]
+    CountLimitExceeded { count: u32, limit: u32 },
+    #[error("Cannot pack {field} {value} into {bits} bits")]
+    PackOverflow {
+        field: &'static str,
+        value: u64,
+        bits: u32,
+    },
+    #[error("Unsupported wire format (magic {magic:#04x}, version {version})")]
+    UnsupportedWireFormat { magic: u8, version: u8 },
+    #[error("Expected timestamp from source kind {expected}, found {found}")]
+    SourceKindMismatch { expected: i32, found: i32 },
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("Persisted clock state is corrupt")]
+    CorruptState,
+    #[error("Clock is not synchronized (maximum error {max_error:?})")]
+    Unsynchronized {
+        /// The maximum error reported for the clock.
+        max_error: Duration,
+    },
+    #[error("No invariant timestamp counter is available")]
+    TscUnavailable,
+    #[error("The performance timer is not available")]
+    PerformanceUnavailable,
+    #[error("No further epochs available")]
+    EpochExhausted,
+    #[error("Cannot parse timestamp from {0:?}")]
+    Parse(String),
+    #[error("Epoch {requested} is behind current epoch {current}")]
+    EpochRegression { current: u32, requested: u32 },
+    #[error("Cannot convert timestamp from domain {from} to {to}")]
+    DomainConversion {
+        from: &'static str,
+        to: &'static str,
+    },
+    #[error("Clock service has shut down")]
+    ServiceStopped,
+    #[error("Clock source did not pass {target:?} before the deadline")]
+    DeadlineExceeded {
+        /// The physical time, plus error bound, that we were waiting for.
+        target: Duration,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -45,7 +195,26 @@ pub type Result<T> = std::result::Result<T, Error>;
 ///
 ///  * `a` happenned concurrently with `b`, or
 ///  * `a` is part of `b`'s causal history, or vica-versa.
+///
+/// With the `borsh` feature, timestamps are encoded as the epoch, the time
+/// and the count, each little-endian, eg: 16 bytes for `WallNST` times. This
+/// layout is stable between releases.
+///
+/// With postcard, the serde encoding is the epoch, the time ticks and the
+/// count, each as a varint, so small epochs and counts take a single byte.
+/// For present-day times with an epoch and count below 128, this is 11 bytes
+/// for `WallNST` times, 10 for `WallUST`, 9 for `WallMST` and 8 for
+/// `WallMillisT`, and at most 20 bytes for any `U64Time`. Use `serde_fixed`
+/// for frames of exactly 16 bytes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Timestamp<T> {
     /// An epoch counter.
     pub epoch: u32,
@@ -56,19 +225,86 @@ pub struct Timestamp<T> {
     pub count: u32,
 }
 
+/// Describes how an observed timestamp compared with the clock's own state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObservationOutcome {
+    /// The observed timestamp was ahead of the clock, and advanced it.
+    RemoteAhead,
+    /// The observed timestamp was already in the clock's past.
+    LocalAhead,
+    /// The observed timestamp was the same as the clock's last timestamp.
+    Equal,
+}
+
+/// The componentwise difference between two timestamps, as returned by
+/// `Timestamp::diff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimestampDiff<D> {
+    /// The first timestamp's epoch minus the second's.
+    pub epochs: i64,
+    /// The magnitude of the difference between the physical times.
+    pub time: D,
+    /// How the first timestamp's physical time compares with the second's.
+    pub time_direction: Ordering,
+    /// The first timestamp's logical counter minus the second's.
+    pub counts: i64,
+}
+
 /// The main clock type.
 #[derive(Debug, Clone)]
 pub struct Clock<S: ClockSource> {
     src: S,
     epoch: u32,
     last_observed: Timestamp<S::Time>,
+    max_count: u32,
+    // The most recent reading from the clock source, to detect regressions.
+    last_reading: Option<S::Time>,
+    metrics: ClockMetrics,
 }
 
+/// The state of a `Clock`, independent of its clock source. Restoring a
+/// clock from a saved state ensures that it will only issue timestamps that
+/// `happen-after` those it had issued or observed when the state was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClockState<T> {
+    /// The epoch applied to physical clock readings.
+    pub epoch: u32,
+    /// The most recently issued or observed timestamp.
+    pub last_observed: Timestamp<T>,
+}
+
+// Converts a difference between clock readings into real time.
+type ToDuration<D> = fn(&D) -> Duration;
+
 /// A wrapper around `Clock` that will refuse updates outside of our tolerance.
+///
+/// Whether an observed timestamp is within tolerance is decided by an
+/// `OffsetPolicy`, by default a `FixedOffsetPolicy`.
 #[derive(Debug, Clone)]
-pub struct OffsetLimiter<S: ClockSource> {
+pub struct OffsetLimiter<S: ClockSource, P = FixedOffsetPolicy<<S as ClockSource>::Delta>> {
     clock: Clock<S>,
-    max_offset: S::Delta,
+    policy: P,
+    // The limit on forward jumps of our own clock source, and how to
+    // measure a difference between readings in real time.
+    max_local_jump: Option<(S::Delta, ToDuration<S::Delta>)>,
+    // The last physical time issued by `now`, and when it was read.
+    last_local: Option<(S::Time, Instant)>,
+    stats: OffsetStats,
+}
+
+/// Statistics about the timestamps observed by an `OffsetLimiter`, for
+/// diagnosing clock skew across a cluster.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OffsetStats {
+    /// The number of observations refused for exceeding a limit.
+    pub rejected: u64,
+    /// The number of observations clamped by `observe_clamped`.
+    pub clamped: u64,
+    /// The greatest offset ahead of our physical time seen in any
+    /// observation, whether accepted or not.
+    pub max_offset_seen: Duration,
+    /// When an observation was last refused.
+    pub last_rejection: Option<SystemTime>,
 }
 
 impl Clock<WallNS> {
@@ -76,6 +312,13 @@ impl Clock<WallNS> {
     pub fn wall_ns() -> Result<Clock<WallNS>> {
         Clock::new(WallNS)
     }
+
+    /// Creates a unique monotonic timestamp, along with the `SystemTime`
+    /// that its physical component represents.
+    pub fn now_systemtime(&mut self) -> Result<(Timestamp<WallNST>, SystemTime)> {
+        let ts = self.now()?;
+        Ok((ts, ts.systemtime()))
+    }
 }
 
 impl Clock<WallMS> {
@@ -83,6 +326,41 @@ impl Clock<WallMS> {
     pub fn wall_ms() -> Result<Clock<WallMS>> {
         Clock::new(WallMS)
     }
+
+    /// Creates a unique monotonic timestamp, along with the `SystemTime`
+    /// that its physical component represents.
+    pub fn now_systemtime(&mut self) -> Result<(Timestamp<WallMST>, SystemTime)> {
+        let ts = self.now()?;
+        Ok((ts, ts.systemtime()))
+    }
+}
+
+impl Clock<WallMillis> {
+    /// Returns a `Clock` that uses WallMillis-clock time.
+    pub fn wall_millis() -> Result<Clock<WallMillis>> {
+        Clock::new(WallMillis)
+    }
+
+    /// Creates a unique monotonic timestamp, along with the `SystemTime`
+    /// that its physical component represents.
+    pub fn now_systemtime(&mut self) -> Result<(Timestamp<WallMillisT>, SystemTime)> {
+        let ts = self.now()?;
+        Ok((ts, ts.systemtime()))
+    }
+}
+
+impl Clock<WallUS> {
+    /// Returns a `Clock` that uses WallUS-clock time.
+    pub fn wall_us() -> Result<Clock<WallUS>> {
+        Clock::new(WallUS)
+    }
+
+    /// Creates a unique monotonic timestamp, along with the `SystemTime`
+    /// that its physical component represents.
+    pub fn now_systemtime(&mut self) -> Result<(Timestamp<WallUST>, SystemTime)> {
+        let ts = self.now()?;
+        Ok((ts, ts.systemtime()))
+    }
 }
 
 impl Clock<ManualClock> {
@@ -93,6 +371,12 @@ impl Clock<ManualClock> {
     pub fn set_time(&mut self, t: u64) {
         self.src.set_time(t)
     }
+
+    /// Moves the clock source's time forwards by `delta`. See
+    /// `ManualClock::advance_by`.
+    pub fn advance_by(&mut self, delta: u64) -> u64 {
+        self.src.advance_by(delta)
+    }
 }
 
 impl<S: ClockSource> Clock<S> {
@@ -107,99 +391,408 @@ impl<S: ClockSource> Clock<S> {
                 count: 0,
             },
             epoch: 0,
+            max_count: u32::MAX,
+            last_reading: Some(init),
+            metrics: ClockMetrics::default(),
         };
         Ok(clock)
     }
 
+    /// Creates a clock with `src` as the time provider, resuming from a
+    /// previously saved `state`.
+    pub fn from_state(src: S, state: ClockState<S::Time>) -> Self {
+        Clock {
+            src,
+            epoch: state.epoch,
+            last_observed: state.last_observed,
+            max_count: u32::MAX,
+            last_reading: None,
+            metrics: ClockMetrics::default(),
+        }
+    }
+
+    /// Returns the clock's current state, suitable for restoring it later
+    /// with `Clock::from_state`.
+    pub fn state(&self) -> ClockState<S::Time> {
+        ClockState {
+            epoch: self.epoch,
+            last_observed: self.last_observed,
+        }
+    }
+
+    /// Folds another clock's state into this one, such as when migrating a
+    /// shard between nodes. This takes the greater of the two epochs and of
+    /// the two last observed timestamps, and so is equivalent to observing
+    /// every timestamp the other clock had issued or observed, in bulk.
+    pub fn merge_state(&mut self, other: ClockState<S::Time>) {
+        self.epoch = self.epoch.max(other.epoch);
+        self.last_observed = self.last_observed.max(other.last_observed);
+    }
+
     /// Creates a clock with `src` as the time provider, and `diff` as how far
     /// in the future we don't mind seeing updates from.
     pub fn with_max_diff(self, max_offset: S::Delta) -> OffsetLimiter<S> {
         OffsetLimiter::new(self, max_offset)
     }
 
+    /// Wraps this clock in an `OffsetLimiter` that uses `policy` to decide
+    /// which observed timestamps to accept.
+    pub fn with_offset_policy<P: OffsetPolicy<S::Time, S::Delta>>(
+        self,
+        policy: P,
+    ) -> OffsetLimiter<S, P> {
+        OffsetLimiter::with_policy(self, policy)
+    }
+
     /// Used to create a new "epoch" of clock times, mostly useful as a manual
     /// override when a cluster member has skewed the clock time far
     /// into the future.
+    ///
+    /// This is unchecked: if `epoch` is lower than that of the last
+    /// timestamp, physical clock readings will sort before it, so the clock
+    /// can only advance its logical counter until it catches up. Use
+    /// `try_set_epoch` to refuse such a change.
     pub fn set_epoch(&mut self, epoch: u32) {
         self.epoch = epoch;
     }
 
+    /// Sets the epoch as `set_epoch` does, unless doing so would move it
+    /// behind the clock's current epoch or that of the last timestamp, in
+    /// which case `Error::EpochRegression` is returned.
+    pub fn try_set_epoch(&mut self, epoch: u32) -> Result<()> {
+        let current = self.epoch.max(self.last_observed.epoch);
+        if epoch < current {
+            return Err(Error::EpochRegression {
+                current,
+                requested: epoch,
+            });
+        }
+        self.epoch = epoch;
+        Ok(())
+    }
+
+    /// Refuse to issue or accept timestamps whose logical counter exceeds
+    /// `max_count`. As the counter only grows whilst the physical clock is
+    /// stalled (or behind a peer's), this provides an early warning that the
+    /// physical clock is not advancing.
+    pub fn set_max_count(&mut self, max_count: u32) {
+        self.max_count = max_count;
+    }
+
     /// Creates a unique monotonic timestamp suitable for annotating messages we send.
+    ///
+    /// Returns `Error::CounterExhausted` if the physical clock has stalled
+    /// for so long that the logical counter would overflow.
     pub fn now(&mut self) -> Result<Timestamp<S::Time>> {
         let pt = self.read_pt()?;
-        self.do_observe(&pt);
+        self.do_observe(&pt)?;
         Ok(self.last_observed)
     }
 
-    fn do_observe(&mut self, observation: &Timestamp<S::Time>) {
-        let lp = self.last_observed;
-
-        self.last_observed = match (
-            lp.epoch.cmp(&observation.epoch),
-            lp.time.cmp(&observation.time),
-            lp.count.cmp(&observation.count),
-        ) {
-            (Ordering::Less, _, _) | (Ordering::Equal, Ordering::Less, _) => *observation,
-            (Ordering::Equal, Ordering::Equal, Ordering::Less) => Timestamp {
-                count: observation.count + 1,
-                ..lp
+    /// Creates a unique monotonic timestamp by advancing only the logical
+    /// counter, without reading the clock source. The physical component of
+    /// the result is that of the most recent timestamp.
+    pub fn tick(&mut self) -> Result<Timestamp<S::Time>> {
+        let count = next_count(self.last_observed.count)?;
+        self.advance(
+            Timestamp {
+                count,
+                ..self.last_observed
             },
-            _ => Timestamp {
-                count: lp.count + 1,
-                ..lp
+            true,
+        )
+    }
+
+    /// Returns the most recently issued or observed timestamp, without
+    /// advancing the clock.
+    pub fn peek(&self) -> Timestamp<S::Time> {
+        self.last_observed
+    }
+
+    /// Reserves `n` strictly increasing timestamps with a single read of the
+    /// clock source. Further calls to `now` will return timestamps that
+    /// `happen-after` every timestamp in the batch.
+    pub fn now_batch(&mut self, n: usize) -> Result<Vec<Timestamp<S::Time>>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let pt = self.read_pt()?;
+        let first = self.last_observed.merge(&pt)?;
+        let last = batch_end(first.count, n)?;
+        self.advance(
+            Timestamp {
+                count: last,
+                ..first
             },
-        };
+            last > 0,
+        )?;
+        let batch = (first.count..=last)
+            .map(|count| Timestamp { count, ..first })
+            .collect();
+        Ok(batch)
+    }
+
+    /// Returns counters describing how the clock has behaved so far.
+    pub fn metrics(&self) -> ClockMetrics {
+        self.metrics
+    }
+
+    /// Resets the counters returned by `metrics` to zero.
+    pub fn reset_metrics(&mut self) {
+        self.metrics = ClockMetrics::default();
+    }
+
+    fn do_observe(&mut self, observation: &Timestamp<S::Time>) -> Result<()> {
+        let next = self.last_observed.merge(observation)?;
+        // The merge only differs from the observation when it had to bump
+        // the counter past a timestamp at or ahead of it.
+        self.advance(next, next != *observation)?;
+        Ok(())
+    }
+
+    // Moves the clock on to `next`, provided it is within our counter limit.
+    // `logical` says whether we got there by incrementing the counter, rather
+    // than adopting a physical time or a timestamp verbatim.
+    fn advance(&mut self, next: Timestamp<S::Time>, logical: bool) -> Result<Timestamp<S::Time>> {
+        if next.count > self.max_count {
+            return Err(Error::CountLimitExceeded {
+                count: next.count,
+                limit: self.max_count,
+            });
+        }
+        if logical {
+            self.metrics.logical_increments += 1;
+        }
+        self.last_observed = next;
+        Ok(next)
+    }
+
+    // Counts an observed timestamp as merged or dominated, once the clock has
+    // been updated with it; `ahead` says whether it was ahead of the clock.
+    fn note_observation(&mut self, ahead: bool) {
+        if ahead {
+            self.metrics.observations_merged += 1;
+        } else {
+            self.metrics.observations_dominated += 1;
+        }
+    }
+
+    // Counts a reading from the clock source that went backwards.
+    fn note_reading(&mut self, time: S::Time) {
+        if self.last_reading.is_some_and(|last| time < last) {
+            self.metrics.regressions += 1;
+        }
+        self.last_reading = Some(time);
     }
 
     /// Accepts a timestamp from an incoming message, and updates the clock
     /// so that further calls to `now` will always return a timestamp that
     /// `happens-after` either locally generated timestamps or that of the
     /// input message.
-    pub fn observe(&mut self, msg: &Timestamp<S::Time>) {
-        self.do_observe(msg);
+    pub fn observe(&mut self, msg: &Timestamp<S::Time>) -> Result<()> {
+        let ahead = *msg > self.last_observed;
+        self.do_observe(msg)?;
+        self.note_observation(ahead);
+        Ok(())
+    }
+
+    /// Accepts a timestamp from an incoming message, following the receive
+    /// rule from the paper: the clock advances to the greatest of its last
+    /// timestamp, the message's timestamp and the current physical time.
+    /// Returns the timestamp assigned to the receive event.
+    ///
+    /// Unlike `observe`, this reads the clock source, so receiving an old
+    /// message does not hold the clock back at a stale physical time.
+    pub fn receive(&mut self, msg: &Timestamp<S::Time>) -> Result<Timestamp<S::Time>> {
+        let pt = self.read_pt()?;
+        let lp = self.last_observed;
+        let key = |t: &Timestamp<S::Time>| (t.epoch, t.time);
+        let (epoch, time) = key(&lp).max(key(msg)).max(key(&pt));
+        let (count, logical) = match [lp, *msg]
+            .iter()
+            .filter(|t| key(t) == (epoch, time))
+            .map(|t| t.count)
+            .max()
+        {
+            Some(count) => (next_count(count)?, true),
+            None => (0, false),
+        };
+        let ts = self.advance(Timestamp { epoch, time, count }, logical)?;
+        self.note_observation(*msg > lp);
+        Ok(ts)
+    }
+
+    /// As `observe`, but also returns the clock's timestamp after merging
+    /// `msg`, and whether `msg` was ahead of or behind the clock beforehand.
+    pub fn observe_now(
+        &mut self,
+        msg: &Timestamp<S::Time>,
+    ) -> Result<(Timestamp<S::Time>, ObservationOutcome)> {
+        let outcome = match msg.cmp(&self.last_observed) {
+            Ordering::Greater => ObservationOutcome::RemoteAhead,
+            Ordering::Less => ObservationOutcome::LocalAhead,
+            Ordering::Equal => ObservationOutcome::Equal,
+        };
+        self.do_observe(msg)?;
+        self.note_observation(outcome == ObservationOutcome::RemoteAhead);
+        Ok((self.last_observed, outcome))
     }
 
     fn read_pt(&mut self) -> Result<Timestamp<S::Time>> {
+        let time = self.src.now()?;
+        self.note_reading(time);
         Ok(Timestamp {
             epoch: self.epoch,
-            time: self.src.now()?,
+            time,
             count: 0,
         })
     }
 }
+
+impl<S: InfallibleClockSource> Clock<S> {
+    /// Creates a unique monotonic timestamp, as `now` does, for sources whose
+    /// readings cannot fail.
+    ///
+    /// # Panics
+    ///
+    /// If the logical counter is exhausted or exceeds the configured
+    /// `max_count`, which can only happen if the source stalls or goes
+    /// backwards for a very long time.
+    pub fn now_infallible(&mut self) -> Timestamp<S::Time> {
+        let time = self.src.now_infallible();
+        self.note_reading(time);
+        let pt = Timestamp {
+            epoch: self.epoch,
+            time,
+            count: 0,
+        };
+        self.do_observe(&pt).expect("logical counter exhausted");
+        self.last_observed
+    }
+}
+
 impl<S: ClockSource> OffsetLimiter<S> {
     pub fn new(clock: Clock<S>, max_offset: S::Delta) -> Self {
-        OffsetLimiter { clock, max_offset }
+        OffsetLimiter::with_policy(clock, FixedOffsetPolicy::new(max_offset))
     }
-    /// Accepts a timestamp from an incoming message, and updates the clock
-    /// so that further calls to `now` will always return a timestamp that
-    /// `happens-after` either locally generated timestamps or that of the
-    /// input message. Returns an Error iff the delta from our local lock to
-    /// the observed timestamp is greater than our configured limit.
-    pub fn observe(&mut self, msg: &Timestamp<S::Time>) -> Result<()> {
-        let pt = self.clock.read_pt()?;
-        self.verify_offset(&pt, msg)?;
-        self.clock.observe(msg);
-        Ok(())
+
+    /// Also refuse to observe timestamps more than `max_offset` behind our
+    /// physical time, as these usually indicate a peer with a broken clock.
+    pub fn with_max_diff_backward(mut self, max_offset: S::Delta) -> Self {
+        self.policy.max_backward = Some(max_offset);
+        self
     }
 
-    /// Creates a unique monotonic timestamp suitable for annotating messages we send.
-    pub fn now(&mut self) -> Result<Timestamp<S::Time>> {
-        self.clock.now()
+    /// Returns the greatest offset ahead of our physical time that we will
+    /// accept.
+    pub fn max_offset(&self) -> &S::Delta {
+        &self.policy.max_forward
     }
 
-    fn verify_offset(&self, pt: &Timestamp<S::Time>, msg: &Timestamp<S::Time>) -> Result<()> {
-        // Guard from overflow when `S::Time.time` uses unsigned arithmetic.
-        if msg.time <= pt.time {
-            return Ok(());
+    /// Changes the greatest offset ahead of our physical time that we will
+    /// accept, eg: to widen the tolerance during an NTP incident. The
+    /// clock's state is unaffected.
+    pub fn set_max_offset(&mut self, max_offset: S::Delta) {
+        self.policy.max_forward = max_offset;
+    }
+
+    /// Returns the greatest offset behind our physical time that we will
+    /// accept, if limited.
+    pub fn max_backward_offset(&self) -> Option<&S::Delta> {
+        self.policy.max_backward.as_ref()
+    }
+
+    /// Changes or removes the limit on how far behind our physical time an
+    /// observed timestamp may be.
+    pub fn set_max_backward_offset(&mut self, max_offset: Option<S::Delta>) {
+        self.policy.max_backward = max_offset;
+    }
+}
+
+impl<S: ClockSource, P> OffsetLimiter<S, P> {
+    /// Wraps `clock`, using `policy` to decide which observed timestamps to
+    /// accept.
+    pub fn with_policy(clock: Clock<S>, policy: P) -> Self {
+        OffsetLimiter {
+            clock,
+            policy,
+            max_local_jump: None,
+            last_local: None,
+            stats: OffsetStats::default(),
         }
+    }
 
-        let diff = msg.time - pt.time;
-        if diff > self.max_offset {
-            return Err(Error::OffsetTooGreat);
+    /// Refuse to issue timestamps from `now` when our own clock source has
+    /// jumped forwards by more than `max_jump` beyond the time that actually
+    /// elapsed since it was last read, as measured by the monotonic clock.
+    /// Otherwise, a local clock stepped hours ahead would silently push
+    /// every timestamp we issue (and every peer that observes them) into
+    /// the future.
+    pub fn with_max_local_jump(self, max_jump: S::Delta) -> Self
+    where
+        S::Delta: AsDuration,
+    {
+        OffsetLimiter {
+            max_local_jump: Some((max_jump, S::Delta::as_duration)),
+            last_local: None,
+            ..self
         }
+    }
 
-        Ok(())
+    /// Returns the greatest forward jump of our own clock source that `now`
+    /// will tolerate, if limited.
+    pub fn max_local_jump(&self) -> Option<&S::Delta> {
+        self.max_local_jump.as_ref().map(|(max_jump, _)| max_jump)
+    }
+
+    /// Changes or removes the limit on forward jumps of our own clock
+    /// source. This also forgets the previous reading, so it may be used to
+    /// accept a jump once it has been confirmed to be a genuine correction.
+    pub fn set_max_local_jump(&mut self, max_jump: Option<S::Delta>)
+    where
+        S::Delta: AsDuration,
+    {
+        self.max_local_jump =
+            max_jump.map(|max_jump| (max_jump, S::Delta::as_duration as ToDuration<_>));
+        self.last_local = None;
+    }
+
+    /// Returns statistics about the timestamps observed so far.
+    pub fn stats(&self) -> OffsetStats {
+        self.stats
+    }
+
+    /// Get a reference to the offset policy
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    /// Get a mutable reference to the offset policy
+    pub fn policy_mut(&mut self) -> &mut P {
+        &mut self.policy
+    }
+
+    /// Creates a unique monotonic timestamp suitable for annotating messages we send.
+    ///
+    /// If a limit has been set with `with_max_local_jump`, returns
+    /// `Error::LocalClockJump` rather than issuing a timestamp when our
+    /// clock source has jumped forwards by more than that limit. The clock
+    /// is left untouched, and further calls fail until the source comes
+    /// back into line, or the limit is changed.
+    pub fn now(&mut self) -> Result<Timestamp<S::Time>> {
+        let pt = self.clock.read_pt()?;
+        let read_at = Instant::now();
+        self.check_local_jump(&pt, read_at)?;
+        self.clock.do_observe(&pt)?;
+        if self.max_local_jump.is_some() {
+            self.last_local = Some((pt.time, read_at));
+        }
+        Ok(self.clock.last_observed)
+    }
+
+    /// Reserves `n` strictly increasing timestamps. See `Clock::now_batch`.
+    pub fn now_batch(&mut self, n: usize) -> Result<Vec<Timestamp<S::Time>>> {
+        self.clock.now_batch(n)
     }
 
     /// Extract the inner `Clock`
@@ -216,15 +809,304 @@ impl<S: ClockSource> OffsetLimiter<S> {
     pub fn inner_mut(&mut self) -> &mut Clock<S> {
         &mut self.clock
     }
+
+    fn check_local_jump(&self, pt: &Timestamp<S::Time>, read_at: Instant) -> Result<()> {
+        let (limit, as_duration, (last_time, last_read)) =
+            match (&self.max_local_jump, self.last_local) {
+                (Some((limit, as_duration)), Some(last)) => (as_duration(limit), as_duration, last),
+                _ => return Ok(()),
+            };
+        if pt.time <= last_time {
+            return Ok(());
+        }
+        let elapsed = read_at.saturating_duration_since(last_read);
+        let observed = as_duration(&(pt.time - last_time)).saturating_sub(elapsed);
+        if observed > limit {
+            return Err(Error::LocalClockJump { observed, limit });
+        }
+        Ok(())
+    }
+}
+
+impl<S: ClockSource, P: OffsetPolicy<S::Time, S::Delta>> OffsetLimiter<S, P> {
+    /// Accepts a timestamp from an incoming message, and updates the clock
+    /// so that further calls to `now` will always return a timestamp that
+    /// `happens-after` either locally generated timestamps or that of the
+    /// input message. Returns an Error iff the delta from our local lock to
+    /// the observed timestamp is greater than our configured limit.
+    pub fn observe(&mut self, msg: &Timestamp<S::Time>) -> Result<()> {
+        let pt = self.clock.read_pt()?;
+        self.verify_offset(&pt, msg)?;
+        self.clock.observe(msg)
+    }
+
+    /// As `observe`, but also returns the clock's timestamp after merging
+    /// `msg`, and how it compared with the clock. See `Clock::observe_now`.
+    pub fn observe_now(
+        &mut self,
+        msg: &Timestamp<S::Time>,
+    ) -> Result<(Timestamp<S::Time>, ObservationOutcome)> {
+        let pt = self.clock.read_pt()?;
+        self.verify_offset(&pt, msg)?;
+        self.clock.observe_now(msg)
+    }
+
+    /// Accepts a timestamp from an incoming message using the paper's
+    /// receive rule, once it has been checked against our configured limit.
+    /// See `Clock::receive`.
+    pub fn receive(&mut self, msg: &Timestamp<S::Time>) -> Result<Timestamp<S::Time>> {
+        let pt = self.clock.read_pt()?;
+        self.verify_offset(&pt, msg)?;
+        self.clock.receive(msg)
+    }
+
+    fn verify_offset(&mut self, pt: &Timestamp<S::Time>, msg: &Timestamp<S::Time>) -> Result<()> {
+        let res = self.check_offset(pt, msg);
+        if res.is_err() {
+            self.stats.rejected += 1;
+            self.stats.last_rejection = Some(SystemTime::now());
+        }
+        res
+    }
+
+    fn record_offset(&mut self, offset: Duration) {
+        self.stats.max_offset_seen = self.stats.max_offset_seen.max(offset);
+    }
+
+    fn check_offset(&mut self, pt: &Timestamp<S::Time>, msg: &Timestamp<S::Time>) -> Result<()> {
+        // Guard from overflow when `S::Time.time` uses unsigned arithmetic.
+        let (offset, direction) = if msg.time > pt.time {
+            (msg.time - pt.time, Ordering::Greater)
+        } else {
+            (pt.time - msg.time, msg.time.cmp(&pt.time))
+        };
+        if let Some(diff) = self.policy.measure(&offset) {
+            if direction == Ordering::Greater {
+                self.record_offset(diff);
+            }
+            self.clock.metrics.offsets.record(diff, direction);
+        }
+        self.policy.check(msg, &offset, direction)
+    }
+}
+
+impl<S: ClockSource> OffsetLimiter<S>
+where
+    S::Time: AsDuration,
+    S::Delta: AsDuration,
+{
+    /// As `observe`, but rather than refusing a timestamp that is too far
+    /// ahead, merges it as if its physical time were exactly `max_offset`
+    /// ahead of ours. Returns the timestamp that was merged, and whether it
+    /// was clamped. This bounds how far the clock can be dragged forwards by
+    /// a single peer, without dropping its messages entirely.
+    ///
+    /// Timestamps behind our physical time cannot advance the clock, so are
+    /// merged unchanged, regardless of any backward limit.
+    pub fn observe_clamped(
+        &mut self,
+        msg: &Timestamp<S::Time>,
+    ) -> Result<(Timestamp<S::Time>, bool)>
+    where
+        S::Time: Add<S::Delta, Output = S::Time>,
+        S::Delta: Copy,
+    {
+        let pt = self.clock.read_pt()?;
+        let limit = pt.time + self.policy.max_forward;
+        if msg.time > pt.time {
+            let offset = (msg.time - pt.time).as_duration();
+            self.record_offset(offset);
+            self.clock.metrics.offsets.record(offset, Ordering::Greater);
+        } else {
+            let offset = (pt.time - msg.time).as_duration();
+            self.clock.metrics.offsets.record(offset, Ordering::Less);
+        }
+        let (merged, clamped) = if msg.time > limit {
+            self.stats.clamped += 1;
+            (
+                Timestamp {
+                    time: limit,
+                    ..*msg
+                },
+                true,
+            )
+        } else {
+            (*msg, false)
+        };
+        self.clock.observe(&merged)?;
+        Ok((merged, clamped))
+    }
 }
 
+/// Writes the timestamp as `epoch:time+count`. The alternate flag (`{:#}`)
+/// is passed on to the time, so that eg: wall clock times are written as
+/// RFC3339 dates.
 impl<T: fmt::Display> fmt::Display for Timestamp<T> {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(fmt, "{}:{}+{}", self.epoch, self.time, self.count)
+        if fmt.alternate() {
+            write!(fmt, "{}:{:#}+{}", self.epoch, self.time, self.count)
+        } else {
+            write!(fmt, "{}:{}+{}", self.epoch, self.time, self.count)
+        }
+    }
+}
+
+/// Writes the timestamp as 32 fixed-width hex digits, which sort in the same
+/// order as the timestamps.
+impl<T: U64Time> fmt::LowerHex for Timestamp<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "{:08x}{:016x}{:08x}",
+            self.epoch,
+            self.time.as_u64(),
+            self.count
+        )
+    }
+}
+
+/// Parses the `epoch:time+count` form written by `Display`.
+impl<T: FromStr> FromStr for Timestamp<T> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::Parse(s.to_owned());
+        let (epoch, rest) = s.split_once(':').ok_or_else(invalid)?;
+        let (time, count) = rest.rsplit_once('+').ok_or_else(invalid)?;
+        Ok(Timestamp {
+            epoch: epoch.parse().map_err(|_| invalid())?,
+            time: time.parse().map_err(|_| invalid())?,
+            count: count.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl<T: Ord + Copy> Timestamp<T> {
+    /// Returns the timestamp that follows both `self` and `observation`.
+    fn merge(self, observation: &Self) -> Result<Self> {
+        let ts = match (
+            self.epoch.cmp(&observation.epoch),
+            self.time.cmp(&observation.time),
+            self.count.cmp(&observation.count),
+        ) {
+            (Ordering::Less, _, _) | (Ordering::Equal, Ordering::Less, _) => *observation,
+            (Ordering::Equal, Ordering::Equal, Ordering::Less) => Timestamp {
+                count: next_count(observation.count)?,
+                ..self
+            },
+            _ => Timestamp {
+                count: next_count(self.count)?,
+                ..self
+            },
+        };
+        Ok(ts)
+    }
+}
+
+/// Increments a logical counter, refusing to wrap around.
+fn next_count(count: u32) -> Result<u32> {
+    count.checked_add(1).ok_or(Error::CounterExhausted)
+}
+
+/// Returns the last count of a batch of `n` starting at `first`.
+fn batch_end(first: u32, n: usize) -> Result<u32> {
+    u32::try_from(n - 1)
+        .ok()
+        .and_then(|extra| first.checked_add(extra))
+        .ok_or(Error::CounterExhausted)
+}
+
+impl<T: Ord + Copy + Sub<Output = D>, D: Ord> Timestamp<T> {
+    /// Returns true if `self` happened before `other` even allowing for the
+    /// physical clocks that issued them to disagree by up to `max_skew`.
+    ///
+    /// Timestamps from an earlier epoch always happen before those from a
+    /// later one. Within an epoch, `self` must be more than `max_skew`
+    /// behind `other`.
+    pub fn happens_before_within(&self, other: &Self, max_skew: D) -> bool {
+        match self.epoch.cmp(&other.epoch) {
+            Ordering::Less => true,
+            Ordering::Greater => false,
+            Ordering::Equal => self.time < other.time && other.time - self.time > max_skew,
+        }
+    }
+
+    /// Returns the difference between `self` and `other` in each component.
+    pub fn diff(&self, other: &Self) -> TimestampDiff<D> {
+        let time_direction = self.time.cmp(&other.time);
+        let time = if time_direction == Ordering::Less {
+            other.time - self.time
+        } else {
+            self.time - other.time
+        };
+        TimestampDiff {
+            epochs: i64::from(self.epoch) - i64::from(other.epoch),
+            time,
+            time_direction,
+            counts: i64::from(self.count) - i64::from(other.count),
+        }
+    }
+
+    /// Returns true if the timestamps are in the same epoch, and their
+    /// physical times are no more than `max_skew` apart, so that their
+    /// ordering may be an artefact of clock skew. This is the case exactly
+    /// when neither `happens_before_within` the other.
+    pub fn definitely_concurrent_within(&self, other: &Self, max_skew: D) -> bool {
+        if self.epoch != other.epoch {
+            return false;
+        }
+        let gap = if self.time <= other.time {
+            other.time - self.time
+        } else {
+            self.time - other.time
+        };
+        gap <= max_skew
+    }
+}
+
+/// Moves the physical component of a timestamp forwards, eg: to compute a
+/// lease expiry. The logical counter is reset if the physical time changes.
+impl<T: Add<Duration, Output = T> + PartialEq + Copy> Add<Duration> for Timestamp<T> {
+    type Output = Timestamp<T>;
+    fn add(self, d: Duration) -> Self::Output {
+        self.with_time(self.time + d)
     }
 }
 
+/// Moves the physical component of a timestamp backwards. The logical
+/// counter is reset if the physical time changes, so the result sorts before
+/// any other timestamp at that time.
+impl<T: Sub<Duration, Output = T> + PartialEq + Copy> Sub<Duration> for Timestamp<T> {
+    type Output = Timestamp<T>;
+    fn sub(self, d: Duration) -> Self::Output {
+        self.with_time(self.time - d)
+    }
+}
+
+impl<T: TimeBounds> Timestamp<T> {
+    /// The least possible timestamp.
+    pub const MIN: Self = Timestamp::new(0, T::MIN, 0);
+    /// The greatest possible timestamp.
+    pub const MAX: Self = Timestamp::new(u32::MAX, T::MAX, u32::MAX);
+}
+
 impl<T> Timestamp<T> {
+    /// Builds a timestamp from its components.
+    pub const fn new(epoch: u32, time: T, count: u32) -> Self {
+        Timestamp { epoch, time, count }
+    }
+
+    fn with_time(self, time: T) -> Self
+    where
+        T: PartialEq,
+    {
+        let count = if time == self.time { self.count } else { 0 };
+        Timestamp {
+            epoch: self.epoch,
+            time,
+            count,
+        }
+    }
+
     pub fn time_into<U: From<T>>(self) -> Timestamp<U> {
         Timestamp {
             epoch: self.epoch,
@@ -234,14 +1116,32 @@ impl<T> Timestamp<T> {
     }
 }
 
+#[cfg(feature = "capnp")]
+mod capnp_impl;
+#[cfg(feature = "chrono")]
+mod chrono_impl;
+#[cfg(feature = "postgres")]
+mod postgres_impl;
+#[cfg(feature = "schemars")]
+mod schemars_impl;
 #[cfg(feature = "serialization")]
 mod serde_impl;
+#[cfg(feature = "time")]
+mod time_impl;
+#[cfg(feature = "async-tokio")]
+mod tokio_impl;
+#[cfg(feature = "uhlc")]
+mod uhlc_impl;
 
 #[cfg(test)]
 mod tests {
-    use super::Timestamp;
+    use super::{ManualT, Timestamp};
     use suppositions::generators::*;
 
+    pub fn manualts() -> Box<dyn GeneratorObject<Item = ManualT>> {
+        u64s().map(ManualT::from).boxed()
+    }
+
     pub fn timestamps<C: Generator + 'static>(
         times: C,
     ) -> Box<dyn GeneratorObject<Item = Timestamp<C::Item>>> {
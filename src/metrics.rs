@@ -0,0 +1,162 @@
+use std::cmp::Ordering;
+use std::time::Duration;
+
+/// The upper bounds of the buckets in an `OffsetHistogram`; the final
+/// bucket holds every offset of at least the last bound.
+pub const OFFSET_BUCKET_BOUNDS: [Duration; 8] = [
+    Duration::from_micros(1),
+    Duration::from_micros(10),
+    Duration::from_micros(100),
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+];
+
+/// The number of buckets in an `OffsetHistogram`.
+pub const OFFSET_BUCKETS: usize = OFFSET_BUCKET_BOUNDS.len() + 1;
+
+/// Counters describing how a `Clock` has behaved, for alerting on clock
+/// health. See `Clock::metrics`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClockMetrics {
+    /// The number of times the clock advanced by incrementing its logical
+    /// counter, as physical time had not moved past the latest timestamp.
+    pub logical_increments: u64,
+    /// The number of times a reading from the clock source was behind the
+    /// one before it.
+    pub regressions: u64,
+    /// The number of observed timestamps that were ahead of the clock, and
+    /// so moved it forwards.
+    pub observations_merged: u64,
+    /// The number of observed timestamps that were at or behind the clock,
+    /// and so were dominated by local time.
+    pub observations_dominated: u64,
+    /// How far the physical time of observed timestamps was from ours. This
+    /// is only recorded by an `OffsetLimiter`, as a bare `Clock` does not
+    /// measure offsets.
+    pub offsets: OffsetHistogram,
+}
+
+/// Counts of offsets between remote and local physical time, by order of
+/// magnitude. See `OFFSET_BUCKET_BOUNDS`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OffsetHistogram {
+    /// Offsets where the remote time was ahead of ours.
+    pub ahead: [u64; OFFSET_BUCKETS],
+    /// Offsets where the remote time was at or behind ours.
+    pub behind: [u64; OFFSET_BUCKETS],
+}
+
+impl OffsetHistogram {
+    /// Returns the index of the bucket that holds `offset`.
+    pub fn bucket(offset: Duration) -> usize {
+        OFFSET_BUCKET_BOUNDS
+            .iter()
+            .position(|bound| offset < *bound)
+            .unwrap_or(OFFSET_BUCKET_BOUNDS.len())
+    }
+
+    /// Records an offset of `offset`, with the remote time ahead of ours if
+    /// `direction` is `Ordering::Greater`.
+    pub fn record(&mut self, offset: Duration, direction: Ordering) {
+        let buckets = match direction {
+            Ordering::Greater => &mut self.ahead,
+            _ => &mut self.behind,
+        };
+        buckets[Self::bucket(offset)] += 1;
+    }
+
+    /// Returns the number of offsets recorded.
+    pub fn count(&self) -> u64 {
+        self.ahead.iter().chain(self.behind.iter()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Clock, ManualClock, ManualT, Result, Timestamp};
+
+    #[test]
+    fn should_bucket_by_order_of_magnitude() {
+        assert_eq!(OffsetHistogram::bucket(Duration::from_nanos(0)), 0);
+        assert_eq!(OffsetHistogram::bucket(Duration::from_micros(1)), 1);
+        assert_eq!(OffsetHistogram::bucket(Duration::from_millis(5)), 4);
+        assert_eq!(OffsetHistogram::bucket(Duration::from_secs(3600)), 8);
+    }
+
+    #[test]
+    fn should_count_logical_increments_and_regressions() -> Result<()> {
+        let mut clock = Clock::new(ManualClock::new(10))?;
+        clock.now()?;
+        clock.now()?;
+        clock.set_time(5);
+        clock.now()?;
+        clock.set_time(20);
+        clock.now()?;
+        let metrics = clock.metrics();
+        assert_eq!(metrics.logical_increments, 3);
+        assert_eq!(metrics.regressions, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn should_count_merged_and_dominated_observations() -> Result<()> {
+        let mut clock = Clock::new(ManualClock::new(10))?;
+        clock.observe(&Timestamp::new(0, ManualT::from(20), 0))?;
+        clock.observe(&Timestamp::new(0, ManualT::from(15), 0))?;
+        clock.receive(&Timestamp::new(0, ManualT::from(5), 0))?;
+        let metrics = clock.metrics();
+        assert_eq!(
+            (metrics.observations_merged, metrics.observations_dominated),
+            (1, 2)
+        );
+        clock.reset_metrics();
+        assert_eq!(clock.metrics(), ClockMetrics::default());
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_count_adopting_a_remote_counter() -> Result<()> {
+        let mut clock = Clock::manual(10)?;
+        clock.observe(&Timestamp::new(0, ManualT::from(20), 7))?;
+        assert_eq!(clock.metrics().logical_increments, 0);
+        clock.observe(&Timestamp::new(0, ManualT::from(20), 7))?;
+        assert_eq!(clock.metrics().logical_increments, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_count_failed_observations() -> Result<()> {
+        let mut clock = Clock::manual(10)?;
+        clock.set_max_count(5);
+        assert!(clock
+            .observe(&Timestamp::new(0, ManualT::from(20), 7))
+            .is_err());
+        assert!(clock
+            .receive(&Timestamp::new(0, ManualT::from(20), 7))
+            .is_err());
+        assert_eq!(clock.metrics(), ClockMetrics::default());
+        Ok(())
+    }
+
+    #[test]
+    fn limiter_should_record_offsets() -> Result<()> {
+        let mut clock = Clock::new(ManualClock::new(1_000_000))?.with_max_diff(1_000_000);
+        clock.observe(&Timestamp::new(0, ManualT::from(1_000_500), 0))?;
+        clock.observe(&Timestamp::new(0, ManualT::from(1_500), 0))?;
+        let offsets = clock.inner().metrics().offsets;
+        assert_eq!(
+            offsets.ahead[OffsetHistogram::bucket(Duration::from_nanos(500))],
+            1
+        );
+        assert_eq!(
+            offsets.behind[OffsetHistogram::bucket(Duration::from_nanos(998_500))],
+            1
+        );
+        assert_eq!(offsets.count(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use std::convert::TryInto;
+
+use crate::{ByteOrdered, Clock, ClockSource, Result, Timestamp, U64Time};
+
+/// A timestamp together with the id of the node that issued it.
+///
+/// Two nodes may issue equal `Timestamp`s, so these are ordered by
+/// timestamp and then by node id, giving a total order over the events of
+/// nodes with distinct ids, eg: to break ties between last-writer-wins
+/// updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeTimestamp<T, Id> {
+    /// The timestamp issued by the node's clock.
+    pub timestamp: Timestamp<T>,
+    /// The id of the issuing node.
+    pub node: Id,
+}
+
+impl<T, Id> NodeTimestamp<T, Id> {
+    /// Returns `timestamp`, as issued by `node`.
+    pub fn new(timestamp: Timestamp<T>, node: Id) -> Self {
+        NodeTimestamp { timestamp, node }
+    }
+}
+
+// Node ids are encoded big-endian after the timestamp, so that the
+// encodings sort in the same order as the values.
+macro_rules! impl_byte_ordered_for_node_id {
+    ($id:ty, $len:expr) => {
+        impl<T: U64Time> ByteOrdered for NodeTimestamp<T, $id> {
+            type Bytes = [u8; $len];
+            fn to_ordered_bytes(&self) -> Self::Bytes {
+                let mut res = [0; $len];
+                res[0..16].copy_from_slice(&self.timestamp.to_ordered_bytes());
+                res[16..].copy_from_slice(&self.node.to_be_bytes());
+                res
+            }
+            fn from_ordered_bytes(bytes: &Self::Bytes) -> Self {
+                NodeTimestamp {
+                    timestamp: Timestamp::from_ordered_bytes(&bytes[0..16].try_into().unwrap()),
+                    node: <$id>::from_be_bytes(bytes[16..].try_into().unwrap()),
+                }
+            }
+        }
+    };
+}
+
+impl_byte_ordered_for_node_id!(u16, 18);
+impl_byte_ordered_for_node_id!(u32, 20);
+impl_byte_ordered_for_node_id!(u64, 24);
+impl_byte_ordered_for_node_id!(u128, 32);
+
+/// A wrapper around `Clock` that stamps each timestamp it issues with this
+/// node's id.
+#[derive(Debug, Clone)]
+pub struct NodeClock<S: ClockSource, Id> {
+    clock: Clock<S>,
+    node: Id,
+}
+
+impl<S: ClockSource, Id: Copy> NodeClock<S, Id> {
+    /// Issues timestamps from `clock` on behalf of the node `node`.
+    pub fn new(clock: Clock<S>, node: Id) -> Self {
+        NodeClock { clock, node }
+    }
+
+    /// Returns this node's id.
+    pub fn node(&self) -> Id {
+        self.node
+    }
+
+    /// Issues a timestamp, as per `Clock::now`, stamped with our node id.
+    pub fn now(&mut self) -> Result<NodeTimestamp<S::Time, Id>> {
+        Ok(NodeTimestamp::new(self.clock.now()?, self.node))
+    }
+
+    /// Updates our clock to account for a timestamp from another node.
+    pub fn observe<OtherId>(&mut self, msg: &NodeTimestamp<S::Time, OtherId>) -> Result<()> {
+        self.clock.observe(&msg.timestamp)
+    }
+
+    /// Returns the underlying clock.
+    pub fn inner(&self) -> &Clock<S> {
+        &self.clock
+    }
+
+    /// Returns the underlying clock.
+    pub fn inner_mut(&mut self) -> &mut Clock<S> {
+        &mut self.clock
+    }
+
+    /// Returns the underlying clock.
+    pub fn into_inner(self) -> Clock<S> {
+        self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::{ManualClock, ManualT};
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    fn node_timestamps() -> Box<dyn GeneratorObject<Item = NodeTimestamp<ManualT, u32>>> {
+        (timestamps(manualts()), u32s())
+            .map(|(ts, node)| NodeTimestamp::new(ts, node))
+            .boxed()
+    }
+
+    #[test]
+    fn should_break_ties_by_node() {
+        let ts = Timestamp::new(0, ManualT::from(5), 0);
+        let later = Timestamp::new(0, ManualT::from(6), 0);
+        assert!(NodeTimestamp::new(ts, 1) < NodeTimestamp::new(ts, 2));
+        assert!(NodeTimestamp::new(ts, 2) < NodeTimestamp::new(later, 1));
+    }
+
+    #[test]
+    fn bytes_should_order_as_node_timestamps() {
+        property((node_timestamps(), node_timestamps())).check(|(ta, tb)| {
+            let (ba, bb) = (ta.to_ordered_bytes(), tb.to_ordered_bytes());
+            NodeTimestamp::from_ordered_bytes(&ba) == ta && ta.cmp(&tb) == ba.cmp(&bb)
+        });
+    }
+
+    #[test]
+    fn should_stamp_timestamps_with_node() -> Result<()> {
+        let mut a = NodeClock::new(Clock::new(ManualClock::new(10))?, 1u32);
+        let mut b = NodeClock::new(Clock::new(ManualClock::new(5))?, 2u32);
+        let sent = a.now()?;
+        assert_eq!(sent.node, 1);
+        b.observe(&sent)?;
+        let reply = b.now()?;
+        assert_eq!(reply.node, 2);
+        assert!(sent < reply, "{:?} < {:?}", sent, reply);
+        Ok(())
+    }
+
+    #[cfg(feature = "serialization")]
+    #[test]
+    fn should_round_trip_via_serde() {
+        property(node_timestamps()).check(|ts| {
+            let s = serde_json::to_string(&ts).expect("to-json");
+            serde_json::from_str::<NodeTimestamp<ManualT, u32>>(&s).expect("from-json") == ts
+        });
+    }
+}
@@ -0,0 +1,117 @@
+use std::convert::TryInto;
+use std::io;
+
+use crate::{CompactTimestamp, Timestamp, U64Time, COMPACT_TIMESTAMP_LEN};
+
+/// Values with a fixed-width byte encoding that sorts in the same order as
+/// the values themselves, as needed for keys in ordered key-value stores.
+///
+/// This is implemented for every `U64Time`, as its big-endian `u64`, and so
+/// for `Timestamp`s and `CompactTimestamp`s of those times, by encoding each
+/// field in turn.
+pub trait ByteOrdered: Sized {
+    /// The encoded form, eg: `[u8; 16]`.
+    type Bytes: AsRef<[u8]> + AsMut<[u8]> + Default;
+
+    /// Encodes the value.
+    fn to_ordered_bytes(&self) -> Self::Bytes;
+
+    /// Decodes a value written by `to_ordered_bytes`.
+    fn from_ordered_bytes(bytes: &Self::Bytes) -> Self;
+
+    /// Writes the encoded value to `wr`.
+    fn write_ordered_bytes<W: io::Write>(&self, mut wr: W) -> io::Result<()> {
+        wr.write_all(self.to_ordered_bytes().as_ref())
+    }
+
+    /// Reads a value written by `write_ordered_bytes`.
+    fn read_ordered_bytes<R: io::Read>(mut r: R) -> io::Result<Self> {
+        let mut buf = Self::Bytes::default();
+        r.read_exact(buf.as_mut())?;
+        Ok(Self::from_ordered_bytes(&buf))
+    }
+}
+
+impl<T: U64Time> ByteOrdered for T {
+    type Bytes = [u8; 8];
+    fn to_ordered_bytes(&self) -> Self::Bytes {
+        self.as_u64().to_be_bytes()
+    }
+    fn from_ordered_bytes(bytes: &Self::Bytes) -> Self {
+        T::of_u64(u64::from_be_bytes(*bytes))
+    }
+}
+
+impl<T: U64Time> ByteOrdered for Timestamp<T> {
+    type Bytes = [u8; 16];
+    fn to_ordered_bytes(&self) -> Self::Bytes {
+        let mut res = [0; 16];
+        res[0..4].copy_from_slice(&self.epoch.to_be_bytes());
+        res[4..12].copy_from_slice(&self.time.to_ordered_bytes());
+        res[12..16].copy_from_slice(&self.count.to_be_bytes());
+        res
+    }
+    fn from_ordered_bytes(bytes: &Self::Bytes) -> Self {
+        Timestamp {
+            epoch: u32::from_be_bytes(bytes[0..4].try_into().unwrap()),
+            time: T::from_ordered_bytes(&bytes[4..12].try_into().unwrap()),
+            count: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+}
+
+impl<T: U64Time> ByteOrdered for CompactTimestamp<T> {
+    type Bytes = [u8; COMPACT_TIMESTAMP_LEN];
+    fn to_ordered_bytes(&self) -> Self::Bytes {
+        let mut res = [0; COMPACT_TIMESTAMP_LEN];
+        res[0..8].copy_from_slice(&self.time.to_ordered_bytes());
+        res[8..12].copy_from_slice(&self.count.to_be_bytes());
+        res
+    }
+    fn from_ordered_bytes(bytes: &Self::Bytes) -> Self {
+        CompactTimestamp {
+            time: T::from_ordered_bytes(&bytes[0..8].try_into().unwrap()),
+            count: u32::from_be_bytes(bytes[8..12].try_into().unwrap()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::Ticks;
+    use std::io::Cursor;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    #[test]
+    fn should_round_trip_via_stream() {
+        property(timestamps(manualts())).check(|ts| {
+            let mut bs = Vec::new();
+            ts.write_ordered_bytes(&mut bs).expect("write");
+            Timestamp::read_ordered_bytes(Cursor::new(&bs)).expect("read") == ts
+        })
+    }
+
+    #[test]
+    fn should_order_as_timestamps() {
+        property((timestamps(manualts()), timestamps(manualts())))
+            .check(|(ta, tb)| ta.cmp(&tb) == ta.to_ordered_bytes().cmp(&tb.to_ordered_bytes()))
+    }
+
+    #[test]
+    fn compact_should_order_as_timestamps() {
+        let compacts = || {
+            (u64s().map(Ticks::<crate::Nanos>::of_u64), u32s())
+                .map(|(time, count)| CompactTimestamp { time, count })
+        };
+        property((compacts(), compacts())).check(|(ta, tb)| {
+            assert_eq!(
+                CompactTimestamp::from_ordered_bytes(&ta.to_ordered_bytes()),
+                ta
+            );
+            ta.cmp(&tb) == ta.to_ordered_bytes().cmp(&tb.to_ordered_bytes())
+        })
+    }
+}
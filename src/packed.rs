@@ -0,0 +1,199 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::{AsDuration, ByteOrdered, Error, FromDuration, Result, Timestamp, U64Time, WallMST};
+
+/// The number of low bits of a packed `u64` that hold the logical count.
+pub const PACKED_COUNT_BITS: u32 = 16;
+/// The number of high bits of a packed `u64` that hold the physical time.
+pub const PACKED_TIME_BITS: u32 = 64 - PACKED_COUNT_BITS;
+
+/// The number of low bits of a CockroachDB-style `u64` that hold the
+/// logical count; the remaining high bits hold physical milliseconds.
+pub const CRDB_LOGICAL_BITS: u32 = 18;
+
+// Checks that `value` fits in `bits` bits.
+pub(crate) fn check_width(field: &'static str, value: u64, bits: u32) -> Result<u64> {
+    if bits < 64 && value >> bits != 0 {
+        return Err(Error::PackOverflow { field, value, bits });
+    }
+    Ok(value)
+}
+
+impl Timestamp<WallMST> {
+    /// Packs the timestamp into a `u64`, with a 48 bit time
+    /// (ie: 1/65536ths of a second, until 2106) above a 16 bit count, such
+    /// that packed values sort in the same order as the timestamps. This
+    /// suits index keys, where 16 bytes per timestamp is heavy.
+    ///
+    /// Fails with `Error::PackOverflow` if the epoch is not zero, or the time
+    /// or count do not fit.
+    pub fn to_packed_u64(&self) -> Result<u64> {
+        check_width("epoch", u64::from(self.epoch), 0)?;
+        let time = check_width("time", self.time.as_u64(), PACKED_TIME_BITS)?;
+        let count = check_width("count", u64::from(self.count), PACKED_COUNT_BITS)?;
+        Ok(time << PACKED_COUNT_BITS | count)
+    }
+
+    /// Unpacks a timestamp written by `to_packed_u64`, in epoch zero.
+    pub fn from_packed_u64(packed: u64) -> Self {
+        Timestamp {
+            epoch: 0,
+            time: WallMST::of_u64(packed >> PACKED_COUNT_BITS),
+            count: (packed & ((1 << PACKED_COUNT_BITS) - 1)) as u32,
+        }
+    }
+}
+
+impl<T: U64Time> Timestamp<T> {
+    /// Packs the epoch, time and count into a single `u128`, whose integer
+    /// order matches the order of the timestamps, eg: for storage in
+    /// existing 128-bit columns.
+    pub fn to_u128(&self) -> u128 {
+        u128::from_be_bytes(self.to_ordered_bytes())
+    }
+
+    /// Unpacks a timestamp written by `to_u128`.
+    pub fn from_u128(packed: u128) -> Self {
+        Self::from_ordered_bytes(&packed.to_be_bytes())
+    }
+}
+
+impl<T: AsDuration + FromDuration> Timestamp<T> {
+    /// Packs the timestamp as physical milliseconds in the high 46 bits and
+    /// the count in the low `CRDB_LOGICAL_BITS` bits, the layout used by
+    /// CockroachDB-style timestamp oracles. Sub-millisecond precision is
+    /// discarded, so distinct timestamps may pack to the same value, but
+    /// packing never reverses their order.
+    ///
+    /// Fails with `Error::PackOverflow` if the epoch is not zero, or the time
+    /// or count do not fit.
+    pub fn to_crdb_u64(&self) -> Result<u64> {
+        check_width("epoch", u64::from(self.epoch), 0)?;
+        let millis = u64::try_from(self.time.as_duration().as_millis()).unwrap_or(u64::MAX);
+        let millis = check_width("time", millis, 64 - CRDB_LOGICAL_BITS)?;
+        let count = check_width("count", u64::from(self.count), CRDB_LOGICAL_BITS)?;
+        Ok(millis << CRDB_LOGICAL_BITS | count)
+    }
+
+    /// Unpacks a timestamp written by `to_crdb_u64`, in epoch zero.
+    pub fn from_crdb_u64(packed: u64) -> Result<Self> {
+        let millis = packed >> CRDB_LOGICAL_BITS;
+        Ok(Timestamp {
+            epoch: 0,
+            time: T::from_duration(Duration::from_millis(millis))?,
+            count: (packed & ((1 << CRDB_LOGICAL_BITS) - 1)) as u32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::{ManualT, WallNST};
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    fn packable() -> Box<dyn GeneratorObject<Item = Timestamp<WallMST>>> {
+        (u64s(), u32s())
+            .map(|(time, count)| Timestamp {
+                epoch: 0,
+                time: WallMST::of_u64(time >> PACKED_COUNT_BITS),
+                count: count >> (32 - PACKED_COUNT_BITS),
+            })
+            .boxed()
+    }
+
+    #[test]
+    fn packed_should_round_trip() {
+        property(packable()).check(|ts| {
+            let packed = ts.to_packed_u64().expect("pack");
+            Timestamp::<WallMST>::from_packed_u64(packed) == ts
+        })
+    }
+
+    #[test]
+    fn packed_should_order_as_timestamps() {
+        property((packable(), packable())).check(|(ta, tb)| {
+            let (pa, pb) = (
+                ta.to_packed_u64().expect("pack"),
+                tb.to_packed_u64().expect("pack"),
+            );
+            ta.cmp(&tb) == pa.cmp(&pb)
+        })
+    }
+
+    #[test]
+    fn packing_should_refuse_overflow() {
+        let ts = Timestamp::new(0, WallMST::of_u64(1 << PACKED_TIME_BITS), 0);
+        assert!(matches!(
+            ts.to_packed_u64(),
+            Err(Error::PackOverflow { field: "time", .. })
+        ));
+        let ts = Timestamp::new(0, WallMST::of_u64(0), 1 << PACKED_COUNT_BITS);
+        assert!(matches!(
+            ts.to_packed_u64(),
+            Err(Error::PackOverflow { field: "count", .. })
+        ));
+        let ts = Timestamp::new(1, WallMST::of_u64(0), 0);
+        assert!(matches!(
+            ts.to_packed_u64(),
+            Err(Error::PackOverflow { field: "epoch", .. })
+        ));
+    }
+
+    #[test]
+    fn u128_should_round_trip() {
+        property(timestamps(manualts()))
+            .check(|ts| Timestamp::<ManualT>::from_u128(ts.to_u128()) == ts)
+    }
+
+    #[test]
+    fn u128_should_order_as_timestamps() {
+        property((timestamps(manualts()), timestamps(manualts())))
+            .check(|(ta, tb)| ta.cmp(&tb) == ta.to_u128().cmp(&tb.to_u128()))
+    }
+
+    fn crdb_packable() -> Box<dyn GeneratorObject<Item = Timestamp<WallNST>>> {
+        (u64s(), u32s())
+            .map(|(millis, count)| Timestamp {
+                epoch: 0,
+                // Whole milliseconds, within the range of `WallNST`.
+                time: WallNST::of_u64(millis % (u64::MAX / 1_000_000) * 1_000_000),
+                count: count >> (32 - CRDB_LOGICAL_BITS),
+            })
+            .boxed()
+    }
+
+    #[test]
+    fn crdb_should_round_trip_whole_millis() {
+        property(crdb_packable()).check(|ts| {
+            let packed = ts.to_crdb_u64().expect("pack");
+            Timestamp::<WallNST>::from_crdb_u64(packed).expect("unpack") == ts
+        })
+    }
+
+    #[test]
+    fn crdb_should_not_reverse_order() {
+        property((crdb_packable(), crdb_packable())).check(|(ta, tb)| {
+            let (pa, pb) = (
+                ta.to_crdb_u64().expect("pack"),
+                tb.to_crdb_u64().expect("pack"),
+            );
+            ta.cmp(&tb) == pa.cmp(&pb)
+        })
+    }
+
+    #[test]
+    fn crdb_should_match_layout() -> Result<()> {
+        let ts = Timestamp::new(0, WallNST::of_u64(1_234_567_890), 5);
+        assert_eq!(ts.to_crdb_u64()?, 1_234 << 18 | 5);
+        let ts = Timestamp::new(0, WallNST::of_u64(0), 1 << CRDB_LOGICAL_BITS);
+        assert!(matches!(
+            ts.to_crdb_u64(),
+            Err(Error::PackOverflow { field: "count", .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,351 @@
+//! Crash-safe persistence of clock state.
+//!
+//! After a restart, a clock has no memory of the timestamps it issued
+//! before, so if the physical clock has stepped backwards in the meantime it
+//! may issue timestamps that sort before ones already handed out. To avoid
+//! this, `PersistentClock` periodically writes the clock's high-water mark to
+//! a file, and `Clock::recover` resumes from that file in a fresh epoch.
+//!
+//! Alternatively, an `EpochStore` simply counts process restarts, and
+//! `Clock::with_epoch_store` uses a fresh epoch for each one.
+
+use std::convert::TryInto;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::{Clock, ClockSource, ClockState, Error, Result, Timestamp, U64Time};
+
+const STATE_LEN: usize = 20;
+
+fn encode<T: U64Time>(state: &ClockState<T>) -> [u8; STATE_LEN] {
+    let ts = &state.last_observed;
+    let mut res = [0; STATE_LEN];
+    res[0..4].copy_from_slice(&state.epoch.to_be_bytes());
+    res[4..8].copy_from_slice(&ts.epoch.to_be_bytes());
+    res[8..16].copy_from_slice(&ts.time.as_u64().to_be_bytes());
+    res[16..20].copy_from_slice(&ts.count.to_be_bytes());
+    res
+}
+
+fn decode<T: U64Time>(bytes: &[u8]) -> Result<ClockState<T>> {
+    if bytes.len() != STATE_LEN {
+        return Err(Error::CorruptState);
+    }
+    let epoch = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+    let ts_epoch = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
+    let time = u64::from_be_bytes(bytes[8..16].try_into().unwrap());
+    let count = u32::from_be_bytes(bytes[16..20].try_into().unwrap());
+    Ok(ClockState {
+        epoch,
+        last_observed: Timestamp {
+            epoch: ts_epoch,
+            time: T::of_u64(time),
+            count,
+        },
+    })
+}
+
+/// Durably replaces the contents of `path` with `data`, such that after a
+/// crash the file holds either the old or the new contents.
+pub(crate) fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+    let mut tmp_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+        .to_owned();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+
+    let mut file = File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp, path)?;
+
+    // Ensure the rename itself is durable.
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Reads the contents of `path`, or `None` if it does not exist.
+pub(crate) fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Durably writes `state` to `path`, replacing any previous state.
+pub fn save_state<T: U64Time, P: AsRef<Path>>(path: P, state: &ClockState<T>) -> Result<()> {
+    write_atomically(path.as_ref(), &encode(state))
+}
+
+/// Reads a state written by `save_state`, or `None` if `path` does not exist.
+pub fn load_state<T: U64Time, P: AsRef<Path>>(path: P) -> Result<Option<ClockState<T>>> {
+    read_if_exists(path.as_ref())?
+        .map(|data| decode(&data))
+        .transpose()
+}
+
+impl<S: ClockSource> Clock<S>
+where
+    S::Time: U64Time,
+{
+    /// Creates a clock with `src` as the time provider, resuming from the
+    /// state persisted at `path`, if any.
+    ///
+    /// Because timestamps may have been issued after the state was last
+    /// persisted, the recovered clock starts in an epoch after any it had
+    /// previously used. The new epoch is persisted before this returns, so
+    /// repeated restarts never reuse an epoch.
+    pub fn recover<P: AsRef<Path>>(src: S, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut clock = Clock::new(src)?;
+        if let Some(state) = load_state::<S::Time, _>(path)? {
+            let epoch = state
+                .epoch
+                .max(state.last_observed.epoch)
+                .checked_add(1)
+                .ok_or(Error::EpochExhausted)?;
+            clock = Clock::from_state(
+                clock.src,
+                ClockState {
+                    epoch,
+                    last_observed: state.last_observed,
+                },
+            );
+        }
+        save_state(path, &clock.state())?;
+        Ok(clock)
+    }
+}
+
+/// A file-backed counter of process incarnations, for use as a clock epoch.
+///
+/// The counter is stored as decimal text, so that operators may inspect or
+/// adjust it by hand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochStore {
+    path: PathBuf,
+}
+
+impl EpochStore {
+    /// Creates a store that keeps the epoch in the file at `path`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        EpochStore { path: path.into() }
+    }
+
+    /// Returns the most recently stored epoch, or `None` if none has been
+    /// stored yet.
+    pub fn current(&self) -> Result<Option<u32>> {
+        read_if_exists(&self.path)?
+            .map(|data| {
+                std::str::from_utf8(&data)
+                    .ok()
+                    .and_then(|s| s.trim().parse().ok())
+                    .ok_or(Error::CorruptState)
+            })
+            .transpose()
+    }
+
+    /// Durably stores and returns the next epoch. The first epoch issued
+    /// from a new store is zero.
+    pub fn next_epoch(&self) -> Result<u32> {
+        let epoch = match self.current()? {
+            Some(epoch) => epoch.checked_add(1).ok_or(Error::EpochExhausted)?,
+            None => 0,
+        };
+        write_atomically(&self.path, format!("{}\n", epoch).as_bytes())?;
+        Ok(epoch)
+    }
+}
+
+impl<S: ClockSource> Clock<S> {
+    /// Creates a clock with `src` as the time provider, in a new epoch taken
+    /// from `store`. Calling this once at process start ensures that each
+    /// incarnation of the process issues timestamps in a distinct epoch.
+    pub fn with_epoch_store(src: S, store: &EpochStore) -> Result<Self> {
+        let epoch = store.next_epoch()?;
+        let mut clock = Clock::new(src)?;
+        clock.set_epoch(epoch);
+        Ok(clock)
+    }
+}
+
+/// A wrapper around `Clock` that persists its state to a file.
+///
+/// The state is written whenever a timestamp is issued in an epoch that has
+/// not yet been persisted, and otherwise at most once per `interval`. Use
+/// `Clock::recover` to resume from the persisted state after a restart.
+#[derive(Debug)]
+pub struct PersistentClock<S: ClockSource> {
+    clock: Clock<S>,
+    path: PathBuf,
+    interval: Duration,
+    last_persisted: Option<(Instant, u32)>,
+}
+
+impl<S: ClockSource> PersistentClock<S>
+where
+    S::Time: U64Time,
+{
+    /// Wraps `clock`, persisting its state to `path` every `interval`.
+    pub fn new<P: Into<PathBuf>>(clock: Clock<S>, path: P, interval: Duration) -> Self {
+        PersistentClock {
+            clock,
+            path: path.into(),
+            interval,
+            last_persisted: None,
+        }
+    }
+
+    /// Creates a unique monotonic timestamp suitable for annotating messages
+    /// we send, persisting the clock state if required.
+    pub fn now(&mut self) -> Result<Timestamp<S::Time>> {
+        let ts = self.clock.now()?;
+        let due = match self.last_persisted {
+            None => true,
+            Some((at, epoch)) => epoch != ts.epoch || at.elapsed() >= self.interval,
+        };
+        if due {
+            self.persist()?;
+        }
+        Ok(ts)
+    }
+
+    /// Accepts a timestamp from an incoming message. See `Clock::observe`.
+    pub fn observe(&mut self, msg: &Timestamp<S::Time>) -> Result<()> {
+        self.clock.observe(msg)
+    }
+
+    /// Writes the clock's current state to the file immediately.
+    pub fn persist(&mut self) -> Result<()> {
+        let state = self.clock.state();
+        save_state(&self.path, &state)?;
+        self.last_persisted = Some((Instant::now(), state.last_observed.epoch));
+        Ok(())
+    }
+
+    /// Extract the inner `Clock`
+    pub fn into_inner(self) -> Clock<S> {
+        self.clock
+    }
+
+    /// Get a reference to the inner `Clock`
+    pub fn inner(&self) -> &Clock<S> {
+        &self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::{ManualClock, ManualT};
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    #[test]
+    fn should_round_trip_via_encoding() {
+        property((u32s(), timestamps(manualts()))).check(|(epoch, last_observed)| {
+            let state = ClockState {
+                epoch,
+                last_observed,
+            };
+            decode::<ManualT>(&encode(&state)).expect("decode") == state
+        });
+    }
+
+    #[test]
+    fn should_reject_truncated_state() {
+        assert!(decode::<ManualT>(&[0; STATE_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn should_start_fresh_without_state() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("clock");
+        let mut clock = Clock::recover(ManualClock::new(10), &path)?;
+        assert_eq!(clock.now()?.epoch, 0);
+        assert!(path.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_regress_after_recovery() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("clock");
+
+        let clock = Clock::recover(ManualClock::new(100), &path)?;
+        let mut clock = PersistentClock::new(clock, &path, Duration::from_secs(3600));
+        let _ = clock.now()?;
+        // Issued after the last persist, so unknown to the recovered clock.
+        let last = clock.now()?;
+
+        // Restart with the physical clock having stepped backwards.
+        let mut recovered = Clock::recover(ManualClock::new(1), &path)?;
+        let t = recovered.now()?;
+        assert!(t > last, "{} > {}", t, last);
+        Ok(())
+    }
+
+    #[test]
+    fn epoch_store_should_count_restarts() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = EpochStore::new(dir.path().join("epoch"));
+        assert_eq!(store.current()?, None);
+
+        let mut first = Clock::with_epoch_store(ManualClock::new(100), &store)?;
+        let a = first.now()?;
+        let mut second = Clock::with_epoch_store(ManualClock::new(1), &store)?;
+        let b = second.now()?;
+
+        assert_eq!((a.epoch, b.epoch), (0, 1));
+        assert!(a < b);
+        assert_eq!(store.current()?, Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn epoch_store_should_reject_corrupt_contents() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("epoch");
+        fs::write(&path, "not an epoch")?;
+        assert!(matches!(
+            EpochStore::new(path).next_epoch(),
+            Err(Error::CorruptState)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn should_persist_new_epochs_immediately() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("clock");
+
+        let clock = Clock::recover(ManualClock::new(100), &path)?;
+        let mut clock = PersistentClock::new(clock, &path, Duration::from_secs(3600));
+        let _ = clock.now()?;
+        clock.observe(&Timestamp {
+            epoch: 7,
+            time: ManualT::from(1),
+            count: 0,
+        })?;
+        let last = clock.now()?;
+        assert_eq!(last.epoch, 7);
+
+        let mut recovered = Clock::recover(ManualClock::new(1), &path)?;
+        let t = recovered.now()?;
+        assert!(t > last, "{} > {}", t, last);
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::{AsDuration, Error, Result, Timestamp};
+
+/// Decides whether an `OffsetLimiter` should accept an observed timestamp,
+/// given how far its physical time is from ours. `T` and `D` are the time
+/// and delta types of the limiter's clock source.
+///
+/// For sources whose times convert to a `Duration`, any
+/// `FnMut(&Timestamp<Duration>, Duration, Ordering) -> Result<()>` is an
+/// `OffsetPolicy`, which allows eg: adaptive policies that compare each
+/// offset against those recently seen from peers.
+pub trait OffsetPolicy<T, D> {
+    /// Checks `timestamp`, whose physical time is `offset` away from ours,
+    /// in the given `direction` (`Ordering::Greater` if it is ahead of us).
+    /// Returns an error such as `Error::OffsetTooGreat` to refuse it.
+    fn check(&mut self, timestamp: &Timestamp<T>, offset: &D, direction: Ordering) -> Result<()>;
+
+    /// Returns `offset` as a `Duration`, if it has one, so that the limiter
+    /// can record it in its statistics. By default offsets are not recorded.
+    fn measure(&self, offset: &D) -> Option<Duration> {
+        let _ = offset;
+        None
+    }
+}
+
+impl<T, D, F> OffsetPolicy<T, D> for F
+where
+    T: AsDuration,
+    D: AsDuration,
+    F: FnMut(&Timestamp<Duration>, Duration, Ordering) -> Result<()>,
+{
+    fn check(&mut self, timestamp: &Timestamp<T>, offset: &D, direction: Ordering) -> Result<()> {
+        self(
+            &as_duration_timestamp(timestamp),
+            offset.as_duration(),
+            direction,
+        )
+    }
+
+    fn measure(&self, offset: &D) -> Option<Duration> {
+        Some(offset.as_duration())
+    }
+}
+
+/// The default `OffsetPolicy`, which refuses timestamps more than a fixed
+/// offset ahead of, or optionally behind, our physical time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedOffsetPolicy<D> {
+    /// The greatest offset ahead of our physical time that we will accept.
+    pub max_forward: D,
+    /// The greatest offset behind our physical time that we will accept,
+    /// if limited.
+    pub max_backward: Option<D>,
+}
+
+impl<D> FixedOffsetPolicy<D> {
+    /// Refuses timestamps more than `max_forward` ahead of our physical time.
+    pub fn new(max_forward: D) -> Self {
+        FixedOffsetPolicy {
+            max_forward,
+            max_backward: None,
+        }
+    }
+}
+
+impl<T: AsDuration, D: AsDuration> OffsetPolicy<T, D> for FixedOffsetPolicy<D> {
+    fn check(&mut self, timestamp: &Timestamp<T>, offset: &D, direction: Ordering) -> Result<()> {
+        let offset = offset.as_duration();
+        if direction == Ordering::Greater {
+            let limit = self.max_forward.as_duration();
+            if offset > limit {
+                return Err(Error::OffsetTooGreat {
+                    observed: offset,
+                    limit,
+                    timestamp: as_duration_timestamp(timestamp),
+                });
+            }
+        } else if let Some(ref limit) = self.max_backward {
+            let limit = limit.as_duration();
+            if offset > limit {
+                return Err(Error::OffsetTooFarBehind {
+                    observed: offset,
+                    limit,
+                    timestamp: as_duration_timestamp(timestamp),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn measure(&self, offset: &D) -> Option<Duration> {
+        Some(offset.as_duration())
+    }
+}
+
+/// An `OffsetPolicy` whose limit follows the reported error bound of our
+/// clock, such as the maximum error that the kernel reports whilst NTP or
+/// chrony is disciplining it, plus a configured slack. This adapts to how
+/// well synchronized we actually are, rather than relying on a constant that
+/// is either too tight or too loose.
+///
+/// The bound is read from `error_bound` when first needed, and then again
+/// whenever it is more than `refresh_interval` old. Timestamps behind our
+/// physical time are always accepted.
+#[derive(Debug, Clone)]
+pub struct AdaptiveOffsetPolicy<F> {
+    error_bound: F,
+    slack: Duration,
+    refresh_interval: Duration,
+    current: Option<(Duration, Instant)>,
+}
+
+impl<F: FnMut() -> Result<Duration>> AdaptiveOffsetPolicy<F> {
+    /// Refuses timestamps ahead of our physical time by more than the bound
+    /// returned by `error_bound` plus `slack`.
+    pub fn new(error_bound: F, slack: Duration, refresh_interval: Duration) -> Self {
+        AdaptiveOffsetPolicy {
+            error_bound,
+            slack,
+            refresh_interval,
+            current: None,
+        }
+    }
+
+    /// Returns the effective limit on forward offsets, reading the error
+    /// bound again if it is stale.
+    pub fn max_forward(&mut self) -> Result<Duration> {
+        match self.current {
+            Some((limit, read_at)) if read_at.elapsed() < self.refresh_interval => Ok(limit),
+            _ => {
+                let limit = (self.error_bound)()?.saturating_add(self.slack);
+                self.current = Some((limit, Instant::now()));
+                Ok(limit)
+            }
+        }
+    }
+}
+
+/// Reads the kernel's maximum clock error; see `NtpStatus`.
+#[cfg(all(feature = "ntp", target_os = "linux"))]
+pub type KernelErrorBound = fn() -> Result<Duration>;
+
+#[cfg(all(feature = "ntp", target_os = "linux"))]
+impl AdaptiveOffsetPolicy<KernelErrorBound> {
+    /// Refuses timestamps ahead of our physical time by more than the
+    /// kernel's reported maximum error plus `slack`.
+    pub fn kernel(slack: Duration, refresh_interval: Duration) -> Self {
+        AdaptiveOffsetPolicy::new(
+            || Ok(crate::NtpStatus::read()?.max_error),
+            slack,
+            refresh_interval,
+        )
+    }
+}
+
+impl<T, D, F> OffsetPolicy<T, D> for AdaptiveOffsetPolicy<F>
+where
+    T: AsDuration,
+    D: AsDuration,
+    F: FnMut() -> Result<Duration>,
+{
+    fn check(&mut self, timestamp: &Timestamp<T>, offset: &D, direction: Ordering) -> Result<()> {
+        if direction != Ordering::Greater {
+            return Ok(());
+        }
+        let offset = offset.as_duration();
+        let limit = self.max_forward()?;
+        if offset > limit {
+            return Err(Error::OffsetTooGreat {
+                observed: offset,
+                limit,
+                timestamp: as_duration_timestamp(timestamp),
+            });
+        }
+        Ok(())
+    }
+
+    fn measure(&self, offset: &D) -> Option<Duration> {
+        Some(offset.as_duration())
+    }
+}
+
+fn as_duration_timestamp<T: AsDuration>(ts: &Timestamp<T>) -> Timestamp<Duration> {
+    Timestamp {
+        epoch: ts.epoch,
+        time: ts.time.as_duration(),
+        count: ts.count,
+    }
+}
@@ -0,0 +1,157 @@
+use std::convert::TryInto;
+use std::error;
+use std::fmt;
+
+use bytes::{BufMut, BytesMut};
+use postgres_types::{accepts, to_sql_checked, FromSql, IsNull, ToSql, Type};
+
+use crate::{ByteOrdered, Timestamp, U64Time};
+
+type BoxError = Box<dyn error::Error + Sync + Send>;
+
+// Postgres numerics are written in base 10000, most significant digit first.
+const NUMERIC_BASE: u128 = 10_000;
+const NUMERIC_POS: u16 = 0x0000;
+
+/// Timestamps are stored as the 16 bytes of their `ByteOrdered` encoding
+/// in `BYTEA` columns, or as the value of `to_u128` in `NUMERIC` columns. In
+/// either case, the column's ordering, and so that of any index on it,
+/// matches the ordering of the timestamps.
+impl<T: U64Time + fmt::Debug> ToSql for Timestamp<T> {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, BoxError> {
+        if *ty == Type::NUMERIC {
+            write_numeric(self.to_u128(), out);
+        } else {
+            out.put_slice(&self.to_ordered_bytes());
+        }
+        Ok(IsNull::No)
+    }
+
+    accepts!(BYTEA, NUMERIC);
+
+    to_sql_checked!();
+}
+
+impl<'a, T: U64Time> FromSql<'a> for Timestamp<T> {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, BoxError> {
+        if *ty == Type::NUMERIC {
+            return Ok(Timestamp::from_u128(read_numeric(raw)?));
+        }
+        let bytes = raw
+            .try_into()
+            .map_err(|_| format!("expected 16 bytes of timestamp, found {}", raw.len()))?;
+        Ok(Timestamp::from_ordered_bytes(bytes))
+    }
+
+    accepts!(BYTEA, NUMERIC);
+}
+
+fn write_numeric(mut val: u128, out: &mut BytesMut) {
+    let mut digits = Vec::new();
+    while val > 0 {
+        digits.push((val % NUMERIC_BASE) as i16);
+        val /= NUMERIC_BASE;
+    }
+    let weight = digits.len().saturating_sub(1) as i16;
+    // Trailing zero digits are implied by the weight.
+    let zeros = digits.iter().take_while(|&&d| d == 0).count();
+    digits.drain(..zeros);
+    out.put_i16(digits.len() as i16);
+    out.put_i16(weight);
+    out.put_u16(NUMERIC_POS);
+    out.put_u16(0);
+    for digit in digits.iter().rev() {
+        out.put_i16(*digit);
+    }
+}
+
+fn read_numeric(raw: &[u8]) -> Result<u128, BoxError> {
+    let field = |i: usize| -> Result<i16, BoxError> {
+        raw.get(i * 2..i * 2 + 2)
+            .map(|b| i16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(|| "truncated numeric".into())
+    };
+    let (ndigits, weight, sign) = (field(0)?, field(1)?, field(2)? as u16);
+    if sign != NUMERIC_POS {
+        return Err("timestamp numeric must not be negative or NaN".into());
+    }
+    let mut val = 0u128;
+    for i in 0..ndigits.max(0) as usize {
+        let digit = field(4 + i)?;
+        if !(0..NUMERIC_BASE as i16).contains(&digit) {
+            return Err("invalid numeric digit".into());
+        }
+        if i as i16 > weight {
+            if digit != 0 {
+                return Err("timestamp numeric must be an integer".into());
+            }
+            continue;
+        }
+        val = val
+            .checked_mul(NUMERIC_BASE)
+            .and_then(|v| v.checked_add(digit as u128))
+            .ok_or("timestamp numeric out of range")?;
+    }
+    for _ in ndigits.max(0)..weight.saturating_add(1) {
+        val = val
+            .checked_mul(NUMERIC_BASE)
+            .ok_or("timestamp numeric out of range")?;
+    }
+    Ok(val)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::{ManualT, WallNST};
+    use suppositions::*;
+
+    fn to_sql<T: U64Time + fmt::Debug>(ts: &Timestamp<T>, ty: &Type) -> BytesMut {
+        let mut out = BytesMut::new();
+        ts.to_sql(ty, &mut out).expect("to-sql");
+        out
+    }
+
+    #[test]
+    fn should_round_trip_via_bytea_and_numeric() {
+        property(timestamps(manualts())).check(|ts| {
+            [Type::BYTEA, Type::NUMERIC].iter().all(|ty| {
+                let raw = to_sql(&ts, ty);
+                Timestamp::<ManualT>::from_sql(ty, &raw).expect("from-sql") == ts
+            })
+        });
+    }
+
+    #[test]
+    fn numeric_should_use_postgres_layout() {
+        // 1 * 2^32 + 2 = 4294967298, ie: 42'9496'7298.
+        let ts = Timestamp::new(0, WallNST::of_u64(1), 2);
+        let raw = to_sql(&ts, &Type::NUMERIC);
+        let expected: &[i16] = &[3, 2, 0, 0, 42, 9496, 7298];
+        let expected: Vec<u8> = expected.iter().flat_map(|d| d.to_be_bytes()).collect();
+        assert_eq!(&raw[..], &expected[..]);
+
+        // 10^8, with trailing zero digits implied by the weight.
+        let ts = Timestamp::new(0, WallNST::of_u64(0), 100_000_000);
+        let raw = to_sql(&ts, &Type::NUMERIC);
+        assert_eq!(&raw[..], &[0, 1, 0, 2, 0, 0, 0, 0, 0, 1][..]);
+        assert_eq!(
+            Timestamp::<WallNST>::from_sql(&Type::NUMERIC, &raw).expect("from-sql"),
+            ts
+        );
+    }
+
+    #[test]
+    fn should_refuse_invalid_values() {
+        assert!(Timestamp::<WallNST>::from_sql(&Type::BYTEA, &[0; 15]).is_err());
+        // -1
+        let negative = [0, 1, 0, 0, 0x40, 0, 0, 0, 0, 1];
+        assert!(Timestamp::<WallNST>::from_sql(&Type::NUMERIC, &negative).is_err());
+        // 1.5
+        let fractional = [0, 2, 0, 0, 0, 0, 0, 1, 0, 1, 0x13, 0x88];
+        assert!(Timestamp::<WallNST>::from_sql(&Type::NUMERIC, &fractional).is_err());
+        assert!(<Timestamp<WallNST> as ToSql>::accepts(&Type::BYTEA));
+        assert!(!<Timestamp<WallNST> as ToSql>::accepts(&Type::TEXT));
+    }
+}
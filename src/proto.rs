@@ -0,0 +1,113 @@
+//! Protobuf messages for timestamps, as defined in
+//! `proto/hybrid_clocks.proto`, so that gRPC services can carry them.
+//!
+//! Timestamps convert to and from the `Timestamp` message, which records
+//! which kind of clock source the ticks came from. Converting back fails with
+//! `Error::SourceKindMismatch` if that does not match the expected time type.
+
+use std::convert::TryFrom;
+
+use crate::{Error, ManualT, U64Time, WallMST, WallMillisT, WallNST, WallUST};
+
+/// The clock source that a timestamp's ticks were read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum SourceKind {
+    Unspecified = 0,
+    WallNs = 1,
+    WallMs = 2,
+    WallUs = 3,
+    WallMillis = 4,
+    Manual = 5,
+}
+
+/// A hybrid logical clock timestamp.
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct Timestamp {
+    #[prost(uint32, tag = "1")]
+    pub epoch: u32,
+    #[prost(uint64, tag = "2")]
+    pub ticks: u64,
+    #[prost(uint32, tag = "3")]
+    pub count: u32,
+    #[prost(enumeration = "SourceKind", tag = "4")]
+    pub source: i32,
+}
+
+/// Time types that may be carried in a `Timestamp` message.
+pub trait ProtoTime: U64Time {
+    /// The kind of source recorded for this time type.
+    const KIND: SourceKind;
+}
+
+impl ProtoTime for WallNST {
+    const KIND: SourceKind = SourceKind::WallNs;
+}
+impl ProtoTime for WallMST {
+    const KIND: SourceKind = SourceKind::WallMs;
+}
+impl ProtoTime for WallUST {
+    const KIND: SourceKind = SourceKind::WallUs;
+}
+impl ProtoTime for WallMillisT {
+    const KIND: SourceKind = SourceKind::WallMillis;
+}
+impl ProtoTime for ManualT {
+    const KIND: SourceKind = SourceKind::Manual;
+}
+
+impl<T: ProtoTime> From<crate::Timestamp<T>> for Timestamp {
+    fn from(ts: crate::Timestamp<T>) -> Self {
+        Timestamp {
+            epoch: ts.epoch,
+            ticks: ts.time.as_u64(),
+            count: ts.count,
+            source: T::KIND as i32,
+        }
+    }
+}
+
+impl<T: ProtoTime> TryFrom<Timestamp> for crate::Timestamp<T> {
+    type Error = Error;
+    fn try_from(msg: Timestamp) -> Result<Self, Error> {
+        if msg.source != T::KIND as i32 {
+            return Err(Error::SourceKindMismatch {
+                expected: T::KIND as i32,
+                found: msg.source,
+            });
+        }
+        Ok(crate::Timestamp {
+            epoch: msg.epoch,
+            time: T::of_u64(msg.ticks),
+            count: msg.count,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    #[test]
+    fn should_round_trip_via_protobuf() -> crate::Result<()> {
+        let ts = crate::Timestamp::new(2, WallNST::of_u64(1_558_805_131_923_316_000), 5);
+        let bytes = Timestamp::from(ts).encode_to_vec();
+        let msg = Timestamp::decode(&bytes[..]).expect("decode");
+        assert_eq!(msg.source(), SourceKind::WallNs);
+        assert_eq!(crate::Timestamp::<WallNST>::try_from(msg)?, ts);
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_mismatched_source() {
+        let msg = Timestamp::from(crate::Timestamp::new(0, WallMST::of_u64(1), 0));
+        assert!(matches!(
+            crate::Timestamp::<WallNST>::try_from(msg),
+            Err(Error::SourceKindMismatch {
+                expected: 1,
+                found: 2
+            })
+        ));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{Timestamp, U64Time};
+
+/// A timestamp with a fixed, padding-free, native-endian layout of 16
+/// bytes: the epoch, the time ticks and the count. Arrays of these may be
+/// read in place from memory-mapped files or shared memory, via
+/// `bytemuck::Pod` with the `bytemuck` feature, or zerocopy's `FromBytes`
+/// and `IntoBytes` with the `zerocopy` feature.
+///
+/// As the struct is packed, fields may be unaligned, so they should be
+/// copied out rather than borrowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[cfg_attr(
+    feature = "zerocopy",
+    derive(
+        zerocopy::FromBytes,
+        zerocopy::IntoBytes,
+        zerocopy::Immutable,
+        zerocopy::KnownLayout
+    )
+)]
+#[repr(C, packed)]
+pub struct RawTimestamp {
+    /// An epoch counter.
+    pub epoch: u32,
+    /// The time ticks, as per `U64Time::as_u64`.
+    pub time: u64,
+    /// The logical count.
+    pub count: u32,
+}
+
+impl RawTimestamp {
+    /// Returns the timestamp, interpreting the ticks as times of type `T`.
+    pub fn to_timestamp<T: U64Time>(&self) -> Timestamp<T> {
+        Timestamp {
+            epoch: self.epoch,
+            time: T::of_u64(self.time),
+            count: self.count,
+        }
+    }
+}
+
+impl<T: U64Time> From<Timestamp<T>> for RawTimestamp {
+    fn from(ts: Timestamp<T>) -> Self {
+        RawTimestamp {
+            epoch: ts.epoch,
+            time: ts.time.as_u64(),
+            count: ts.count,
+        }
+    }
+}
+
+impl<T: U64Time> From<RawTimestamp> for Timestamp<T> {
+    fn from(raw: RawTimestamp) -> Self {
+        raw.to_timestamp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::ManualT;
+    use suppositions::*;
+
+    #[test]
+    fn should_have_no_padding() {
+        assert_eq!(std::mem::size_of::<RawTimestamp>(), 16);
+        assert_eq!(std::mem::align_of::<RawTimestamp>(), 1);
+    }
+
+    #[test]
+    fn should_round_trip_via_raw() {
+        property(timestamps(manualts()))
+            .check(|ts| Timestamp::<ManualT>::from(RawTimestamp::from(ts)) == ts);
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn should_cast_slices_with_bytemuck() {
+        use crate::WallNST;
+
+        let stamps = [
+            RawTimestamp::from(Timestamp::new(1, WallNST::of_u64(2), 3)),
+            RawTimestamp::from(Timestamp::new(4, WallNST::of_u64(5), 6)),
+        ];
+        let bytes: &[u8] = bytemuck::cast_slice(&stamps);
+        assert_eq!(bytes.len(), 32);
+        // Unaligned reads are fine, as the struct is packed.
+        let read: &[RawTimestamp] = bytemuck::cast_slice(&bytes[16..]);
+        assert_eq!(
+            read[0].to_timestamp(),
+            Timestamp::new(4, WallNST::of_u64(5), 6)
+        );
+    }
+
+    #[cfg(feature = "zerocopy")]
+    #[test]
+    fn should_read_slices_with_zerocopy() {
+        use crate::WallNST;
+        use zerocopy::{FromBytes, IntoBytes};
+
+        let stamps = [
+            RawTimestamp::from(Timestamp::new(1, WallNST::of_u64(2), 3)),
+            RawTimestamp::from(Timestamp::new(4, WallNST::of_u64(5), 6)),
+        ];
+        let bytes = stamps.as_bytes();
+        let read = <[RawTimestamp]>::ref_from_bytes(&bytes[16..]).expect("ref_from_bytes");
+        assert_eq!(read.len(), 1);
+        assert_eq!(
+            read[0].to_timestamp(),
+            Timestamp::new(4, WallNST::of_u64(5), 6)
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use std::borrow::Cow;
+
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+
+/// Describes whichever serde representation is enabled for human-readable
+/// formats: an `[epoch, time, count]` array, or with the `serde-named`
+/// feature, an object with those fields.
+impl<T: JsonSchema> JsonSchema for crate::Timestamp<T> {
+    fn schema_name() -> Cow<'static, str> {
+        format!("Timestamp_{}", T::schema_name()).into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let epoch = generator.subschema_for::<u32>();
+        let time = generator.subschema_for::<T>();
+        let count = generator.subschema_for::<u32>();
+        if cfg!(feature = "serde-named") {
+            json_schema!({
+                "type": "object",
+                "properties": {
+                    "epoch": epoch,
+                    "time": time,
+                    "count": count,
+                },
+                "required": ["epoch", "time", "count"],
+            })
+        } else {
+            json_schema!({
+                "type": "array",
+                "prefixItems": [epoch, time, count],
+                "minItems": 3,
+                "maxItems": 3,
+            })
+        }
+    }
+}
+
+// The sample is serialized to check that the schema matches it.
+#[cfg(all(test, feature = "serialization"))]
+mod tests {
+    use crate::{Timestamp, WallNST};
+
+    #[test]
+    fn should_describe_serde_representation() {
+        let schema = schemars::schema_for!(Timestamp<WallNST>);
+        let json = serde_json::to_value(&schema).expect("to-json");
+        let sample =
+            serde_json::to_value(Timestamp::new(1, WallNST::of_u64(2), 3)).expect("to-json");
+        if cfg!(feature = "serde-named") {
+            assert_eq!(json["type"], "object");
+            assert!(sample.is_object());
+        } else {
+            assert_eq!(json["type"], "array");
+            assert!(sample.is_array());
+        }
+        assert_eq!(json["title"], "Timestamp_WallNST");
+    }
+}
@@ -0,0 +1,106 @@
+//! Serializes a `Timestamp` as exactly 16 bytes, for use with
+//! `#[serde(with = "hybrid_clocks::serde_fixed")]`.
+//!
+//! The bytes are the order-preserving encoding of `ByteOrdered`: the epoch,
+//! the time ticks and the count, each big-endian. This layout is stable, and
+//! will not change between releases.
+//!
+//! The bytes are written as a fixed-size tuple rather than a byte string
+//! (as `serde_bytes` would), so that binary formats such as bincode and
+//! postcard write them without a length prefix, and payloads stay
+//! byte-comparable.
+
+use std::convert::TryInto;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de;
+use serde::ser::{self, SerializeTuple};
+
+use crate::{ByteOrdered, Timestamp, U64Time};
+
+const LEN: usize = 16;
+
+/// Serializes `ts` as its 16 byte encoding.
+pub fn serialize<T: U64Time, S: ser::Serializer>(
+    ts: &Timestamp<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut tuple = serializer.serialize_tuple(LEN)?;
+    for byte in ts.to_ordered_bytes().iter() {
+        tuple.serialize_element(byte)?;
+    }
+    tuple.end()
+}
+
+/// Deserializes a timestamp from its 16 byte encoding, given either as a
+/// tuple of bytes, or as a byte string.
+pub fn deserialize<'de, T: U64Time, D: de::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Timestamp<T>, D::Error> {
+    struct Visitor<T>(PhantomData<T>);
+    impl<'de, T: U64Time> de::Visitor<'de> for Visitor<T> {
+        type Value = Timestamp<T>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "{} bytes of timestamp", LEN)
+        }
+
+        fn visit_seq<V: de::SeqAccess<'de>>(self, mut seq: V) -> Result<Timestamp<T>, V::Error> {
+            let mut bytes = [0u8; LEN];
+            for (i, byte) in bytes.iter_mut().enumerate() {
+                *byte = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+            }
+            Ok(Timestamp::from_ordered_bytes(&bytes))
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Timestamp<T>, E> {
+            let bytes = v
+                .try_into()
+                .map_err(|_| de::Error::invalid_length(v.len(), &self))?;
+            Ok(Timestamp::from_ordered_bytes(&bytes))
+        }
+    }
+    deserializer.deserialize_tuple(LEN, Visitor(PhantomData))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{manualts, timestamps};
+    use crate::{ManualT, Timestamp};
+    use suppositions::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Key(#[serde(with = "crate::serde_fixed")] Timestamp<ManualT>);
+
+    #[test]
+    fn should_encode_exactly_sixteen_bytes() {
+        let key = Key(Timestamp::new(1, ManualT::from(0x0203_0405_0607_0809), 10));
+        let bytes = bincode::serialize(&key).expect("serialize");
+        // This layout is stable across releases.
+        assert_eq!(bytes, [0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 0, 0, 10]);
+        assert_eq!(
+            bincode::deserialize::<Key>(&bytes).expect("deserialize"),
+            key
+        );
+    }
+
+    #[test]
+    fn should_order_as_timestamps() {
+        property((timestamps(manualts()), timestamps(manualts()))).check(|(ta, tb)| {
+            let ba = bincode::serialize(&Key(ta)).expect("serialize");
+            let bb = bincode::serialize(&Key(tb)).expect("serialize");
+            ta.cmp(&tb) == ba.cmp(&bb)
+        })
+    }
+
+    #[test]
+    fn should_round_trip_via_json() {
+        property(timestamps(manualts())).check(|ts| {
+            let json = serde_json::to_string(&Key(ts)).expect("to-json");
+            serde_json::from_str::<Key>(&json).expect("from-json") == Key(ts)
+        })
+    }
+}
@@ -1,10 +1,96 @@
+use std::fmt;
+use std::marker::PhantomData;
+
 use serde::{de, ser};
 
+use crate::ClockSource;
+
 #[derive(Serialize, Deserialize)]
 struct Timestamp<T>(u32, T, u32);
 
+#[derive(Serialize)]
+#[serde(rename = "Timestamp")]
+struct NamedTimestamp<T> {
+    epoch: u32,
+    time: T,
+    count: u32,
+}
+
+#[cfg(feature = "serde-named")]
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum Field {
+    Epoch,
+    Time,
+    Count,
+}
+
+struct TimestampVisitor<T>(PhantomData<T>);
+
+impl<'de, T: de::Deserialize<'de>> de::Visitor<'de> for TimestampVisitor<T> {
+    type Value = crate::Timestamp<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a timestamp")
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let epoch = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let time = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let count = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        Ok(crate::Timestamp { epoch, time, count })
+    }
+
+    #[cfg(feature = "serde-named")]
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (mut epoch, mut time, mut count) = (None, None, None);
+        while let Some(key) = map.next_key()? {
+            match key {
+                Field::Epoch if epoch.is_some() => return Err(de::Error::duplicate_field("epoch")),
+                Field::Time if time.is_some() => return Err(de::Error::duplicate_field("time")),
+                Field::Count if count.is_some() => return Err(de::Error::duplicate_field("count")),
+                Field::Epoch => epoch = Some(map.next_value()?),
+                Field::Time => time = Some(map.next_value()?),
+                Field::Count => count = Some(map.next_value()?),
+            }
+        }
+        Ok(crate::Timestamp {
+            epoch: epoch.ok_or_else(|| de::Error::missing_field("epoch"))?,
+            time: time.ok_or_else(|| de::Error::missing_field("time"))?,
+            count: count.ok_or_else(|| de::Error::missing_field("count"))?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ClockState<TS>(u32, TS);
+
+#[derive(Serialize, Deserialize)]
+struct CompactTimestamp<T>(T, u32);
+
+#[derive(Serialize, Deserialize)]
+struct NodeTimestamp<TS, Id>(TS, Id);
+
+/// Timestamps are serialized as an `(epoch, time, count)` tuple, or with the
+/// `serde-named` feature, as a struct with named fields for human-readable
+/// formats such as JSON. With the feature enabled, human-readable formats
+/// accept either shape when deserializing.
 impl<T: ser::Serialize + Copy> ser::Serialize for crate::Timestamp<T> {
     fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if cfg!(feature = "serde-named") && serializer.is_human_readable() {
+            return NamedTimestamp {
+                epoch: self.epoch,
+                time: self.time,
+                count: self.count,
+            }
+            .serialize(serializer);
+        }
         self::Timestamp(self.epoch, self.time, self.count).serialize(serializer)
     }
 }
@@ -14,7 +100,196 @@ impl<'de, T: de::Deserialize<'de>> de::Deserialize<'de> for crate::Timestamp<T>
     where
         D: de::Deserializer<'de>,
     {
-        let self::Timestamp(epoch, time, count) = de::Deserialize::deserialize(deserializer)?;
-        Ok(crate::Timestamp { epoch, time, count })
+        let visitor = TimestampVisitor(PhantomData);
+        if cfg!(feature = "serde-named") && deserializer.is_human_readable() {
+            return deserializer.deserialize_any(visitor);
+        }
+        deserializer.deserialize_tuple_struct("Timestamp", 3, visitor)
+    }
+}
+
+impl<T: ser::Serialize + Copy> ser::Serialize for crate::CompactTimestamp<T> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self::CompactTimestamp(self.time, self.count).serialize(serializer)
+    }
+}
+
+impl<'de, T: de::Deserialize<'de>> de::Deserialize<'de> for crate::CompactTimestamp<T> {
+    fn deserialize<D>(
+        deserializer: D,
+    ) -> ::std::result::Result<crate::CompactTimestamp<T>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let self::CompactTimestamp(time, count) = de::Deserialize::deserialize(deserializer)?;
+        Ok(crate::CompactTimestamp { time, count })
+    }
+}
+
+impl<T, Id> ser::Serialize for crate::CausalContext<T, Id>
+where
+    T: ser::Serialize + Ord + Copy,
+    Id: ser::Serialize + Ord + Clone,
+{
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, T, Id> de::Deserialize<'de> for crate::CausalContext<T, Id>
+where
+    T: de::Deserialize<'de> + Ord + Copy,
+    Id: de::Deserialize<'de> + Ord + Clone,
+{
+    fn deserialize<D>(
+        deserializer: D,
+    ) -> ::std::result::Result<crate::CausalContext<T, Id>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let entries: Vec<(Id, crate::Timestamp<T>)> = de::Deserialize::deserialize(deserializer)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+/// Node timestamps are serialized as a `(timestamp, node)` tuple.
+impl<T: ser::Serialize + Copy, Id: ser::Serialize + Copy> ser::Serialize
+    for crate::NodeTimestamp<T, Id>
+{
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self::NodeTimestamp(self.timestamp, self.node).serialize(serializer)
+    }
+}
+
+impl<'de, T: de::Deserialize<'de>, Id: de::Deserialize<'de>> de::Deserialize<'de>
+    for crate::NodeTimestamp<T, Id>
+{
+    fn deserialize<D>(
+        deserializer: D,
+    ) -> ::std::result::Result<crate::NodeTimestamp<T, Id>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let self::NodeTimestamp(timestamp, node) = de::Deserialize::deserialize(deserializer)?;
+        Ok(crate::NodeTimestamp { timestamp, node })
+    }
+}
+
+impl<T: ser::Serialize + Copy> ser::Serialize for crate::ClockState<T> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self::ClockState(self.epoch, self.last_observed).serialize(serializer)
+    }
+}
+
+impl<'de, T: de::Deserialize<'de>> de::Deserialize<'de> for crate::ClockState<T> {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<crate::ClockState<T>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let self::ClockState(epoch, last_observed) = de::Deserialize::deserialize(deserializer)?;
+        Ok(crate::ClockState {
+            epoch,
+            last_observed,
+        })
+    }
+}
+
+/// Serializes the clock's state, as per `Clock::state`.
+impl<Src: ClockSource> ser::Serialize for crate::Clock<Src>
+where
+    Src::Time: ser::Serialize,
+{
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.state().serialize(serializer)
+    }
+}
+
+/// Restores the clock's state with a default-constructed clock source.
+impl<'de, Src: ClockSource + Default> de::Deserialize<'de> for crate::Clock<Src>
+where
+    Src::Time: de::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<crate::Clock<Src>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let state = de::Deserialize::deserialize(deserializer)?;
+        Ok(crate::Clock::from_state(Src::default(), state))
+    }
+}
+
+impl<T: ser::Serialize, D> ser::Serialize for crate::InDomain<T, D> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.inner().serialize(serializer)
+    }
+}
+
+impl<'de, T: de::Deserialize<'de>, D> de::Deserialize<'de> for crate::InDomain<T, D> {
+    fn deserialize<De>(deserializer: De) -> ::std::result::Result<crate::InDomain<T, D>, De::Error>
+    where
+        De: de::Deserializer<'de>,
+    {
+        let time = de::Deserialize::deserialize(deserializer)?;
+        Ok(crate::InDomain::new(time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{manualts, timestamps};
+    use crate::{Timestamp, WallMST, WallMillisT, WallNST, WallUST};
+    use suppositions::*;
+
+    // 2025-01-01T00:00:00Z
+    const SECS: u64 = 1_735_689_600;
+
+    fn postcard_len<T: serde::Serialize + Copy>(time: T) -> usize {
+        let ts = Timestamp::new(1, time, 2);
+        postcard::to_allocvec(&ts).expect("to-postcard").len()
+    }
+
+    #[test]
+    fn postcard_frames_should_be_compact() {
+        assert_eq!(postcard_len(WallNST::of_u64(SECS * 1_000_000_000)), 11);
+        assert_eq!(postcard_len(WallMST::of_u64(SECS << 16)), 9);
+        assert_eq!(postcard_len(WallUST::of_u64(SECS * 1_000_000)), 10);
+        assert_eq!(postcard_len(WallMillisT::of_u64(SECS * 1_000)), 8);
+        let max = Timestamp::new(u32::MAX, WallNST::of_u64(u64::MAX), u32::MAX);
+        assert_eq!(postcard::to_allocvec(&max).expect("to-postcard").len(), 20);
+    }
+
+    #[test]
+    fn postcard_frame_should_be_stable() {
+        let ts = Timestamp::new(1, WallNST::of_u64(300), 2);
+        let bytes = postcard::to_allocvec(&ts).expect("to-postcard");
+        assert_eq!(bytes, [1, 0xac, 0x02, 2]);
+        assert_eq!(
+            postcard::from_bytes::<Timestamp<WallNST>>(&bytes).expect("from-postcard"),
+            ts
+        );
+    }
+
+    #[test]
+    fn should_round_trip_wide_times_via_json() {
+        let ts = Timestamp::new(1, u128::MAX, 2);
+        let s = serde_json::to_string(&ts).expect("to-json");
+        assert_eq!(
+            serde_json::from_str::<Timestamp<u128>>(&s).expect("from-json"),
+            ts
+        );
+    }
+
+    #[test]
+    fn should_report_the_offending_field() {
+        let err = serde_json::from_str::<Timestamp<u64>>(r#"[1,"x",2]"#).unwrap_err();
+        assert!(err.to_string().contains("invalid type: string"), "{}", err);
+    }
+
+    #[test]
+    fn should_round_trip_via_postcard() {
+        property(timestamps(manualts())).check(|ts| {
+            let bytes = postcard::to_allocvec(&ts).expect("to-postcard");
+            ts == postcard::from_bytes(&bytes).expect("from-postcard")
+        });
     }
 }
@@ -0,0 +1,76 @@
+//! Serializes a `Timestamp` as its `Display` string, `epoch:time+count`,
+//! for APIs that want timestamps as opaque strings. Use it with
+//! `#[serde(with = "hybrid_clocks::serde_str")]`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{de, ser};
+
+use crate::Timestamp;
+
+/// Serializes `ts` as its `Display` string.
+pub fn serialize<T: fmt::Display, S: ser::Serializer>(
+    ts: &Timestamp<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(ts)
+}
+
+/// Deserializes a timestamp from a string, as parsed by `FromStr`.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Timestamp<T>, D::Error>
+where
+    T: FromStr,
+    D: de::Deserializer<'de>,
+{
+    struct Visitor<T>(std::marker::PhantomData<T>);
+    impl<'de, T: FromStr> de::Visitor<'de> for Visitor<T> {
+        type Value = Timestamp<T>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a timestamp string of the form epoch:time+count")
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<Timestamp<T>, E> {
+            s.parse().map_err(E::custom)
+        }
+    }
+    deserializer.deserialize_str(Visitor(std::marker::PhantomData))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{manualts, timestamps};
+    use crate::{ManualT, Timestamp};
+    use suppositions::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Msg {
+        #[serde(with = "crate::serde_str")]
+        ts: Timestamp<ManualT>,
+    }
+
+    #[test]
+    fn should_serialize_as_display_string() {
+        let msg = Msg {
+            ts: Timestamp::new(1, ManualT::from(2), 3),
+        };
+        let json = serde_json::to_string(&msg).expect("to-json");
+        assert_eq!(json, r#"{"ts":"1:2+3"}"#);
+        assert_eq!(serde_json::from_str::<Msg>(&json).expect("from-json"), msg);
+    }
+
+    #[test]
+    fn should_round_trip() {
+        property(timestamps(manualts())).check(|ts| {
+            let json = serde_json::to_string(&Msg { ts }).expect("to-json");
+            serde_json::from_str::<Msg>(&json).expect("from-json").ts == ts
+        })
+    }
+
+    #[test]
+    fn should_refuse_malformed_strings() {
+        assert!(serde_json::from_str::<Msg>(r#"{"ts":"1:2"}"#).is_err());
+        assert!(serde_json::from_str::<Msg>(r#"{"ts":[1,2,3]}"#).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::{Clock, ClockSource, Error, Result, Timestamp};
+
+/// How many requests may be queued for a `ClockService` by default before
+/// handles wait for it to catch up.
+pub const DEFAULT_SERVICE_CAPACITY: usize = 1024;
+
+/// Owns a `Clock` on a dedicated tokio task, which serves requests from any
+/// number of `ClockHandle`s over a channel. This avoids sharing the clock
+/// behind a mutex on the hot path of async applications.
+///
+/// Requests are served in the order they are received, so timestamps are
+/// issued in the same order as the calls to `ClockHandle::now` that they
+/// answer.
+#[derive(Debug)]
+pub struct ClockService<S: ClockSource> {
+    handle: ClockHandle<S::Time>,
+    task: JoinHandle<Clock<S>>,
+}
+
+/// A cheaply cloneable handle to a `ClockService`.
+#[derive(Debug)]
+pub struct ClockHandle<T> {
+    requests: mpsc::Sender<Request<T>>,
+}
+
+#[derive(Debug)]
+enum Request<T> {
+    Now(oneshot::Sender<Result<Timestamp<T>>>),
+    Observe(Timestamp<T>, oneshot::Sender<Result<()>>),
+    Receive(Timestamp<T>, oneshot::Sender<Result<Timestamp<T>>>),
+    Shutdown,
+}
+
+impl<S> ClockService<S>
+where
+    S: ClockSource + Send + 'static,
+    S::Time: Send,
+{
+    /// Moves `clock` onto a new task on the current tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// If called from outside of a tokio runtime.
+    pub fn spawn(clock: Clock<S>) -> Self {
+        ClockService::spawn_with_capacity(clock, DEFAULT_SERVICE_CAPACITY)
+    }
+
+    /// As `spawn`, but queueing at most `capacity` requests.
+    pub fn spawn_with_capacity(clock: Clock<S>, capacity: usize) -> Self {
+        let (requests, rx) = mpsc::channel(capacity);
+        let task = tokio::spawn(serve(clock, rx));
+        ClockService {
+            handle: ClockHandle { requests },
+            task,
+        }
+    }
+
+    /// Returns a new handle to the service.
+    pub fn handle(&self) -> ClockHandle<S::Time> {
+        self.handle.clone()
+    }
+
+    /// Stops the service once it has answered the requests queued before
+    /// this one, and returns the clock. Requests made afterwards fail with
+    /// `Error::ServiceStopped`.
+    pub async fn shutdown(self) -> Result<Clock<S>> {
+        // If the task has already stopped, awaiting it reports why.
+        let _ = self.handle.requests.send(Request::Shutdown).await;
+        self.task.await.map_err(|_| Error::ServiceStopped)
+    }
+}
+
+async fn serve<S: ClockSource>(
+    mut clock: Clock<S>,
+    mut requests: mpsc::Receiver<Request<S::Time>>,
+) -> Clock<S> {
+    // Replies are dropped if the caller has given up waiting.
+    while let Some(req) = requests.recv().await {
+        match req {
+            Request::Now(reply) => {
+                let _ = reply.send(clock.now());
+            }
+            Request::Observe(msg, reply) => {
+                let _ = reply.send(clock.observe(&msg));
+            }
+            Request::Receive(msg, reply) => {
+                let _ = reply.send(clock.receive(&msg));
+            }
+            Request::Shutdown => break,
+        }
+    }
+    clock
+}
+
+impl<T> ClockHandle<T> {
+    /// Creates a unique monotonic timestamp suitable for annotating messages
+    /// we send. See `Clock::now`.
+    pub async fn now(&self) -> Result<Timestamp<T>> {
+        self.call(Request::Now).await?
+    }
+
+    /// Accepts a timestamp from an incoming message. See `Clock::observe`.
+    pub async fn observe(&self, msg: Timestamp<T>) -> Result<()> {
+        self.call(|reply| Request::Observe(msg, reply)).await?
+    }
+
+    /// Accepts a timestamp from an incoming message using the paper's
+    /// receive rule. See `Clock::receive`.
+    pub async fn receive(&self, msg: Timestamp<T>) -> Result<Timestamp<T>> {
+        self.call(|reply| Request::Receive(msg, reply)).await?
+    }
+
+    /// Returns true once the service has stopped.
+    pub fn is_stopped(&self) -> bool {
+        self.requests.is_closed()
+    }
+
+    async fn call<R>(&self, req: impl FnOnce(oneshot::Sender<R>) -> Request<T>) -> Result<R> {
+        let (reply, rx) = oneshot::channel();
+        self.requests
+            .send(req(reply))
+            .await
+            .map_err(|_| Error::ServiceStopped)?;
+        rx.await.map_err(|_| Error::ServiceStopped)
+    }
+}
+
+impl<T> Clone for ClockHandle<T> {
+    fn clone(&self) -> Self {
+        ClockHandle {
+            requests: self.requests.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ManualClock, ManualT};
+
+    #[tokio::test]
+    async fn should_issue_increasing_timestamps_to_each_handle() -> Result<()> {
+        let service = ClockService::spawn(Clock::new(ManualClock::new(10))?);
+        let (a, b) = (service.handle(), service.handle());
+        let mut prev = a.now().await?;
+        for _ in 0..10 {
+            let next = b.now().await?;
+            assert!(prev < next, "{} < {}", prev, next);
+            prev = a.now().await?;
+            assert!(next < prev, "{} < {}", next, prev);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_observe_via_handle() -> Result<()> {
+        let service = ClockService::spawn(Clock::new(ManualClock::new(10))?);
+        let handle = service.handle();
+        let msg = Timestamp::new(0, ManualT::from(20), 3);
+        handle.observe(msg).await?;
+        assert!(handle.now().await? > msg);
+        assert_eq!(
+            handle.receive(msg).await?,
+            Timestamp::new(0, ManualT::from(20), 5)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_return_clock_on_shutdown() -> Result<()> {
+        let service = ClockService::spawn(Clock::new(ManualClock::new(10))?);
+        let handle = service.handle();
+        let last = handle.now().await?;
+        let clock = service.shutdown().await?;
+        assert_eq!(clock.state().last_observed, last);
+
+        assert!(handle.is_stopped());
+        let err = handle.now().await.unwrap_err();
+        assert!(matches!(err, Error::ServiceStopped), "{:?}", err);
+        Ok(())
+    }
+}
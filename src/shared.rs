@@ -0,0 +1,141 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{Clock, ClockSource, ObservationOutcome, Result, Timestamp};
+
+/// A cloneable handle to a `Clock` that may be shared between threads.
+///
+/// Every clone refers to the same underlying clock, so timestamps issued via
+/// any handle are strictly ordered with respect to each other.
+#[derive(Debug)]
+pub struct SharedClock<S: ClockSource> {
+    inner: Arc<Mutex<Clock<S>>>,
+}
+
+impl<S: ClockSource> SharedClock<S> {
+    /// Wraps `clock` in a shareable handle.
+    pub fn new(clock: Clock<S>) -> Self {
+        SharedClock {
+            inner: Arc::new(Mutex::new(clock)),
+        }
+    }
+
+    /// Creates a unique monotonic timestamp suitable for annotating messages we send.
+    pub fn now(&self) -> Result<Timestamp<S::Time>> {
+        self.lock().now()
+    }
+
+    /// Advances only the logical counter. See `Clock::tick`.
+    pub fn tick(&self) -> Result<Timestamp<S::Time>> {
+        self.lock().tick()
+    }
+
+    /// Returns the most recently issued or observed timestamp. See `Clock::peek`.
+    pub fn peek(&self) -> Timestamp<S::Time> {
+        self.lock().peek()
+    }
+
+    /// Reserves `n` strictly increasing timestamps whilst holding the lock
+    /// once. See `Clock::now_batch`.
+    pub fn now_batch(&self, n: usize) -> Result<Vec<Timestamp<S::Time>>> {
+        self.lock().now_batch(n)
+    }
+
+    /// Accepts a timestamp from an incoming message. See `Clock::observe`.
+    pub fn observe(&self, msg: &Timestamp<S::Time>) -> Result<()> {
+        self.lock().observe(msg)
+    }
+
+    /// Accepts a timestamp from an incoming message, returning the resulting
+    /// timestamp and how it compared. See `Clock::observe_now`.
+    pub fn observe_now(
+        &self,
+        msg: &Timestamp<S::Time>,
+    ) -> Result<(Timestamp<S::Time>, ObservationOutcome)> {
+        self.lock().observe_now(msg)
+    }
+
+    /// Sets the epoch for the underlying clock. See `Clock::set_epoch`.
+    pub fn set_epoch(&self, epoch: u32) {
+        self.lock().set_epoch(epoch)
+    }
+
+    /// Locks the underlying clock, for operations not exposed on the handle.
+    pub fn lock(&self) -> MutexGuard<'_, Clock<S>> {
+        // The clock's state is only ever replaced wholesale, so it remains
+        // consistent even if another thread panicked whilst holding the lock.
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<S: ClockSource> Clone for SharedClock<S> {
+    fn clone(&self) -> Self {
+        SharedClock {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S: ClockSource> From<Clock<S>> for SharedClock<S> {
+    fn from(clock: Clock<S>) -> Self {
+        SharedClock::new(clock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ManualT, WallNS};
+    use std::thread;
+
+    fn assert_send_sync<T: Clone + Send + Sync>() {}
+
+    #[test]
+    fn should_be_send_and_sync() {
+        assert_send_sync::<SharedClock<WallNS>>();
+    }
+
+    #[test]
+    fn clones_should_share_state() -> Result<()> {
+        let a = SharedClock::new(Clock::manual(0)?);
+        let b = a.clone();
+        b.observe(&Timestamp {
+            epoch: 0,
+            time: ManualT::from(10),
+            count: 0,
+        })?;
+        assert_eq!(
+            a.now()?,
+            Timestamp {
+                epoch: 0,
+                time: ManualT::from(10),
+                count: 1,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_issue_unique_timestamps_across_threads() -> Result<()> {
+        let clock = SharedClock::new(Clock::wall_ns()?);
+        let threads = (0..4)
+            .map(|_| {
+                let clock = clock.clone();
+                thread::spawn(move || (0..100).map(|_| clock.now()).collect::<Result<Vec<_>>>())
+            })
+            .collect::<Vec<_>>();
+
+        let mut all = Vec::new();
+        for t in threads {
+            let issued = t.join().expect("join")?;
+            assert!(issued.windows(2).all(|w| w[0] < w[1]));
+            all.extend(issued);
+        }
+        let n = all.len();
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), n);
+        Ok(())
+    }
+}
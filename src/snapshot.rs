@@ -0,0 +1,214 @@
+//! Consistent snapshots at a given timestamp, following "Logical Physical
+//! Clocks and Consistent Snapshots in Globally Distributed Databases".
+//!
+//! Because hybrid logical clocks respect causality, the set of events with
+//! timestamps at or before some target is always a consistent cut: if an
+//! event is included, then so is everything that happened before it. So a
+//! snapshot may be taken by asking each peer for its state as of the last
+//! event at or before the target.
+//!
+//! A peer's contribution is only final once its clock has reached the
+//! target, since until then it may yet issue timestamps at or before it.
+//! `SnapshotTracker` collects progress reports from each peer, and yields
+//! the boundary of the snapshot once every peer has passed the target.
+
+use std::collections::BTreeMap;
+
+use crate::Timestamp;
+
+/// Collects the progress of each peer towards a snapshot at a target
+/// timestamp.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotTracker<T, Id: Ord> {
+    target: Timestamp<T>,
+    peers: BTreeMap<Id, Progress<T>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Progress<T> {
+    latest: Option<Timestamp<T>>,
+    boundary: Option<Timestamp<T>>,
+}
+
+/// The last event at or before the target timestamp on each peer, which
+/// together describe a consistent snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotBoundary<T, Id: Ord> {
+    target: Timestamp<T>,
+    entries: BTreeMap<Id, Option<Timestamp<T>>>,
+}
+
+impl<T: Ord + Copy, Id: Ord> SnapshotTracker<T, Id> {
+    /// Starts a snapshot at `target`, which must include the state of each
+    /// of `peers`.
+    pub fn new<I: IntoIterator<Item = Id>>(target: Timestamp<T>, peers: I) -> Self {
+        let progress = Progress {
+            latest: None,
+            boundary: None,
+        };
+        SnapshotTracker {
+            target,
+            peers: peers.into_iter().map(|p| (p, progress)).collect(),
+        }
+    }
+
+    /// Returns the timestamp that the snapshot is taken at.
+    pub fn target(&self) -> &Timestamp<T> {
+        &self.target
+    }
+
+    /// Records that `peer` has issued or observed `timestamp`, adding it
+    /// to the snapshot if required. Reports may arrive in any order.
+    pub fn record(&mut self, peer: Id, timestamp: Timestamp<T>) {
+        let progress = self.peers.entry(peer).or_insert(Progress {
+            latest: None,
+            boundary: None,
+        });
+        progress.latest = progress.latest.max(Some(timestamp));
+        if timestamp <= self.target {
+            progress.boundary = progress.boundary.max(Some(timestamp));
+        }
+    }
+
+    /// Returns true if `timestamp` falls within the snapshot.
+    pub fn includes(&self, timestamp: &Timestamp<T>) -> bool {
+        *timestamp <= self.target
+    }
+
+    /// Iterates over the peers whose clocks are not yet known to have
+    /// reached the target, and so may yet add events to the snapshot.
+    pub fn pending(&self) -> impl Iterator<Item = &Id> {
+        self.peers
+            .iter()
+            .filter(move |(_, p)| !p.is_complete(&self.target))
+            .map(|(id, _)| id)
+    }
+
+    /// Returns true if every peer's clock has reached the target, so that
+    /// no further events can fall within the snapshot.
+    pub fn is_complete(&self) -> bool {
+        self.peers.values().all(|p| p.is_complete(&self.target))
+    }
+
+    /// Returns the boundary of the snapshot, once it is complete.
+    pub fn boundary(&self) -> Option<SnapshotBoundary<T, Id>>
+    where
+        Id: Clone,
+    {
+        if !self.is_complete() {
+            return None;
+        }
+        let entries = self
+            .peers
+            .iter()
+            .map(|(id, p)| (id.clone(), p.boundary))
+            .collect();
+        Some(SnapshotBoundary {
+            target: self.target,
+            entries,
+        })
+    }
+}
+
+impl<T: Ord> Progress<T> {
+    fn is_complete(&self, target: &Timestamp<T>) -> bool {
+        self.latest.as_ref().is_some_and(|latest| latest >= target)
+    }
+}
+
+impl<T: Ord, Id: Ord> SnapshotBoundary<T, Id> {
+    /// Returns the timestamp that the snapshot was taken at.
+    pub fn target(&self) -> &Timestamp<T> {
+        &self.target
+    }
+
+    /// Returns the timestamp of the last event on `peer` that is included
+    /// in the snapshot, or `None` if it had none, or is not a participant.
+    pub fn get(&self, peer: &Id) -> Option<&Timestamp<T>> {
+        self.entries.get(peer).and_then(Option::as_ref)
+    }
+
+    /// Iterates over each participant, along with the timestamp of its last
+    /// event included in the snapshot.
+    pub fn iter(&self) -> impl Iterator<Item = (&Id, Option<&Timestamp<T>>)> {
+        self.entries.iter().map(|(id, ts)| (id, ts.as_ref()))
+    }
+
+    /// Returns true if the event at `timestamp` on `peer` falls within the
+    /// snapshot.
+    pub fn includes(&self, peer: &Id, timestamp: &Timestamp<T>) -> bool {
+        self.get(peer).is_some_and(|last| timestamp <= last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::{Clock, ManualClock, ManualT};
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    fn ts(t: u64) -> Timestamp<ManualT> {
+        Timestamp::new(0, ManualT::from(t), 0)
+    }
+
+    #[test]
+    fn should_be_complete_once_every_peer_passes_target() {
+        let mut snap = SnapshotTracker::new(ts(10), vec!["a", "b"]);
+        snap.record("a", ts(4));
+        snap.record("a", ts(12));
+        snap.record("b", ts(9));
+        assert!(!snap.is_complete());
+        assert_eq!(snap.pending().collect::<Vec<_>>(), vec![&"b"]);
+        assert_eq!(snap.boundary(), None);
+
+        snap.record("b", ts(10));
+        let boundary = snap.boundary().expect("complete");
+        assert_eq!(boundary.get(&"a"), Some(&ts(4)));
+        assert_eq!(boundary.get(&"b"), Some(&ts(10)));
+        assert!(boundary.includes(&"a", &ts(3)) && !boundary.includes(&"a", &ts(5)));
+    }
+
+    #[test]
+    fn boundary_should_be_last_event_at_or_before_target() {
+        property((timestamps(manualts()), vecs(timestamps(manualts())))).check(
+            |(target, events)| {
+                let mut snap = SnapshotTracker::new(target, Some(()));
+                for &e in events.iter() {
+                    snap.record((), e);
+                }
+                snap.record((), target);
+                let boundary = snap.boundary().expect("complete");
+                let expected = events
+                    .iter()
+                    .chain(Some(&target))
+                    .filter(|e| **e <= target)
+                    .max();
+                boundary.get(&()) == expected
+            },
+        );
+    }
+
+    #[test]
+    fn should_include_causes_of_included_events() {
+        // a sends a message to b, whose receipt falls within the snapshot;
+        // so the send must too.
+        property((u64s(), u64s(), u64s())).check(|(t0, t1, skew)| {
+            let (t0, t1, skew) = (t0 >> 2, t1 >> 2, skew >> 2);
+            let mut a = Clock::new(ManualClock::new(t0 + skew)).expect("clock");
+            let mut b = Clock::new(ManualClock::new(t1)).expect("clock");
+            let send = a.now().expect("send");
+            b.observe(&send).expect("observe");
+            let recv = b.now().expect("recv");
+
+            let mut snap = SnapshotTracker::new(recv, vec!["a", "b"]);
+            snap.record("a", send);
+            snap.record("a", a.now().expect("a"));
+            snap.record("b", recv);
+            snap.record("a", recv);
+            let boundary = snap.boundary().expect("complete");
+            boundary.includes(&"a", &send)
+        });
+    }
+}
@@ -0,0 +1,228 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::packed::check_width;
+use crate::{AsDuration, Clock, ClockSource, Error, Result, WallMS};
+
+/// Describes how a `SnowflakeGen` packs IDs: from the most significant bit,
+/// milliseconds since a custom epoch, the node id, then a sequence number.
+/// The widths must sum to at most 63 bits, so that IDs are positive as
+/// `i64`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SnowflakeLayout {
+    /// The number of bits of milliseconds since `epoch`.
+    pub time_bits: u32,
+    /// The number of bits of node id.
+    pub node_bits: u32,
+    /// The number of bits of sequence number within each millisecond.
+    pub count_bits: u32,
+    /// The custom epoch, as a duration since the unix epoch.
+    pub epoch: Duration,
+}
+
+impl SnowflakeLayout {
+    /// Twitter's original layout: 41 bits of time since 2010-11-04, 10 bits
+    /// of node id and a 12 bit sequence.
+    pub const TWITTER: SnowflakeLayout = SnowflakeLayout {
+        time_bits: 41,
+        node_bits: 10,
+        count_bits: 12,
+        epoch: Duration::from_millis(1_288_834_974_657),
+    };
+
+    /// Splits `id` into its time since the unix epoch, node id and sequence.
+    pub fn decode(&self, id: u64) -> (Duration, u64, u64) {
+        let mask = |bits: u32| (1u64 << bits) - 1;
+        let millis = id >> (self.node_bits + self.count_bits) & mask(self.time_bits);
+        let node = id >> self.count_bits & mask(self.node_bits);
+        let count = id & mask(self.count_bits);
+        (self.epoch + Duration::from_millis(millis), node, count)
+    }
+}
+
+impl Default for SnowflakeLayout {
+    fn default() -> Self {
+        SnowflakeLayout::TWITTER
+    }
+}
+
+/// Issues Twitter-style snowflake IDs from a hybrid logical clock, such
+/// that IDs from a generator are strictly increasing, and ordered
+/// consistently with the timestamps issued and observed by its clock.
+///
+/// The sequence number for the first ID in each millisecond is the clock's
+/// count, and it increments for each further ID in that millisecond, as the
+/// count resets whenever the source ticks. Once the sequence is exhausted,
+/// `next_id` fails with `Error::CountLimitExceeded` until the next
+/// millisecond.
+#[derive(Debug, Clone)]
+pub struct SnowflakeGen<S: ClockSource = WallMS> {
+    clock: Clock<S>,
+    layout: SnowflakeLayout,
+    node_id: u64,
+    // The millisecond and sequence number of the last ID issued.
+    last: Option<(u64, u64)>,
+}
+
+impl SnowflakeGen<WallMS> {
+    /// Creates a generator for `node_id` with the Twitter layout, using the
+    /// system clock.
+    pub fn new(node_id: u64) -> Result<Self> {
+        SnowflakeGen::with_layout(Clock::wall_ms()?, node_id, SnowflakeLayout::TWITTER)
+    }
+}
+
+impl<S: ClockSource> SnowflakeGen<S>
+where
+    S::Time: AsDuration,
+{
+    /// Creates a generator for `node_id` that issues IDs from `clock`,
+    /// packed as per `layout`. Fails with `Error::PackOverflow` if the
+    /// layout is wider than 63 bits, or the node id does not fit.
+    pub fn with_layout(clock: Clock<S>, node_id: u64, layout: SnowflakeLayout) -> Result<Self> {
+        let width = layout.time_bits + layout.node_bits + layout.count_bits;
+        if width > 63 {
+            return Err(Error::PackOverflow {
+                field: "layout",
+                value: u64::from(width),
+                bits: 63,
+            });
+        }
+        check_width("node", node_id, layout.node_bits)?;
+        Ok(SnowflakeGen {
+            clock,
+            layout,
+            node_id,
+            last: None,
+        })
+    }
+
+    /// Returns the layout used for IDs.
+    pub fn layout(&self) -> &SnowflakeLayout {
+        &self.layout
+    }
+
+    /// Returns the underlying clock, eg: to observe remote timestamps.
+    pub fn inner_mut(&mut self) -> &mut Clock<S> {
+        &mut self.clock
+    }
+
+    /// Issues the next ID.
+    pub fn next_id(&mut self) -> Result<u64> {
+        let ts = self.clock.now()?;
+        let since_epoch = ts
+            .time
+            .as_duration()
+            .checked_sub(self.layout.epoch)
+            .ok_or(Error::SupportedTime(ts.time.as_duration().as_nanos()))?;
+        let millis = u64::try_from(since_epoch.as_millis()).unwrap_or(u64::MAX);
+        let millis = check_width("time", millis, self.layout.time_bits)?;
+        let count = match self.last {
+            Some((last_millis, last_count)) if last_millis == millis => last_count + 1,
+            _ => u64::from(ts.count),
+        };
+        let limit = 1u64 << self.layout.count_bits;
+        if count >= limit {
+            return Err(Error::CountLimitExceeded {
+                count: u32::try_from(count).unwrap_or(u32::MAX),
+                limit: u32::try_from(limit - 1).unwrap_or(u32::MAX),
+            });
+        }
+        self.last = Some((millis, count));
+        Ok(millis << (self.layout.node_bits + self.layout.count_bits)
+            | self.node_id << self.layout.count_bits
+            | count)
+    }
+
+    /// Issues the next ID as an `i64`, as used by many snowflake consumers.
+    /// This is always positive.
+    pub fn next_i64(&mut self) -> Result<i64> {
+        Ok(self.next_id()? as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ManualClock, Timestamp};
+
+    const LAYOUT: SnowflakeLayout = SnowflakeLayout {
+        time_bits: 41,
+        node_bits: 10,
+        count_bits: 2,
+        epoch: Duration::from_secs(0),
+    };
+
+    fn gen(node_id: u64) -> Result<SnowflakeGen<ManualClock>> {
+        let clock = Clock::new(ManualClock::new(5_000_000))?;
+        SnowflakeGen::with_layout(clock, node_id, LAYOUT)
+    }
+
+    #[test]
+    fn should_pack_time_node_and_sequence() -> Result<()> {
+        let mut gen = gen(3)?;
+        let id = gen.next_id()?;
+        assert_eq!(LAYOUT.decode(id), (Duration::from_millis(5), 3, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn should_issue_increasing_ids_until_exhausted() -> Result<()> {
+        let mut gen = gen(3)?;
+        let ids = (0..3).map(|_| gen.next_id()).collect::<Result<Vec<_>>>()?;
+        assert!(ids.windows(2).all(|w| w[0] < w[1]), "{:?}", ids);
+        assert!(matches!(
+            gen.next_id(),
+            Err(Error::CountLimitExceeded { count: 4, limit: 3 })
+        ));
+        gen.inner_mut().advance_by(1_000_000);
+        let next = gen.next_id()?;
+        assert!(next > ids[2]);
+        assert_eq!(LAYOUT.decode(next), (Duration::from_millis(6), 3, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn should_continue_sequence_within_millisecond() -> Result<()> {
+        let mut gen = gen(3)?;
+        let a = gen.next_id()?;
+        // A new tick within the same millisecond resets the clock's count.
+        gen.inner_mut().advance_by(1);
+        let b = gen.next_id()?;
+        assert!(a < b);
+        assert_eq!(LAYOUT.decode(b).2, LAYOUT.decode(a).2 + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn should_follow_observed_timestamps() -> Result<()> {
+        let mut gen = gen(3)?;
+        let a = gen.next_id()?;
+        gen.inner_mut()
+            .observe(&Timestamp::new(0, 7_000_000.into(), 0))?;
+        let b = gen.next_id()?;
+        assert!(a < b);
+        assert_eq!(LAYOUT.decode(b).0, Duration::from_millis(7));
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_invalid_layouts() {
+        assert!(matches!(
+            gen(1 << 10),
+            Err(Error::PackOverflow { field: "node", .. })
+        ));
+        let wide = SnowflakeLayout {
+            time_bits: 48,
+            ..SnowflakeLayout::TWITTER
+        };
+        let clock = Clock::new(ManualClock::new(0)).expect("clock");
+        assert!(matches!(
+            SnowflakeGen::with_layout(clock, 0, wide),
+            Err(Error::PackOverflow {
+                field: "layout",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use super::{ClockSource, SharedClockSource, WallNST};
+use crate::Result;
+
+/// A clock source that returns a cached wall-clock reading in nanoseconds,
+/// which is only updated by `refresh`, or periodically by a background
+/// thread started with `spawn_refresher`. Reading it is a single atomic
+/// load, at the cost of readings being up to one refresh interval stale.
+///
+/// Clones share the same cached reading. The reading never goes
+/// backwards, even if the system clock does.
+#[derive(Debug, Clone)]
+pub struct CachedWall {
+    reading: Arc<AtomicU64>,
+}
+
+/// Stops the background thread started by `CachedWall::spawn_refresher`
+/// when dropped.
+#[derive(Debug)]
+pub struct CachedWallRefresher {
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl CachedWall {
+    /// Creates a source holding the current system time.
+    pub fn new() -> Result<Self> {
+        let src = CachedWall {
+            reading: Arc::new(AtomicU64::new(0)),
+        };
+        src.refresh()?;
+        Ok(src)
+    }
+
+    /// Updates the cached reading from the system clock.
+    pub fn refresh(&self) -> Result<()> {
+        let now = WallNST::from_timespec(SystemTime::now())?;
+        self.reading.fetch_max(now.as_u64(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Starts a thread that refreshes the cached reading every `interval`,
+    /// until the returned handle is dropped. Failed refreshes are skipped.
+    pub fn spawn_refresher(&self, interval: Duration) -> Result<CachedWallRefresher> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let src = self.clone();
+        let thread = {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("cached-wall-refresher".into())
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        let _ = src.refresh();
+                        thread::park_timeout(interval);
+                    }
+                })?
+        };
+        Ok(CachedWallRefresher {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for CachedWallRefresher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+impl ClockSource for CachedWall {
+    type Time = WallNST;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for CachedWall {
+    fn now_shared(&self) -> Result<Self::Time> {
+        Ok(WallNST::of_u64(self.reading.load(Ordering::Relaxed)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_only_advance_when_refreshed() -> Result<()> {
+        let mut src = CachedWall::new()?;
+        let a = src.now()?;
+        thread::sleep(Duration::from_millis(2));
+        assert_eq!(src.now()?, a);
+        src.refresh()?;
+        assert!(src.now()? > a);
+        Ok(())
+    }
+
+    #[test]
+    fn should_refresh_in_background() -> Result<()> {
+        let mut src = CachedWall::new()?;
+        let a = src.now()?;
+        let refresher = src.spawn_refresher(Duration::from_millis(1))?;
+        let mut b = a;
+        for _ in 0..1000 {
+            b = src.now()?;
+            if b > a {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        drop(refresher);
+        assert!(b > a, "{} > {}", b, a);
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use super::{ClockSource, SharedClockSource, WallNST};
+use crate::Result;
+
+/// A clock source that returns wall-clock time in nanoseconds, as `WallNS`
+/// does, but reads the operating system's coarse realtime clock where one
+/// is available (`CLOCK_REALTIME_COARSE` on Linux, `CLOCK_REALTIME_FAST` on
+/// FreeBSD), and falls back to `SystemTime` elsewhere.
+///
+/// Coarse reads are cheaper, but typically only advance every few
+/// milliseconds. The logical counter disambiguates timestamps issued in
+/// between, so this suits services that issue very many timestamps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WallCoarse;
+
+impl ClockSource for WallCoarse {
+    type Time = WallNST;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for WallCoarse {
+    fn now_shared(&self) -> Result<Self::Time> {
+        coarse_now()
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn coarse_now() -> Result<WallNST> {
+    use std::convert::TryFrom;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const CLOCK: libc::clockid_t = libc::CLOCK_REALTIME_COARSE;
+    #[cfg(target_os = "freebsd")]
+    const CLOCK: libc::clockid_t = libc::CLOCK_REALTIME_FAST;
+
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `ts` is a valid, writable timespec for the duration of the call.
+    if unsafe { libc::clock_gettime(CLOCK, &mut ts) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let since_epoch = Duration::new(u64::try_from(ts.tv_sec)?, u32::try_from(ts.tv_nsec)?);
+    WallNST::from_since_epoch(since_epoch)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn coarse_now() -> Result<WallNST> {
+    WallNST::from_timespec(std::time::SystemTime::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    #[test]
+    fn should_be_close_to_system_time() -> Result<()> {
+        let coarse = WallCoarse.now()?;
+        let system = WallNST::from_timespec(SystemTime::now())?;
+        let skew = if coarse > system {
+            coarse - system
+        } else {
+            system - coarse
+        };
+        assert!(skew < Duration::from_secs(1), "{} vs {}", coarse, system);
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::{ClockSource, SharedClockSource};
+use crate::Result;
+
+/// A clock source that passes readings through from another, except whilst
+/// a `FrozenClock` guard from `freeze` is held, when every reading returns
+/// the frozen time instead.
+///
+/// Clones share their frozen state, so a test can keep a clone to freeze a
+/// source that has been moved into a `Clock`, eg: to test code that is
+/// generic over the clock source deterministically with a real wall clock.
+#[derive(Debug, Clone)]
+pub struct FreezableSource<S: ClockSource> {
+    src: S,
+    frozen: Arc<Mutex<Option<S::Time>>>,
+}
+
+/// Pins a `FreezableSource` (and each of its clones) to a fixed reading,
+/// until dropped. Guards may be nested, in which case dropping one restores
+/// the reading frozen by the guard before it.
+#[derive(Debug)]
+#[must_use = "the source is only frozen whilst the guard is held"]
+pub struct FrozenClock<T> {
+    frozen: Arc<Mutex<Option<T>>>,
+    previous: Option<T>,
+}
+
+impl<S: ClockSource> FreezableSource<S> {
+    /// Wraps `src`, which is initially not frozen.
+    pub fn new(src: S) -> Self {
+        FreezableSource {
+            src,
+            frozen: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Pins readings to `at` until the returned guard is dropped.
+    pub fn freeze(&self, at: S::Time) -> FrozenClock<S::Time> {
+        let previous = self.lock().replace(at);
+        FrozenClock {
+            frozen: self.frozen.clone(),
+            previous,
+        }
+    }
+
+    /// Pins readings to the current reading of the inner source, until the
+    /// returned guard is dropped.
+    pub fn freeze_now(&mut self) -> Result<FrozenClock<S::Time>> {
+        let at = self.src.now()?;
+        Ok(self.freeze(at))
+    }
+
+    /// Returns the time readings are pinned to, if frozen.
+    pub fn frozen_at(&self) -> Option<S::Time> {
+        *self.lock()
+    }
+
+    /// Get a reference to the inner clock source.
+    pub fn inner(&self) -> &S {
+        &self.src
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<S::Time>> {
+        // The state is a plain value, so is always consistent.
+        self.frozen.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> FrozenClock<T> {
+    /// Returns the time the source is pinned to.
+    pub fn time(&self) -> T
+    where
+        T: Copy,
+    {
+        let frozen = self.frozen.lock().unwrap_or_else(|e| e.into_inner());
+        frozen.expect("frozen whilst guard is held")
+    }
+}
+
+impl<T> Drop for FrozenClock<T> {
+    fn drop(&mut self) {
+        let mut frozen = self.frozen.lock().unwrap_or_else(|e| e.into_inner());
+        *frozen = self.previous.take();
+    }
+}
+
+impl<S: ClockSource> ClockSource for FreezableSource<S> {
+    type Time = S::Time;
+    type Delta = S::Delta;
+    fn now(&mut self) -> Result<Self::Time> {
+        match self.frozen_at() {
+            Some(t) => Ok(t),
+            None => self.src.now(),
+        }
+    }
+    fn uncertainty(&self) -> Option<Self::Delta> {
+        self.src.uncertainty()
+    }
+}
+
+impl<S: SharedClockSource> SharedClockSource for FreezableSource<S> {
+    fn now_shared(&self) -> Result<Self::Time> {
+        match self.frozen_at() {
+            Some(t) => Ok(t),
+            None => self.src.now_shared(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Clock, WallNS, WallNST};
+
+    fn issue_two<S: ClockSource>(clock: &mut Clock<S>) -> Result<(S::Time, S::Time)> {
+        Ok((clock.now()?.time, clock.now()?.time))
+    }
+
+    #[test]
+    fn should_pin_wall_clock_whilst_guard_held() -> Result<()> {
+        let src = FreezableSource::new(WallNS);
+        let mut clock = Clock::new(src.clone())?;
+        let at = WallNST::of_u64(u64::MAX / 2);
+        {
+            let guard = src.freeze(at);
+            assert_eq!(guard.time(), at);
+            assert_eq!(issue_two(&mut clock)?, (at, at));
+        }
+        assert_eq!(src.frozen_at(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn should_restore_outer_freeze() -> Result<()> {
+        let mut src = FreezableSource::new(WallNS);
+        let outer = src.freeze_now()?;
+        let inner = src.freeze(WallNST::of_u64(1));
+        drop(inner);
+        assert_eq!(src.now()?, outer.time());
+        Ok(())
+    }
+}
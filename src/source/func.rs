@@ -0,0 +1,171 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Sub;
+use std::time::Duration;
+
+use super::{AsDuration, ClockSource, TimeBounds, U64Time};
+use crate::Result;
+
+/// Describes the length of the ticks returned by a `FnSource`.
+pub trait TickUnit {
+    /// The number of nanoseconds in each tick.
+    const NANOS_PER_TICK: u64;
+}
+
+/// Ticks of one nanosecond.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Nanos;
+/// Ticks of one microsecond.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Micros;
+/// Ticks of one millisecond.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Millis;
+
+impl TickUnit for Nanos {
+    const NANOS_PER_TICK: u64 = 1;
+}
+impl TickUnit for Micros {
+    const NANOS_PER_TICK: u64 = 1_000;
+}
+impl TickUnit for Millis {
+    const NANOS_PER_TICK: u64 = 1_000_000;
+}
+
+/// A number of ticks of the unit `U`, as returned by a `FnSource`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ticks<U> {
+    ticks: u64,
+    unit: PhantomData<U>,
+}
+
+/// A clock source that calls `F` for each reading, which returns a number of
+/// ticks of the unit `U` (nanoseconds by default), from whatever epoch the
+/// function uses. This allows any time provider to be used as a clock
+/// source, without writing a new type.
+#[derive(Clone)]
+pub struct FnSource<F, U = Nanos> {
+    f: F,
+    unit: PhantomData<U>,
+}
+
+impl<U> Ticks<U> {
+    /// Returns the number of ticks.
+    pub const fn as_u64(self) -> u64 {
+        self.ticks
+    }
+
+    /// Builds a time from a number of ticks.
+    pub const fn of_u64(ticks: u64) -> Self {
+        Ticks {
+            ticks,
+            unit: PhantomData,
+        }
+    }
+}
+
+impl<F, U> FnSource<F, U> {
+    /// Creates a source that reads the time by calling `f`.
+    pub fn new(f: F) -> Self {
+        FnSource {
+            f,
+            unit: PhantomData,
+        }
+    }
+
+    /// Extract the inner function.
+    pub fn into_inner(self) -> F {
+        self.f
+    }
+}
+
+impl<F: FnMut() -> Result<u64>, U: TickUnit + Ord + Copy + fmt::Debug> ClockSource
+    for FnSource<F, U>
+{
+    type Time = Ticks<U>;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        Ok(Ticks::of_u64((self.f)()?))
+    }
+}
+
+impl<F, U> fmt::Debug for FnSource<F, U> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("FnSource").finish()
+    }
+}
+
+pub(super) fn ticks_to_duration<U: TickUnit>(ticks: u64) -> Duration {
+    let nanos = u128::from(ticks) * u128::from(U::NANOS_PER_TICK);
+    let secs = u64::try_from(nanos / 1_000_000_000).unwrap_or(u64::MAX);
+    Duration::new(secs, (nanos % 1_000_000_000) as u32)
+}
+
+impl<U: TickUnit> Sub for Ticks<U> {
+    type Output = Duration;
+    /// Panics if `rhs` is after `self`.
+    fn sub(self, rhs: Self) -> Self::Output {
+        let ticks = self.ticks.checked_sub(rhs.ticks);
+        ticks_to_duration::<U>(ticks.expect("subtraction underflow"))
+    }
+}
+
+impl<U: TickUnit> AsDuration for Ticks<U> {
+    fn as_duration(&self) -> Duration {
+        ticks_to_duration::<U>(self.ticks)
+    }
+}
+
+impl<U> TimeBounds for Ticks<U> {
+    const MIN: Self = Ticks::of_u64(0);
+    const MAX: Self = Ticks::of_u64(u64::MAX);
+}
+
+impl<U: Copy> U64Time for Ticks<U> {
+    fn as_u64(self) -> u64 {
+        self.ticks
+    }
+    fn of_u64(val: u64) -> Self {
+        Ticks::of_u64(val)
+    }
+}
+
+impl<U> fmt::Display for Ticks<U> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.ticks.fmt(fmt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Clock, Error};
+
+    #[test]
+    fn should_read_from_function() -> Result<()> {
+        let mut readings = vec![5, 3, 8].into_iter();
+        let src = FnSource::<_, Millis>::new(move || readings.next().ok_or(Error::CorruptState));
+        let mut clock = Clock::new(src)?;
+        let a = clock.now()?;
+        let b = clock.now()?;
+        assert_eq!((a.time.as_u64(), a.count), (5, 1));
+        assert_eq!((b.time.as_u64(), b.count), (8, 0));
+        assert!(matches!(clock.now(), Err(Error::CorruptState)));
+        Ok(())
+    }
+
+    #[test]
+    fn should_measure_in_units() {
+        let (a, b) = (Ticks::<Micros>::of_u64(1), Ticks::<Micros>::of_u64(2_500));
+        assert_eq!(b - a, Duration::from_micros(2_499));
+        assert_eq!(
+            Ticks::<Millis>::of_u64(3).as_duration(),
+            Duration::from_millis(3)
+        );
+        assert_eq!(
+            Ticks::<Millis>::MAX.as_duration().as_nanos(),
+            u128::from(u64::MAX) * 1_000_000
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::ops::{Add, Sub};
+
+use rand_core::RngCore;
+
+use super::{ClockSource, ScalableDelta};
+use crate::Result;
+
+/// A clock source that perturbs the readings of another by a random amount
+/// of up to `max_jitter` in either direction, to simulate a noisy physical
+/// clock. Readings may therefore go backwards.
+///
+/// The jitter is drawn from `R`, so seeding it gives reproducible runs.
+#[derive(Debug, Clone)]
+pub struct JitterSource<S: ClockSource, R> {
+    src: S,
+    rng: R,
+    max_jitter: S::Delta,
+}
+
+impl<S: ClockSource, R: RngCore> JitterSource<S, R> {
+    /// Wraps `src`, perturbing its readings by up to `max_jitter`, drawn
+    /// from `rng`.
+    pub fn new(src: S, rng: R, max_jitter: S::Delta) -> Self {
+        JitterSource {
+            src,
+            rng,
+            max_jitter,
+        }
+    }
+
+    /// Get a reference to the inner clock source.
+    pub fn inner(&self) -> &S {
+        &self.src
+    }
+
+    /// Extract the inner clock source.
+    pub fn into_inner(self) -> S {
+        self.src
+    }
+}
+
+impl<S: ClockSource, R: RngCore> ClockSource for JitterSource<S, R>
+where
+    S::Time: Add<S::Delta, Output = S::Time> + Sub<S::Delta, Output = S::Time>,
+    S::Delta: Copy + ScalableDelta,
+{
+    type Time = S::Time;
+    type Delta = S::Delta;
+    /// Panics if a perturbed reading falls outside of the time range.
+    fn now(&mut self) -> Result<Self::Time> {
+        let t = self.src.now()?;
+        let sample = self.rng.next_u64();
+        // Use the top 53 bits as a fraction, and the lowest for the sign.
+        let fraction = (sample >> 11) as f64 / (1u64 << 53) as f64;
+        let jitter = self.max_jitter.mul_f64(fraction);
+        if sample & 1 == 0 {
+            Ok(t + jitter)
+        } else {
+            Ok(t - jitter)
+        }
+    }
+    fn uncertainty(&self) -> Option<Self::Delta> {
+        self.src.uncertainty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Clock, ManualClock, ManualT};
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    // A xorshift generator, which is plenty for perturbing test clocks.
+    struct XorShift(u64);
+
+    impl RngCore for XorShift {
+        fn next_u32(&mut self) -> u32 {
+            (self.next_u64() >> 32) as u32
+        }
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            rand_core::impls::fill_bytes_via_next(self, dest)
+        }
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand_core::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn should_stay_within_bounds() -> Result<()> {
+        let mut src = JitterSource::new(ManualClock::new(1000), XorShift(1), 10);
+        for _ in 0..1000 {
+            let t = src.now()?;
+            assert!(ManualT::from(990) <= t && t <= ManualT::from(1010), "{}", t);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn clock_should_stay_monotonic_despite_jitter() {
+        property((u64s(), vecs(u8s()))).check(|(seed, steps)| -> Result<()> {
+            let src = JitterSource::new(ManualClock::new(1_000), XorShift(seed | 1), 50);
+            let mut clock = Clock::new(src)?;
+            let mut prev = clock.now()?;
+            let mut time = 1_000;
+            for step in steps {
+                time += u64::from(step);
+                clock.src.inner().set_time(time);
+                let next = clock.now()?;
+                assert!(prev < next, "{} < {}", prev, next);
+                prev = next;
+            }
+            Ok(())
+        });
+    }
+}
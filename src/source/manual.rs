@@ -1,27 +1,139 @@
-use std::{cell::Cell, fmt};
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
-use super::ClockSource;
-use crate::Result;
+use super::{
+    AsDuration, ClockSource, FromDuration, InfallibleClockSource, SharedClockSource, TimeBounds,
+    U64Time,
+};
+use crate::{Error, Result};
 
-pub struct ManualClock(Cell<u64>);
+/// A clock source whose time is set explicitly, for tests. It may be shared
+/// between threads, eg: behind an `Arc`, and driven from any of them.
+#[derive(Debug, Default)]
+pub struct ManualClock(AtomicU64);
 #[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    rkyv(derive(Debug))
+)]
 pub struct ManualT(u64);
 
-impl<'a> ClockSource for ManualClock {
+impl ClockSource for ManualClock {
     type Time = ManualT;
     type Delta = u64;
     fn now(&mut self) -> Result<Self::Time> {
-        Ok(self.0.get().into())
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for ManualClock {
+    fn now_shared(&self) -> Result<Self::Time> {
+        Ok(self.0.load(Ordering::SeqCst).into())
+    }
+}
+
+impl InfallibleClockSource for ManualClock {
+    fn now_infallible(&mut self) -> Self::Time {
+        self.0.load(Ordering::SeqCst).into()
     }
 }
 
 impl ManualClock {
     pub fn new(t: u64) -> ManualClock {
-        ManualClock(Cell::new(t))
+        ManualClock(AtomicU64::new(t))
     }
     pub fn set_time(&self, t: u64) {
-        self.0.set(t)
+        self.0.store(t, Ordering::SeqCst)
+    }
+
+    /// Returns the current fake time.
+    pub fn time(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Moves the fake time forwards by `delta`, returning the new time.
+    pub fn advance_by(&self, delta: u64) -> u64 {
+        self.0.fetch_add(delta, Ordering::SeqCst) + delta
+    }
+
+    /// Moves the fake time forwards to `t`.
+    ///
+    /// # Panics
+    ///
+    /// If the fake time is already after `t`, as that is usually a mistake
+    /// in the test; use `set_time` to move the time backwards deliberately.
+    pub fn advance_to(&self, t: u64) {
+        let prev = self.0.fetch_max(t, Ordering::SeqCst);
+        assert!(prev <= t, "cannot advance from {} back to {}", prev, t);
+    }
+}
+
+/// A clock source for tests that advances itself after every reading, by
+/// a fixed step or according to a schedule of steps. Once a schedule is
+/// exhausted, the time stays put.
+#[derive(Debug, Clone)]
+pub struct MockClock<I = iter::Repeat<u64>> {
+    time: u64,
+    steps: I,
+}
+
+impl MockClock {
+    /// Returns a clock that first reads `start`, and advances by `step`
+    /// after every reading.
+    pub fn stepping(start: u64, step: u64) -> Self {
+        MockClock::with_schedule(start, iter::repeat(step))
+    }
+}
+
+impl<I: Iterator<Item = u64>> MockClock<I> {
+    /// Returns a clock that first reads `start`, and advances by each of
+    /// `steps` in turn after every reading.
+    pub fn with_schedule<J: IntoIterator<IntoIter = I>>(start: u64, steps: J) -> Self {
+        MockClock {
+            time: start,
+            steps: steps.into_iter(),
+        }
+    }
+
+    /// Returns the time that the next reading will return.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+}
+
+impl<I: Iterator<Item = u64>> ClockSource for MockClock<I> {
+    type Time = ManualT;
+    type Delta = u64;
+    fn now(&mut self) -> Result<Self::Time> {
+        Ok(self.now_infallible())
+    }
+}
+
+impl<I: Iterator<Item = u64>> InfallibleClockSource for MockClock<I> {
+    fn now_infallible(&mut self) -> Self::Time {
+        let t = self.time;
+        self.time += self.steps.next().unwrap_or(0);
+        ManualT(t)
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl ArchivedManualT {
+    /// Returns the archived time, which can be read in place without
+    /// deserializing it.
+    pub fn to_native(&self) -> ManualT {
+        ManualT(self.0.to_native())
     }
 }
 
@@ -31,6 +143,46 @@ impl From<u64> for ManualT {
     }
 }
 
+impl AsDuration for ManualT {
+    fn as_duration(&self) -> Duration {
+        Duration::from_nanos(self.0)
+    }
+}
+
+impl FromDuration for ManualT {
+    fn from_duration(since_epoch: Duration) -> Result<Self> {
+        Ok(ManualT(u64::try_from(since_epoch.as_nanos())?))
+    }
+}
+
+impl TimeBounds for ManualT {
+    const MIN: Self = ManualT(0);
+    const MAX: Self = ManualT(u64::MAX);
+}
+
+impl U64Time for ManualT {
+    fn as_u64(self) -> u64 {
+        self.0
+    }
+    fn of_u64(val: u64) -> Self {
+        ManualT(val)
+    }
+}
+
+impl std::ops::Add<u64> for ManualT {
+    type Output = ManualT;
+    fn add(self, delta: u64) -> Self::Output {
+        ManualT(self.0 + delta)
+    }
+}
+
+impl std::ops::Sub<u64> for ManualT {
+    type Output = ManualT;
+    fn sub(self, delta: u64) -> Self::Output {
+        ManualT(self.0 - delta)
+    }
+}
+
 impl std::ops::Sub for ManualT {
     type Output = u64;
     fn sub(self, other: Self) -> Self::Output {
@@ -43,3 +195,42 @@ impl fmt::Display for ManualT {
         write!(fmt, "{}", self.0)
     }
 }
+
+impl FromStr for ManualT {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        s.parse()
+            .map(ManualT)
+            .map_err(|_| Error::Parse(s.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_advance_relative_to_current_time() {
+        let clock = ManualClock::new(10);
+        assert_eq!(clock.advance_by(5), 15);
+        clock.advance_to(20);
+        clock.advance_to(20);
+        assert_eq!(clock.time(), 20);
+    }
+
+    #[test]
+    fn mock_clock_should_follow_schedule() {
+        let mut clock = MockClock::with_schedule(10, vec![1, 5]);
+        let readings: Vec<_> = (0..4).map(|_| clock.now_infallible()).collect();
+        assert_eq!(
+            readings,
+            vec![ManualT(10), ManualT(11), ManualT(16), ManualT(16)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot advance")]
+    fn should_refuse_to_advance_backwards() {
+        ManualClock::new(10).advance_to(5);
+    }
+}
@@ -1,16 +1,179 @@
+use std::convert::TryFrom;
 use std::fmt;
 use std::ops::Sub;
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "pretty-print")]
+use std::time::SystemTime;
 
+#[cfg(feature = "pretty-print")]
+use time::format_description::well_known::Rfc3339;
+
+mod cached;
+#[cfg(feature = "coarse")]
+mod coarse;
+mod counter;
+mod frozen;
+mod func;
+#[cfg(feature = "jitter")]
+mod jitter;
+#[cfg(all(feature = "ntp", target_os = "linux"))]
+mod ntp;
+#[cfg(all(windows, feature = "precise-win"))]
+mod precise_win;
+#[cfg(feature = "quanta-clock")]
+mod quanta_clock;
+mod rebased;
+mod scaled;
+mod signed;
+mod skewed;
+mod tick_counter;
+#[cfg(feature = "tsc")]
+mod tsc;
+mod wall_millis;
 mod wall_ms;
+mod wall_ticks;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod wasm;
+pub use self::cached::*;
+#[cfg(feature = "coarse")]
+pub use self::coarse::*;
+pub use self::counter::*;
+pub use self::frozen::*;
+pub use self::func::*;
+#[cfg(feature = "jitter")]
+pub use self::jitter::*;
+#[cfg(all(feature = "ntp", target_os = "linux"))]
+pub use self::ntp::*;
+#[cfg(all(windows, feature = "precise-win"))]
+pub use self::precise_win::*;
+#[cfg(feature = "quanta-clock")]
+pub use self::quanta_clock::*;
+pub use self::rebased::*;
+pub use self::scaled::*;
+pub use self::signed::*;
+pub use self::skewed::*;
+pub use self::tick_counter::*;
+#[cfg(feature = "tsc")]
+pub use self::tsc::*;
+pub use self::wall_millis::*;
 pub use self::wall_ms::*;
+pub use self::wall_ticks::*;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub use self::wasm::*;
 mod manual;
+mod monotonic;
 mod wall_ns;
+mod wall_us;
 pub use self::manual::*;
+pub use self::monotonic::*;
 pub use self::wall_ns::*;
-use crate::Result;
+pub use self::wall_us::*;
+use crate::{Error, Result};
 
 const NANOS_PER_SEC: u64 = 1_000_000_000;
 
+/// Writes a time since the unix epoch as seconds with nanosecond precision.
+/// With the alternate flag (`{:#}`), or when the `pretty-print` feature is
+/// enabled, it is written as an RFC3339 date instead.
+fn fmt_since_epoch(since_epoch: Duration, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if fmt.alternate() || cfg!(feature = "pretty-print") {
+        return fmt_rfc3339(since_epoch, fmt);
+    }
+    write!(
+        fmt,
+        "{}.{:09}",
+        since_epoch.as_secs(),
+        since_epoch.subsec_nanos()
+    )
+}
+
+/// Writes a time since the unix epoch as floating point seconds, which is
+/// how `WallNST` and `WallMST` have always been displayed. The alternate
+/// flag and the `pretty-print` feature are handled as by `fmt_since_epoch`.
+fn fmt_secs_f64(since_epoch: Duration, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if fmt.alternate() || cfg!(feature = "pretty-print") {
+        return fmt_rfc3339(since_epoch, fmt);
+    }
+    write!(fmt, "{}", since_epoch.as_secs_f64())
+}
+
+/// Writes a time since the unix epoch as an RFC3339 date in UTC, using
+/// the days-to-civil algorithm from
+/// <http://howardhinnant.github.io/date_algorithms.html>.
+fn fmt_rfc3339(since_epoch: Duration, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    write!(
+        fmt,
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_nanos()
+    )
+}
+
+/// Returns the start of the bucket of width `bucket` ticks that contains
+/// `ticks`, or if `round` is set, the nearest bucket boundary (rounding
+/// halves up). Panics if `bucket` is zero, or rounding up overflows.
+fn bucket_ticks(ticks: u64, bucket: u64, round: bool) -> u64 {
+    assert!(bucket > 0, "bucket width must be non-zero");
+    let start = ticks - ticks % bucket;
+    if round && ticks - start >= bucket - bucket / 2 {
+        start
+            .checked_add(bucket)
+            .expect("overflow when rounding time")
+    } else {
+        start
+    }
+}
+
+/// Parses a time since the unix epoch, as written by `fmt_since_epoch`.
+/// Seconds with up to nine fractional digits are always accepted.
+fn parse_since_epoch(s: &str) -> Result<Duration> {
+    #[cfg(feature = "pretty-print")]
+    {
+        if let Ok(t) = time::OffsetDateTime::parse(s, &Rfc3339) {
+            return Ok(SystemTime::from(t).duration_since(SystemTime::UNIX_EPOCH)?);
+        }
+    }
+    let invalid = || Error::Parse(s.to_owned());
+    let (secs, frac) = s.split_once('.').unwrap_or((s, ""));
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(secs) || !is_digits(frac) || frac.len() > 9 {
+        return Err(invalid());
+    }
+    let secs = secs.parse().map_err(|_| invalid())?;
+    let nanos = format!("{:0<9}", frac).parse().map_err(|_| invalid())?;
+    Ok(Duration::new(secs, nanos))
+}
+
+/// Parses a time since the unix epoch, as written by `fmt_secs_f64`. As
+/// floating point seconds may carry more than nine fractional digits, they
+/// are rounded to the nearest nanosecond.
+fn parse_secs_f64(s: &str) -> Result<Duration> {
+    parse_since_epoch(s).or_else(|err| {
+        if !s.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+            return Err(err);
+        }
+        let secs: f64 = s.parse().map_err(|_| Error::Parse(s.to_owned()))?;
+        Duration::try_from_secs_f64(secs).map_err(|_| Error::Parse(s.to_owned()))
+    })
+}
+
 /// Describes the interface that the inner clock source must provide.
 pub trait ClockSource {
     /// Represents the described clock time.
@@ -22,4 +185,136 @@ pub trait ClockSource {
 
     /// Returns the current clock time.
     fn now(&mut self) -> Result<Self::Time>;
+
+    /// Returns a bound on how far readings may be from true time, if the
+    /// source knows one. See `UncertainTimestamp`.
+    fn uncertainty(&self) -> Option<Self::Delta> {
+        None
+    }
+}
+
+/// A type-erased clock source, for when the concrete source should be
+/// chosen at runtime (eg: a real clock in production and a `ManualClock` in
+/// tests) without making every containing type generic.
+pub type BoxedClockSource<T, D> = Box<dyn ClockSource<Time = T, Delta = D> + Send>;
+
+impl<S: ClockSource + ?Sized> ClockSource for Box<S> {
+    type Time = S::Time;
+    type Delta = S::Delta;
+    fn now(&mut self) -> Result<Self::Time> {
+        (**self).now()
+    }
+    fn uncertainty(&self) -> Option<Self::Delta> {
+        (**self).uncertainty()
+    }
+}
+
+/// A clock source that can be read through a shared reference, and so may
+/// be used without exclusive access to the source.
+pub trait SharedClockSource: ClockSource {
+    /// Returns the current clock time.
+    fn now_shared(&self) -> Result<Self::Time>;
+}
+
+impl<S: SharedClockSource + ?Sized> ClockSource for Arc<S> {
+    type Time = S::Time;
+    type Delta = S::Delta;
+    fn now(&mut self) -> Result<Self::Time> {
+        (**self).now_shared()
+    }
+    fn uncertainty(&self) -> Option<Self::Delta> {
+        (**self).uncertainty()
+    }
+}
+
+impl<S: SharedClockSource + ?Sized> SharedClockSource for Arc<S> {
+    fn now_shared(&self) -> Result<Self::Time> {
+        (**self).now_shared()
+    }
+}
+
+/// A clock source whose readings can never fail.
+pub trait InfallibleClockSource: ClockSource {
+    /// Returns the current clock time.
+    fn now_infallible(&mut self) -> Self::Time;
+}
+
+/// Clock times and deltas that can be expressed as a `Duration`, so that
+/// they may be reported independently of the clock source. Times are
+/// expressed as the duration since the source's epoch. Plain integers and
+/// manual clock times are treated as nanoseconds.
+pub trait AsDuration {
+    /// Returns the equivalent `Duration`.
+    fn as_duration(&self) -> Duration;
+}
+
+impl AsDuration for Duration {
+    fn as_duration(&self) -> Duration {
+        *self
+    }
+}
+
+impl AsDuration for u32 {
+    fn as_duration(&self) -> Duration {
+        Duration::from_nanos(u64::from(*self))
+    }
+}
+
+impl AsDuration for u64 {
+    fn as_duration(&self) -> Duration {
+        Duration::from_nanos(*self)
+    }
+}
+
+impl AsDuration for u128 {
+    /// Saturates at `Duration::MAX`.
+    fn as_duration(&self) -> Duration {
+        let secs = u64::try_from(*self / u128::from(NANOS_PER_SEC)).unwrap_or(u64::MAX);
+        Duration::new(secs, (*self % u128::from(NANOS_PER_SEC)) as u32)
+    }
+}
+
+/// Clock times that can be built from a `Duration` since the source's
+/// epoch; the inverse of `AsDuration`.
+pub trait FromDuration: Sized {
+    /// Returns the equivalent time, or fails if it is not representable.
+    fn from_duration(since_epoch: Duration) -> Result<Self>;
+}
+
+/// Clock deltas that can be scaled by a factor, as needed by sources that
+/// simulate faulty or accelerated clocks.
+pub trait ScalableDelta: Sized {
+    /// Returns this delta multiplied by `factor`, saturating at the bounds
+    /// of the type. Negative factors are treated as zero.
+    fn mul_f64(self, factor: f64) -> Self;
+}
+
+impl ScalableDelta for Duration {
+    fn mul_f64(self, factor: f64) -> Self {
+        Duration::try_from_secs_f64(self.as_secs_f64() * factor.max(0.0)).unwrap_or(Duration::MAX)
+    }
+}
+
+impl ScalableDelta for u64 {
+    fn mul_f64(self, factor: f64) -> Self {
+        // Float to integer casts saturate.
+        (self as f64 * factor) as u64
+    }
+}
+
+/// Clock times with a least and greatest value.
+pub trait TimeBounds {
+    /// The earliest representable time.
+    const MIN: Self;
+    /// The latest representable time.
+    const MAX: Self;
+}
+
+/// Clock times that can be losslessly represented as a `u64` tick count,
+/// which must sort in the same order as the times; see `ByteOrdered`.
+pub trait U64Time: Copy {
+    /// Returns the number of ticks since the source's epoch.
+    fn as_u64(self) -> u64;
+    /// Builds a time from the number of ticks since the source's epoch.
+    fn of_u64(val: u64) -> Self;
 }
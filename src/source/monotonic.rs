@@ -0,0 +1,233 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use super::{ClockSource, SharedClockSource, WallNS, WallNST};
+use crate::Result;
+
+/// A clock source that returns wall-clock time in nanoseconds, as `WallNS`
+/// does, but only reads the system clock once, at construction. Subsequent
+/// readings add the time elapsed on the monotonic clock to that anchor, so
+/// never go backwards, even if the system clock is stepped by NTP.
+///
+/// Over long periods readings will drift away from the system clock, as
+/// slew adjustments are not followed either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonotonicNS {
+    base: WallNST,
+    anchor: Instant,
+}
+
+impl MonotonicNS {
+    /// Creates a source anchored to the current system time.
+    pub fn new() -> Result<Self> {
+        // Reading the system clock first ensures we never run ahead of it.
+        let base = WallNST::from_timespec(SystemTime::now())?;
+        let anchor = Instant::now();
+        Ok(MonotonicNS::anchored_at(base, anchor))
+    }
+
+    /// Creates a source which reads `base` at the instant `anchor`.
+    pub fn anchored_at(base: WallNST, anchor: Instant) -> Self {
+        MonotonicNS { base, anchor }
+    }
+
+    /// Returns the wall-clock time that this source is anchored to.
+    pub fn base(&self) -> WallNST {
+        self.base
+    }
+
+    // Returns the reading this source gives at `instant`.
+    fn at(&self, instant: Instant) -> Result<WallNST> {
+        let elapsed = instant.saturating_duration_since(self.anchor);
+        WallNST::from_since_epoch(self.base.duration_since_epoch() + elapsed)
+    }
+}
+
+impl ClockSource for MonotonicNS {
+    type Time = WallNST;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for MonotonicNS {
+    fn now_shared(&self) -> Result<Self::Time> {
+        self.at(Instant::now())
+    }
+}
+
+/// A clock source that follows the system clock whilst it is slewed, but
+/// refuses sudden steps.
+///
+/// Readings are taken from the monotonic clock, as with `MonotonicNS`. Every
+/// `reanchor_interval` the system clock (or another `realtime` source) is
+/// consulted, and if it is within `max_step` of our own reading, we
+/// re-anchor to it. Larger divergences are counted and ignored, so that
+/// the source keeps counting monotonic time. Readings never go backwards.
+#[derive(Debug, Clone)]
+pub struct SlewTrackingNS<S = WallNS> {
+    realtime: S,
+    mono: MonotonicNS,
+    reanchor_interval: Duration,
+    max_step: Duration,
+    last: WallNST,
+    steps_refused: u64,
+}
+
+impl SlewTrackingNS<WallNS> {
+    /// Creates a source that follows the system clock, re-anchoring every
+    /// `reanchor_interval`, provided it has moved by no more than
+    /// `max_step` relative to the monotonic clock.
+    pub fn new(reanchor_interval: Duration, max_step: Duration) -> Result<Self> {
+        SlewTrackingNS::with_realtime(WallNS, reanchor_interval, max_step)
+    }
+}
+
+impl<S: ClockSource<Time = WallNST, Delta = Duration>> SlewTrackingNS<S> {
+    /// As `new`, but follows `realtime` rather than the system clock.
+    pub fn with_realtime(
+        mut realtime: S,
+        reanchor_interval: Duration,
+        max_step: Duration,
+    ) -> Result<Self> {
+        let base = realtime.now()?;
+        let anchor = Instant::now();
+        Ok(SlewTrackingNS {
+            realtime,
+            mono: MonotonicNS::anchored_at(base, anchor),
+            reanchor_interval,
+            max_step,
+            last: base,
+            steps_refused: 0,
+        })
+    }
+
+    /// Returns how many times the realtime source was found to have stepped
+    /// by more than `max_step`, and was ignored.
+    pub fn steps_refused(&self) -> u64 {
+        self.steps_refused
+    }
+
+    fn reanchor(&mut self) -> Result<()> {
+        let realtime = self.realtime.now()?;
+        let anchor = Instant::now();
+        let ours = self.mono.at(anchor)?;
+        let divergence = if realtime > ours {
+            realtime - ours
+        } else {
+            ours - realtime
+        };
+        let base = if divergence <= self.max_step {
+            realtime.max(self.last)
+        } else {
+            self.steps_refused += 1;
+            ours
+        };
+        self.mono = MonotonicNS::anchored_at(base, anchor);
+        Ok(())
+    }
+}
+
+impl<S: ClockSource<Time = WallNST, Delta = Duration>> ClockSource for SlewTrackingNS<S> {
+    type Time = WallNST;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        if self.mono.anchor.elapsed() >= self.reanchor_interval {
+            self.reanchor()?;
+        }
+        let t = self.mono.now_shared()?.max(self.last);
+        self.last = t;
+        Ok(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn should_start_at_system_time() -> Result<()> {
+        let before = WallNST::from_timespec(SystemTime::now())?;
+        let mut src = MonotonicNS::new()?;
+        let t = src.now()?;
+        let after = WallNST::from_timespec(SystemTime::now())?;
+        assert!(
+            before <= src.base() && t <= after,
+            "{} <= {} <= {}",
+            before,
+            t,
+            after
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_follow_elapsed_time_from_anchor() -> Result<()> {
+        let base = WallNST::of_u64(1_000_000_000);
+        let anchor = Instant::now() - Duration::from_secs(5);
+        let mut src = MonotonicNS::anchored_at(base, anchor);
+        let a = src.now()?;
+        let b = src.now()?;
+        assert!(a - base >= Duration::from_secs(5));
+        assert!(a <= b);
+        Ok(())
+    }
+
+    #[derive(Debug, Clone)]
+    struct Realtime(Rc<Cell<WallNST>>);
+
+    impl ClockSource for Realtime {
+        type Time = WallNST;
+        type Delta = Duration;
+        fn now(&mut self) -> Result<WallNST> {
+            Ok(self.0.get())
+        }
+    }
+
+    fn slew_tracking(base: WallNST) -> Result<(Rc<Cell<WallNST>>, SlewTrackingNS<Realtime>)> {
+        let cell = Rc::new(Cell::new(base));
+        let src = SlewTrackingNS::with_realtime(
+            Realtime(cell.clone()),
+            Duration::from_secs(0),
+            Duration::from_secs(1),
+        )?;
+        Ok((cell, src))
+    }
+
+    #[test]
+    fn should_follow_slew() -> Result<()> {
+        let base = WallNST::of_u64(1_000_000_000_000);
+        let (realtime, mut src) = slew_tracking(base)?;
+        realtime.set(base + Duration::from_millis(500));
+        let t = src.now()?;
+        assert!(t >= base + Duration::from_millis(500), "{}", t);
+        assert_eq!(src.steps_refused(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_steps() -> Result<()> {
+        let base = WallNST::of_u64(1_000_000_000_000);
+        let (realtime, mut src) = slew_tracking(base)?;
+        realtime.set(base + Duration::from_secs(3600));
+        let t = src.now()?;
+        assert!(t < base + Duration::from_secs(1), "{}", t);
+        assert_eq!(src.steps_refused(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_go_backwards_when_slewed_back() -> Result<()> {
+        let base = WallNST::of_u64(1_000_000_000_000);
+        let (realtime, mut src) = slew_tracking(base)?;
+        realtime.set(base + Duration::from_millis(500));
+        let a = src.now()?;
+        realtime.set(base);
+        let b = src.now()?;
+        assert!(a <= b, "{} <= {}", a, b);
+        assert_eq!(src.steps_refused(), 0);
+        Ok(())
+    }
+}
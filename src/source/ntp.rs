@@ -0,0 +1,164 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use super::{ClockSource, SharedClockSource, WallNST};
+use crate::{Error, Result};
+
+/// The kernel's view of how well the system clock is synchronized, as
+/// reported by `adjtimex(2)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NtpStatus {
+    /// Whether the kernel considers the clock synchronized, ie: the
+    /// synchronization daemon has not marked it unsynchronized, and it is
+    /// not in the `TIME_ERROR` state.
+    pub synchronized: bool,
+    /// The maximum error of the clock, which grows whilst the clock is not
+    /// being disciplined.
+    pub max_error: Duration,
+    /// The estimated error of the clock.
+    pub est_error: Duration,
+    /// The system time at which the status was read.
+    pub time: WallNST,
+}
+
+impl NtpStatus {
+    /// Reads the current status from the kernel, without adjusting the clock.
+    pub fn read() -> Result<Self> {
+        // SAFETY: `timex` is plain old data, for which all zeroes is valid.
+        let mut tx: libc::timex = unsafe { std::mem::zeroed() };
+        // With `modes` zero, this only reads the clock state.
+        // SAFETY: `tx` is a valid, writable timex for the duration of the call.
+        let state = unsafe { libc::adjtimex(&mut tx) };
+        if state < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let subsec_nanos = if tx.status & libc::STA_NANO != 0 {
+            tx.time.tv_usec
+        } else {
+            tx.time.tv_usec * 1_000
+        };
+        let since_epoch =
+            Duration::new(u64::try_from(tx.time.tv_sec)?, u32::try_from(subsec_nanos)?);
+        Ok(NtpStatus {
+            synchronized: state != libc::TIME_ERROR && tx.status & libc::STA_UNSYNC == 0,
+            max_error: micros(tx.maxerror)?,
+            est_error: micros(tx.esterror)?,
+            time: WallNST::from_since_epoch(since_epoch)?,
+        })
+    }
+}
+
+fn micros<I>(us: I) -> Result<Duration>
+where
+    u64: TryFrom<I, Error = std::num::TryFromIntError>,
+{
+    Ok(Duration::from_micros(u64::try_from(us)?))
+}
+
+/// A clock source that returns wall-clock time in nanoseconds, as `WallNS`
+/// does, but refuses to issue readings with `Error::Unsynchronized` when the
+/// kernel reports that the clock is not synchronized, or that its maximum
+/// error exceeds a threshold. This stops a node whose synchronization daemon
+/// has died from stamping data with a free-running clock.
+///
+/// Only available on Linux, with the `ntp` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NtpWallNS {
+    max_error: Duration,
+}
+
+impl NtpWallNS {
+    /// Creates a source that refuses readings whilst the kernel's maximum
+    /// error exceeds `max_error`.
+    pub fn new(max_error: Duration) -> Self {
+        NtpWallNS { max_error }
+    }
+
+    /// Returns the greatest maximum error for which readings are issued.
+    pub fn max_error(&self) -> Duration {
+        self.max_error
+    }
+
+    fn check(&self, status: NtpStatus) -> Result<WallNST> {
+        if !status.synchronized || status.max_error > self.max_error {
+            return Err(Error::Unsynchronized {
+                max_error: status.max_error,
+            });
+        }
+        Ok(status.time)
+    }
+}
+
+impl ClockSource for NtpWallNS {
+    type Time = WallNST;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+
+    /// Returns the kernel's current maximum error, if it can be read.
+    fn uncertainty(&self) -> Option<Self::Delta> {
+        NtpStatus::read().ok().map(|status| status.max_error)
+    }
+}
+
+impl SharedClockSource for NtpWallNS {
+    fn now_shared(&self) -> Result<Self::Time> {
+        self.check(NtpStatus::read()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn status(synchronized: bool, max_error: Duration) -> NtpStatus {
+        NtpStatus {
+            synchronized,
+            max_error,
+            est_error: Duration::from_micros(0),
+            time: WallNST::of_u64(1_000),
+        }
+    }
+
+    #[test]
+    fn should_read_kernel_status() -> Result<()> {
+        let before = WallNST::from_timespec(SystemTime::now())?;
+        let status = NtpStatus::read()?;
+        let after = WallNST::from_timespec(SystemTime::now())?;
+        // The kernel's timestamp may be a little coarser than `SystemTime`.
+        let slop = Duration::from_millis(10);
+        assert!(
+            before - slop <= status.time && status.time <= after,
+            "{} <= {} <= {}",
+            before,
+            status.time,
+            after
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_unsynchronized_clock() {
+        let src = NtpWallNS::new(Duration::from_millis(100));
+        assert!(matches!(
+            src.check(status(false, Duration::from_millis(1))),
+            Err(Error::Unsynchronized { .. })
+        ));
+    }
+
+    #[test]
+    fn should_refuse_excessive_max_error() {
+        let src = NtpWallNS::new(Duration::from_millis(100));
+        let max_error = Duration::from_millis(101);
+        match src.check(status(true, max_error)) {
+            Err(Error::Unsynchronized { max_error: e }) => assert_eq!(e, max_error),
+            res => panic!("expected Unsynchronized, got {:?}", res),
+        }
+        assert_eq!(
+            src.check(status(true, Duration::from_millis(100))).ok(),
+            Some(WallNST::of_u64(1_000))
+        );
+    }
+}
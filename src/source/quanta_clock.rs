@@ -0,0 +1,77 @@
+use std::time::{Duration, SystemTime};
+
+use super::{ClockSource, SharedClockSource, WallNST};
+use crate::Result;
+
+/// A clock source that returns wall-clock time in nanoseconds, as `WallNS`
+/// does, but derives its readings from a calibrated `quanta::Clock`, which
+/// reads the CPU's timestamp counter where possible, and so avoids a system
+/// call per reading.
+///
+/// Like `MonotonicNS`, the system clock is only read at construction, and
+/// readings add the time elapsed since, so never go backwards.
+#[derive(Debug, Clone)]
+pub struct WallQuanta {
+    clock: quanta::Clock,
+    base: WallNST,
+    anchor: quanta::Instant,
+}
+
+impl WallQuanta {
+    /// Creates a source using a newly calibrated `quanta::Clock`, anchored
+    /// to the current system time.
+    pub fn new() -> Result<Self> {
+        WallQuanta::with_clock(quanta::Clock::new())
+    }
+
+    /// Creates a source using `clock`, anchored to the current system time.
+    pub fn with_clock(clock: quanta::Clock) -> Result<Self> {
+        let base = WallNST::from_timespec(SystemTime::now())?;
+        let anchor = clock.now();
+        Ok(WallQuanta {
+            clock,
+            base,
+            anchor,
+        })
+    }
+}
+
+impl ClockSource for WallQuanta {
+    type Time = WallNST;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for WallQuanta {
+    fn now_shared(&self) -> Result<Self::Time> {
+        let elapsed = self.clock.now().saturating_duration_since(self.anchor);
+        WallNST::from_since_epoch(self.base.duration_since_epoch() + elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_advance_with_clock() -> Result<()> {
+        let (clock, mock) = quanta::Clock::mock();
+        let mut src = WallQuanta::with_clock(clock)?;
+        let a = src.now()?;
+        mock.increment(Duration::from_millis(5));
+        let b = src.now()?;
+        assert_eq!(b - a, Duration::from_millis(5));
+        Ok(())
+    }
+
+    #[test]
+    fn should_start_near_system_time() -> Result<()> {
+        let t = WallQuanta::new()?.now()?;
+        let system = WallNST::from_timespec(SystemTime::now())?;
+        let skew = if t > system { t - system } else { system - t };
+        assert!(skew < Duration::from_secs(1), "{} vs {}", t, system);
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Sub;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::{
+    fmt_since_epoch, parse_since_epoch, AsDuration, ClockSource, FromDuration, TimeBounds, U64Time,
+};
+use crate::{Error, Result};
+
+/// Seconds from the unix epoch to 2020-01-01T00:00:00Z, a convenient base for
+/// `Rebased` sources.
+pub const BASE_2020_SECS: u64 = 1_577_836_800;
+
+/// A clock source that measures time from a base instant `BASE_SECS` seconds
+/// after the unix epoch, rather than from the epoch itself. This gives
+/// sources such as `WallMS` a longer usable range in their `u64` ticks, and
+/// so in `CompactTimestamp` encodings.
+///
+/// Readings before the base are refused with `Error::SupportedTime`.
+#[derive(Debug, Clone, Default)]
+pub struct Rebased<S, const BASE_SECS: u64> {
+    src: S,
+}
+
+/// A time measured from a base instant `BASE_SECS` seconds after the unix
+/// epoch, as the underlying time type `T`.
+///
+/// When serialized, the base is written alongside the time, and times with
+/// a different base are refused when deserialized, so that peers configured
+/// with different bases cannot silently misinterpret each other's times.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RebasedT<T, const BASE_SECS: u64>(T);
+
+impl<S, const BASE_SECS: u64> Rebased<S, BASE_SECS> {
+    /// Creates a source that rebases readings from `src`.
+    pub fn new(src: S) -> Self {
+        Rebased { src }
+    }
+
+    /// Extract the inner clock source
+    pub fn into_inner(self) -> S {
+        self.src
+    }
+}
+
+impl<T: U64Time + FromDuration, const BASE_SECS: u64> RebasedT<T, BASE_SECS> {
+    /// Returns the base instant, as a time since the unix epoch.
+    pub fn base() -> Result<T> {
+        T::from_duration(Duration::from_secs(BASE_SECS))
+    }
+
+    /// Rebases `t`, a time since the unix epoch. Fails if `t` is before the
+    /// base.
+    pub fn from_absolute(t: T) -> Result<Self> {
+        let base = Self::base()?.as_u64();
+        let ticks = t
+            .as_u64()
+            .checked_sub(base)
+            .ok_or(Error::SupportedTime(u128::from(t.as_u64())))?;
+        Ok(RebasedT(T::of_u64(ticks)))
+    }
+
+    /// Returns the equivalent time since the unix epoch, or fails if that is
+    /// not representable by `T`.
+    pub fn to_absolute(self) -> Result<T> {
+        let ticks = u128::from(Self::base()?.as_u64()) + u128::from(self.0.as_u64());
+        Ok(T::of_u64(
+            u64::try_from(ticks).map_err(|_| Error::SupportedTime(ticks))?,
+        ))
+    }
+}
+
+impl<T, const BASE_SECS: u64> RebasedT<T, BASE_SECS> {
+    /// Returns the time since the base, as the underlying time type.
+    pub fn since_base(self) -> T {
+        self.0
+    }
+
+    /// Builds a time from the time since the base.
+    pub const fn of_since_base(t: T) -> Self {
+        RebasedT(t)
+    }
+}
+
+impl<S, const BASE_SECS: u64> ClockSource for Rebased<S, BASE_SECS>
+where
+    S: ClockSource,
+    S::Time: U64Time + FromDuration,
+{
+    type Time = RebasedT<S::Time, BASE_SECS>;
+    type Delta = S::Delta;
+    fn now(&mut self) -> Result<Self::Time> {
+        RebasedT::from_absolute(self.src.now()?)
+    }
+    fn uncertainty(&self) -> Option<Self::Delta> {
+        self.src.uncertainty()
+    }
+}
+
+impl<T: Sub, const BASE_SECS: u64> Sub for RebasedT<T, BASE_SECS> {
+    type Output = T::Output;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.0 - rhs.0
+    }
+}
+
+impl<T: AsDuration, const BASE_SECS: u64> AsDuration for RebasedT<T, BASE_SECS> {
+    /// Returns the duration since the unix epoch, saturating at
+    /// `Duration::MAX`.
+    fn as_duration(&self) -> Duration {
+        Duration::from_secs(BASE_SECS).saturating_add(self.0.as_duration())
+    }
+}
+
+impl<T: TimeBounds, const BASE_SECS: u64> TimeBounds for RebasedT<T, BASE_SECS> {
+    const MIN: Self = RebasedT(T::MIN);
+    const MAX: Self = RebasedT(T::MAX);
+}
+
+impl<T: U64Time, const BASE_SECS: u64> U64Time for RebasedT<T, BASE_SECS> {
+    fn as_u64(self) -> u64 {
+        self.0.as_u64()
+    }
+    fn of_u64(val: u64) -> Self {
+        RebasedT(T::of_u64(val))
+    }
+}
+
+impl<T: fmt::Debug, const BASE_SECS: u64> fmt::Debug for RebasedT<T, BASE_SECS> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "RebasedT({}, {:?})", BASE_SECS, self.0)
+    }
+}
+
+/// Writes the time since the unix epoch, as the underlying time type would.
+impl<T: AsDuration, const BASE_SECS: u64> fmt::Display for RebasedT<T, BASE_SECS> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_since_epoch(self.as_duration(), fmt)
+    }
+}
+
+impl<T: U64Time + FromDuration, const BASE_SECS: u64> FromStr for RebasedT<T, BASE_SECS> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        RebasedT::from_absolute(T::from_duration(parse_since_epoch(s)?)?)
+    }
+}
+
+#[cfg(feature = "serialization")]
+mod serde_impl {
+    use serde::{de, ser};
+
+    use super::RebasedT;
+
+    #[derive(Serialize, Deserialize)]
+    struct Rebased<T>(u64, T);
+
+    impl<T: ser::Serialize + Copy, const BASE_SECS: u64> ser::Serialize for RebasedT<T, BASE_SECS> {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Rebased(BASE_SECS, self.0).serialize(serializer)
+        }
+    }
+
+    impl<'de, T: de::Deserialize<'de>, const BASE_SECS: u64> de::Deserialize<'de>
+        for RebasedT<T, BASE_SECS>
+    {
+        fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            let Rebased(base, time) = de::Deserialize::deserialize(deserializer)?;
+            if base != BASE_SECS {
+                return Err(de::Error::custom(format_args!(
+                    "time has base {}, expected {}",
+                    base, BASE_SECS
+                )));
+            }
+            Ok(RebasedT(time))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ManualClock, ManualT, WallMS, WallMST, WallNST};
+
+    type Rebased2020<S> = Rebased<S, BASE_2020_SECS>;
+
+    #[test]
+    fn should_measure_from_base() -> Result<()> {
+        let base = Duration::from_secs(BASE_2020_SECS);
+        let src = ManualClock::new(base.as_nanos() as u64 + 5);
+        let mut rebased = Rebased2020::new(src);
+        let t = rebased.now()?;
+        assert_eq!(t.since_base(), ManualT::from(5));
+        assert_eq!(t.as_duration(), base + Duration::from_nanos(5));
+        assert_eq!(t.to_absolute()?, ManualT::from(base.as_nanos() as u64 + 5));
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_times_before_base() {
+        let mut rebased = Rebased2020::new(ManualClock::new(5));
+        assert!(matches!(rebased.now(), Err(Error::SupportedTime(5))));
+    }
+
+    #[test]
+    fn should_extend_range_past_absolute_limit() -> Result<()> {
+        let t = RebasedT::<WallMST, BASE_2020_SECS>::of_since_base(WallMST::of_u64(u64::MAX));
+        assert!(t.to_absolute().is_err());
+        assert!(t.as_duration() > WallMST::of_u64(u64::MAX).duration_since_epoch());
+        let _ = Rebased2020::new(WallMS).now()?;
+        Ok(())
+    }
+
+    #[test]
+    fn should_round_trip_via_display() -> Result<()> {
+        let t = RebasedT::<WallNST, BASE_2020_SECS>::of_since_base(WallNST::of_u64(1_500));
+        assert_eq!(
+            t.to_string().parse::<RebasedT<WallNST, BASE_2020_SECS>>()?,
+            t
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "serialization")]
+    #[test]
+    fn should_refuse_mismatched_base() {
+        let t = RebasedT::<WallNST, BASE_2020_SECS>::of_since_base(WallNST::of_u64(42));
+        let json = serde_json::to_string(&t).expect("serialize");
+        assert_eq!(json, format!("[{},42]", BASE_2020_SECS));
+        let back: RebasedT<WallNST, BASE_2020_SECS> =
+            serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(back, t);
+        assert!(serde_json::from_str::<RebasedT<WallNST, 0>>(&json).is_err());
+    }
+}
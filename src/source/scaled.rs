@@ -0,0 +1,90 @@
+use std::ops::{Add, Sub};
+
+use super::{ClockSource, ScalableDelta};
+use crate::Result;
+
+/// A clock source that runs faster (or slower) than another by a constant
+/// factor, by scaling the time elapsed since its first reading. This allows
+/// eg: lease expiry to be simulated quickly in tests, whilst still using the
+/// inner source's time arithmetic.
+#[derive(Debug, Clone)]
+pub struct ScaledSource<S: ClockSource> {
+    src: S,
+    anchor: S::Time,
+    factor: f64,
+}
+
+impl<S: ClockSource> ScaledSource<S> {
+    /// Wraps `src`, so that time passes `factor` times as quickly from its
+    /// current reading.
+    pub fn new(mut src: S, factor: f64) -> Result<Self> {
+        let anchor = src.now()?;
+        Ok(ScaledSource {
+            src,
+            anchor,
+            factor,
+        })
+    }
+
+    /// Returns the factor by which time is scaled.
+    pub fn factor(&self) -> f64 {
+        self.factor
+    }
+
+    /// Get a reference to the inner clock source.
+    pub fn inner(&self) -> &S {
+        &self.src
+    }
+
+    /// Extract the inner clock source.
+    pub fn into_inner(self) -> S {
+        self.src
+    }
+}
+
+impl<S: ClockSource> ClockSource for ScaledSource<S>
+where
+    S::Time: Add<S::Delta, Output = S::Time> + Sub<S::Delta, Output = S::Time>,
+    S::Delta: ScalableDelta,
+{
+    type Time = S::Time;
+    type Delta = S::Delta;
+    /// Panics if the scaled reading falls outside of the time range.
+    fn now(&mut self) -> Result<Self::Time> {
+        let t = self.src.now()?;
+        // Spelt out, as `S::Time` may also be subtracted by `S::Delta`.
+        let elapsed = <S::Time as Sub>::sub;
+        if t >= self.anchor {
+            Ok(self.anchor + elapsed(t, self.anchor).mul_f64(self.factor))
+        } else {
+            Ok(self.anchor - elapsed(self.anchor, t).mul_f64(self.factor))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ManualClock, ManualT, WallNS};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn should_scale_elapsed_time() -> Result<()> {
+        let mut src = ScaledSource::new(ManualClock::new(1_000), 60.0)?;
+        src.inner().set_time(1_010);
+        assert_eq!(src.now()?, ManualT::from(1_600));
+        src.inner().set_time(999);
+        assert_eq!(src.now()?, ManualT::from(940));
+        Ok(())
+    }
+
+    #[test]
+    fn should_accelerate_wall_clock() -> Result<()> {
+        let mut src = ScaledSource::new(WallNS, 1_000.0)?;
+        let start = src.now()?;
+        thread::sleep(Duration::from_millis(2));
+        assert!(src.now()? - start >= Duration::from_secs(2));
+        Ok(())
+    }
+}
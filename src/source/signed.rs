@@ -0,0 +1,249 @@
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use super::{
+    fmt_since_epoch, parse_since_epoch, AsDuration, ClockSource, SharedClockSource, TimeBounds,
+    U64Time,
+};
+use crate::{ByteOrdered, Error, Result, Timestamp};
+
+/// A clock source that returns wall-clock time in nanoseconds, as a signed
+/// offset from the unix epoch. Unlike `WallNS`, this can represent times
+/// before 1970 (back to 1677), eg: for ingesting historical records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SignedWallNS;
+/// A time in nanoseconds before (if negative) or after the unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SignedWallNST(i64);
+
+// Flipping the sign bit maps signed values onto unsigned ones in order.
+const SIGN_BIT: u64 = 1 << 63;
+
+impl SignedWallNST {
+    /// Returns a `SignedWallNST` representing the `SystemTime`. Fails if the
+    /// time is more than about 292 years from the unix epoch.
+    pub fn from_timespec(t: SystemTime) -> Result<Self> {
+        match t.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(after) => Ok(SignedWallNST(after.as_nanos().try_into()?)),
+            Err(e) => {
+                let before = i128::try_from(e.duration().as_nanos())?;
+                Ok(SignedWallNST((-before).try_into()?))
+            }
+        }
+    }
+
+    /// Returns a `SystemTime` representing this timestamp.
+    pub fn as_systemtime(self) -> SystemTime {
+        let magnitude = Duration::from_nanos(self.0.unsigned_abs());
+        if self.0 < 0 {
+            SystemTime::UNIX_EPOCH - magnitude
+        } else {
+            SystemTime::UNIX_EPOCH + magnitude
+        }
+    }
+
+    /// Returns time in nanoseconds since the unix epoch, which is negative
+    /// for earlier times.
+    pub const fn as_i64(self) -> i64 {
+        self.0
+    }
+
+    /// Builds a time from nanoseconds since the unix epoch.
+    pub const fn of_i64(nanos: i64) -> Self {
+        SignedWallNST(nanos)
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or `None` if
+    /// `earlier` is after `self`.
+    pub fn checked_sub(self, earlier: Self) -> Option<Duration> {
+        if earlier > self {
+            return None;
+        }
+        Some(Duration::from_nanos(self.0.abs_diff(earlier.0)))
+    }
+
+    /// Returns the time `d` after `self`, or `None` if that is not
+    /// representable.
+    pub fn checked_add_duration(self, d: Duration) -> Option<Self> {
+        let nanos = i64::try_from(d.as_nanos()).ok()?;
+        self.0.checked_add(nanos).map(SignedWallNST)
+    }
+
+    /// Returns the time `d` before `self`, or `None` if that is not
+    /// representable.
+    pub fn checked_sub_duration(self, d: Duration) -> Option<Self> {
+        let nanos = i64::try_from(d.as_nanos()).ok()?;
+        self.0.checked_sub(nanos).map(SignedWallNST)
+    }
+}
+
+impl Sub for SignedWallNST {
+    type Output = Duration;
+    /// Panics if `rhs` is after `self`; see `checked_sub`.
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs).expect("subtraction underflow")
+    }
+}
+
+impl Add<Duration> for SignedWallNST {
+    type Output = SignedWallNST;
+    /// Panics if the result is not representable; see `checked_add_duration`.
+    fn add(self, d: Duration) -> Self::Output {
+        self.checked_add_duration(d)
+            .expect("overflow when adding duration to time")
+    }
+}
+
+impl Sub<Duration> for SignedWallNST {
+    type Output = SignedWallNST;
+    /// Panics if the result is not representable; see `checked_sub_duration`.
+    fn sub(self, d: Duration) -> Self::Output {
+        self.checked_sub_duration(d)
+            .expect("overflow when subtracting duration from time")
+    }
+}
+
+impl ClockSource for SignedWallNS {
+    type Time = SignedWallNST;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for SignedWallNS {
+    fn now_shared(&self) -> Result<Self::Time> {
+        SignedWallNST::from_timespec(SystemTime::now())
+    }
+}
+
+impl AsDuration for SignedWallNST {
+    /// Times before the unix epoch are reported as zero.
+    fn as_duration(&self) -> Duration {
+        Duration::from_nanos(u64::try_from(self.0).unwrap_or(0))
+    }
+}
+
+impl TimeBounds for SignedWallNST {
+    const MIN: Self = SignedWallNST(i64::MIN);
+    const MAX: Self = SignedWallNST(i64::MAX);
+}
+
+/// The `u64` form is offset so that it sorts in the same order as the times.
+impl U64Time for SignedWallNST {
+    fn as_u64(self) -> u64 {
+        (self.0 as u64) ^ SIGN_BIT
+    }
+    fn of_u64(val: u64) -> Self {
+        SignedWallNST((val ^ SIGN_BIT) as i64)
+    }
+}
+
+/// Writes the time as for `WallNST`, with a leading `-` for times before the
+/// unix epoch, which are never written as RFC3339 dates.
+impl fmt::Display for SignedWallNST {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let magnitude = Duration::from_nanos(self.0.unsigned_abs());
+        if self.0 < 0 {
+            write!(
+                fmt,
+                "-{}.{:09}",
+                magnitude.as_secs(),
+                magnitude.subsec_nanos()
+            )
+        } else {
+            fmt_since_epoch(magnitude, fmt)
+        }
+    }
+}
+
+impl FromStr for SignedWallNST {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::Parse(s.to_owned());
+        let (negative, rest) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let nanos = i128::try_from(parse_since_epoch(rest)?.as_nanos()).map_err(|_| invalid())?;
+        let nanos = if negative { -nanos } else { nanos };
+        Ok(SignedWallNST(nanos.try_into().map_err(|_| invalid())?))
+    }
+}
+
+impl Timestamp<SignedWallNST> {
+    /// Returns the `SystemTime` represented by the physical component of
+    /// this timestamp.
+    pub fn systemtime(&self) -> SystemTime {
+        self.time.as_systemtime()
+    }
+
+    /// Encodes the timestamp such that the encodings sort in the same order
+    /// as the timestamps, including those before the unix epoch.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        self.to_ordered_bytes()
+    }
+
+    /// Decodes a timestamp written by `to_bytes`.
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self::from_ordered_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::timestamps;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    fn signed_wallclocks() -> Box<dyn GeneratorObject<Item = SignedWallNST>> {
+        u64s().map(|t| SignedWallNST(t as i64)).boxed()
+    }
+
+    #[test]
+    fn should_represent_times_before_epoch() -> Result<()> {
+        let moon_landing = SystemTime::UNIX_EPOCH - Duration::from_secs(14_182_940);
+        let t = SignedWallNST::from_timespec(moon_landing)?;
+        assert_eq!(t.as_i64(), -14_182_940_000_000_000);
+        assert_eq!(t.as_systemtime(), moon_landing);
+        assert_eq!(t.to_string(), "-14182940.000000000");
+        Ok(())
+    }
+
+    #[test]
+    fn bytes_should_order_as_timestamps() {
+        property((
+            timestamps(signed_wallclocks()),
+            timestamps(signed_wallclocks()),
+        ))
+        .check(|(ta, tb)| {
+            assert_eq!(Timestamp::<SignedWallNST>::from_bytes(ta.to_bytes()), ta);
+            ta.cmp(&tb) == ta.to_bytes().cmp(&tb.to_bytes())
+        })
+    }
+
+    #[test]
+    fn should_round_trip_via_display() {
+        property(timestamps(signed_wallclocks())).check(|ts| {
+            let s = ts.to_string();
+            assert_eq!(
+                s.parse::<Timestamp<SignedWallNST>>().expect("parse"),
+                ts,
+                "{}",
+                s
+            );
+        })
+    }
+
+    #[test]
+    fn sub_should_span_epoch() {
+        let (a, b) = (SignedWallNST(-5), SignedWallNST(7));
+        assert_eq!(b - a, Duration::from_nanos(12));
+        assert_eq!(a.checked_sub(b), None);
+    }
+}
@@ -0,0 +1,127 @@
+use std::ops::{Add, Sub};
+
+use super::ClockSource;
+use crate::Result;
+
+/// How far, and in which direction, a `SkewedSource` is offset from its
+/// inner source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Skew<D> {
+    /// Readings are this far ahead of the inner source.
+    Ahead(D),
+    /// Readings are this far behind the inner source.
+    Behind(D),
+}
+
+/// A clock source that offsets the readings of another, to simulate a node
+/// whose clock is wrong, eg: in fault injection tests.
+///
+/// The offset may be constant, or ramp up by a fixed step after each
+/// reading, to simulate a clock that drifts further away over time.
+#[derive(Debug, Clone)]
+pub struct SkewedSource<S: ClockSource> {
+    src: S,
+    skew: Skew<S::Delta>,
+    ramp: Option<S::Delta>,
+}
+
+impl<S: ClockSource> SkewedSource<S> {
+    /// Wraps `src`, offsetting its readings by `skew`.
+    pub fn new(src: S, skew: Skew<S::Delta>) -> Self {
+        SkewedSource {
+            src,
+            skew,
+            ramp: None,
+        }
+    }
+
+    /// Wraps `src`, so that readings are `offset` ahead of it.
+    pub fn ahead(src: S, offset: S::Delta) -> Self {
+        SkewedSource::new(src, Skew::Ahead(offset))
+    }
+
+    /// Wraps `src`, so that readings are `offset` behind it.
+    pub fn behind(src: S, offset: S::Delta) -> Self {
+        SkewedSource::new(src, Skew::Behind(offset))
+    }
+
+    /// Increases the magnitude of the offset by `step` after every reading.
+    pub fn with_ramp(self, step: S::Delta) -> Self {
+        SkewedSource {
+            ramp: Some(step),
+            ..self
+        }
+    }
+
+    /// Returns the offset that will be applied to the next reading.
+    pub fn skew(&self) -> &Skew<S::Delta> {
+        &self.skew
+    }
+
+    /// Changes the offset applied to further readings.
+    pub fn set_skew(&mut self, skew: Skew<S::Delta>) {
+        self.skew = skew;
+    }
+
+    /// Get a reference to the inner clock source.
+    pub fn inner(&self) -> &S {
+        &self.src
+    }
+
+    /// Extract the inner clock source.
+    pub fn into_inner(self) -> S {
+        self.src
+    }
+}
+
+impl<S: ClockSource> ClockSource for SkewedSource<S>
+where
+    S::Time: Add<S::Delta, Output = S::Time> + Sub<S::Delta, Output = S::Time>,
+    S::Delta: Copy + Add<Output = S::Delta>,
+{
+    type Time = S::Time;
+    type Delta = S::Delta;
+    /// Panics if a reading offset behind the inner source would precede the
+    /// start of its time range.
+    fn now(&mut self) -> Result<Self::Time> {
+        let t = self.src.now()?;
+        let (skewed, next) = match self.skew {
+            Skew::Ahead(d) => (t + d, Skew::Ahead(d)),
+            Skew::Behind(d) => (t - d, Skew::Behind(d)),
+        };
+        if let Some(step) = self.ramp {
+            self.skew = match next {
+                Skew::Ahead(d) => Skew::Ahead(d + step),
+                Skew::Behind(d) => Skew::Behind(d + step),
+            };
+        }
+        Ok(skewed)
+    }
+    fn uncertainty(&self) -> Option<Self::Delta> {
+        self.src.uncertainty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ManualClock, ManualT};
+
+    #[test]
+    fn should_offset_readings() -> Result<()> {
+        let mut ahead = SkewedSource::ahead(ManualClock::new(100), 20);
+        let mut behind = SkewedSource::behind(ManualClock::new(100), 20);
+        assert_eq!(ahead.now()?, ManualT::from(120));
+        assert_eq!(behind.now()?, ManualT::from(80));
+        Ok(())
+    }
+
+    #[test]
+    fn should_ramp_offset() -> Result<()> {
+        let mut src = SkewedSource::ahead(ManualClock::new(100), 0).with_ramp(5);
+        assert_eq!(src.now()?, ManualT::from(100));
+        assert_eq!(src.now()?, ManualT::from(105));
+        assert_eq!(src.skew(), &Skew::Ahead(10));
+        Ok(())
+    }
+}
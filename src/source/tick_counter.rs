@@ -0,0 +1,142 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use super::{ClockSource, WallNST};
+use crate::Result;
+
+/// A free-running hardware tick counter, such as a SysTick or `CYCCNT`
+/// register. Any `FnMut() -> u64` is a `TickCounter`.
+///
+/// Like the rest of the crate, this requires `std`; `no_std` targets are
+/// not supported.
+pub trait TickCounter {
+    /// Returns the current raw counter value. Counters narrower than 64 bits
+    /// should be described with `TickCounterSource::with_counter_bits`.
+    fn ticks(&mut self) -> u64;
+}
+
+impl<F: FnMut() -> u64> TickCounter for F {
+    fn ticks(&mut self) -> u64 {
+        self()
+    }
+}
+
+/// A clock source for embedded nodes, backed by a monotonic tick counter
+/// with a configurable tick rate. Readings are the counter's elapsed time
+/// added to a base time (by default, the unix epoch), and are given as
+/// `WallNST`, so that such nodes use the same `Timestamp` wire format as
+/// other nodes.
+///
+/// Counters that wrap, such as a 32-bit `CYCCNT`, are extended to 64 bits,
+/// provided that the source is read at least once per wrap period.
+#[derive(Debug, Clone)]
+pub struct TickCounterSource<C> {
+    counter: C,
+    nanos: u64,
+    ticks: u64,
+    base: WallNST,
+    mask: u64,
+    last_raw: u64,
+    wrapped: u64,
+}
+
+impl<C: TickCounter> TickCounterSource<C> {
+    /// Creates a source whose counter advances `ticks_per_sec` times a
+    /// second. Panics if `ticks_per_sec` is zero.
+    pub fn new(counter: C, ticks_per_sec: u64) -> Self {
+        TickCounterSource::with_ratio(counter, 1_000_000_000, ticks_per_sec)
+    }
+
+    /// Creates a source whose counter advances by `ticks` every `nanos`
+    /// nanoseconds. Panics if `ticks` is zero.
+    pub fn with_ratio(counter: C, nanos: u64, ticks: u64) -> Self {
+        assert!(ticks > 0, "tick ratio must have non-zero ticks");
+        TickCounterSource {
+            counter,
+            nanos,
+            ticks,
+            base: WallNST::of_u64(0),
+            mask: u64::MAX,
+            last_raw: 0,
+            wrapped: 0,
+        }
+    }
+
+    /// Treats the counter as `bits` wide, counting wraps so that readings
+    /// continue to advance. Panics unless `bits` is between 1 and 64.
+    pub fn with_counter_bits(mut self, bits: u32) -> Self {
+        assert!(
+            (1..=64).contains(&bits),
+            "counter width must be 1 to 64 bits"
+        );
+        self.mask = u64::MAX >> (64 - bits);
+        self.last_raw &= self.mask;
+        self
+    }
+
+    /// Adds `base` to every reading, eg: a wall-clock time received from a
+    /// peer when the counter read zero.
+    pub fn with_base(mut self, base: WallNST) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Returns the total ticks counted, accounting for wraps.
+    fn elapsed_ticks(&mut self) -> u128 {
+        let raw = self.counter.ticks() & self.mask;
+        if raw < self.last_raw {
+            self.wrapped += 1;
+        }
+        self.last_raw = raw;
+        u128::from(self.wrapped) * (u128::from(self.mask) + 1) + u128::from(raw)
+    }
+
+    /// Extract the inner counter
+    pub fn into_inner(self) -> C {
+        self.counter
+    }
+}
+
+impl<C: TickCounter> ClockSource for TickCounterSource<C> {
+    type Time = WallNST;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        let nanos = self.elapsed_ticks() * u128::from(self.nanos) / u128::from(self.ticks);
+        let base = u128::from(self.base.as_u64());
+        Ok(WallNST::of_u64(u64::try_from(base + nanos)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn counter(start: u64) -> (Rc<Cell<u64>>, impl FnMut() -> u64) {
+        let cell = Rc::new(Cell::new(start));
+        let reader = cell.clone();
+        (cell, move || reader.get())
+    }
+
+    #[test]
+    fn should_scale_ticks_to_nanos() -> Result<()> {
+        let (ticks, c) = counter(0);
+        let mut src = TickCounterSource::new(c, 48_000_000).with_base(WallNST::of_u64(1_000));
+        assert_eq!(src.now()?, WallNST::of_u64(1_000));
+        ticks.set(48_000_000);
+        assert_eq!(src.now()?, WallNST::of_u64(1_000_001_000));
+        Ok(())
+    }
+
+    #[test]
+    fn should_extend_wrapping_counters() -> Result<()> {
+        let (ticks, c) = counter(u64::from(u32::MAX));
+        let mut src = TickCounterSource::with_ratio(c, 1, 1).with_counter_bits(32);
+        let before = src.now()?;
+        ticks.set(4);
+        let after = src.now()?;
+        assert_eq!(after - before, Duration::from_nanos(5));
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use super::{ClockSource, WallNST};
+use crate::{Error, Result};
+
+/// A clock source that returns wall-clock time in nanoseconds, as `WallNS`
+/// does, derived from the CPU's timestamp counter (`RDTSC`).
+///
+/// The counter is calibrated against the system clock at construction, and
+/// re-calibrated whenever `recalibrate_interval` has passed, to correct for
+/// drift. Readings never go backwards. This is only available on x86_64
+/// CPUs with an invariant TSC; elsewhere, construction fails with
+/// `Error::TscUnavailable`.
+///
+/// This is intended for latency-critical code on pinned cores; readings are
+/// only comparable across cores if the counters are synchronised.
+#[derive(Debug, Clone)]
+pub struct TscClock {
+    base: WallNST,
+    anchor: u64,
+    nanos_per_cycle: f64,
+    recalibrate_cycles: u64,
+    last: WallNST,
+}
+
+impl TscClock {
+    /// Calibrates over 10ms, and re-calibrates every second.
+    pub fn new() -> Result<Self> {
+        TscClock::calibrated(Duration::from_millis(10), Duration::from_secs(1))
+    }
+
+    /// Calibrates the counter against the system clock over `window`, and
+    /// re-calibrates whenever `recalibrate_interval` has passed. Fails with
+    /// `Error::TscUnavailable` if the system clock is stepped backwards
+    /// during the window, in which case it is worth trying again.
+    pub fn calibrated(window: Duration, recalibrate_interval: Duration) -> Result<Self> {
+        if !arch::invariant_tsc() {
+            return Err(Error::TscUnavailable);
+        }
+        let (start, start_cycles) = read_pair()?;
+        thread::sleep(window);
+        let (base, anchor) = read_pair()?;
+        let nanos_per_cycle = rate((start, start_cycles), (base, anchor))?;
+        Ok(TscClock {
+            base,
+            anchor,
+            nanos_per_cycle,
+            recalibrate_cycles: (recalibrate_interval.as_nanos() as f64 / nanos_per_cycle) as u64,
+            last: base,
+        })
+    }
+
+    /// Returns the calibrated length of a counter cycle, in nanoseconds.
+    pub fn nanos_per_cycle(&self) -> f64 {
+        self.nanos_per_cycle
+    }
+
+    fn recalibrate(&mut self) -> Result<()> {
+        let (now, cycles) = read_pair()?;
+        if now > self.base {
+            self.nanos_per_cycle = rate((self.base, self.anchor), (now, cycles))?;
+        }
+        self.base = now.max(self.last);
+        self.anchor = cycles;
+        Ok(())
+    }
+}
+
+// Returns the nanoseconds per cycle between two readings of the system
+// clock and the counter. Fails if the system clock went backwards in the
+// meantime (eg: it was stepped by NTP), or the counter did not advance.
+fn rate(from: (WallNST, u64), to: (WallNST, u64)) -> Result<f64> {
+    let elapsed = to.0.checked_sub(from.0).ok_or(Error::TscUnavailable)?;
+    let cycles = to.1.wrapping_sub(from.1);
+    if cycles == 0 {
+        return Err(Error::TscUnavailable);
+    }
+    Ok(elapsed.as_nanos() as f64 / cycles as f64)
+}
+
+fn read_pair() -> Result<(WallNST, u64)> {
+    let now = WallNST::from_timespec(SystemTime::now())?;
+    Ok((now, arch::rdtsc()))
+}
+
+impl ClockSource for TscClock {
+    type Time = WallNST;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        let mut cycles = arch::rdtsc().wrapping_sub(self.anchor);
+        if cycles >= self.recalibrate_cycles {
+            self.recalibrate()?;
+            cycles = arch::rdtsc().wrapping_sub(self.anchor);
+        }
+        let elapsed = Duration::from_nanos((cycles as f64 * self.nanos_per_cycle) as u64);
+        let t = self
+            .base
+            .checked_add_duration(elapsed)
+            .ok_or(Error::SupportedTime(elapsed.as_nanos()))?
+            .max(self.last);
+        self.last = t;
+        Ok(t)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    use std::arch::x86_64::{__cpuid, _rdtsc};
+
+    pub(super) fn rdtsc() -> u64 {
+        // SAFETY: `RDTSC` is available on all x86_64 CPUs.
+        unsafe { _rdtsc() }
+    }
+
+    // Checks the "invariant TSC" flag, CPUID.80000007H:EDX[8]. `__cpuid` is
+    // only marked safe by newer compilers.
+    #[allow(unused_unsafe)]
+    pub(super) fn invariant_tsc() -> bool {
+        // SAFETY: `CPUID` is available on all x86_64 CPUs.
+        let max_extended = unsafe { __cpuid(0x8000_0000) }.eax;
+        max_extended >= 0x8000_0007 && unsafe { __cpuid(0x8000_0007) }.edx & (1 << 8) != 0
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+mod arch {
+    pub(super) fn rdtsc() -> u64 {
+        0
+    }
+
+    pub(super) fn invariant_tsc() -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_refuse_to_calibrate_across_clock_steps() {
+        let at = |nanos| WallNST::of_u64(nanos);
+        assert_eq!(rate((at(1_000), 10), (at(2_000), 510)).ok(), Some(2.0));
+        assert!(matches!(
+            rate((at(2_000), 10), (at(1_000), 510)),
+            Err(Error::TscUnavailable)
+        ));
+        assert!(matches!(
+            rate((at(1_000), 10), (at(2_000), 10)),
+            Err(Error::TscUnavailable)
+        ));
+    }
+
+    #[test]
+    fn should_track_system_time_when_available() -> Result<()> {
+        let mut src = match TscClock::new() {
+            Ok(src) => src,
+            Err(Error::TscUnavailable) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let a = src.now()?;
+        let b = src.now()?;
+        let system = WallNST::from_timespec(SystemTime::now())?;
+        assert!(a <= b, "{} <= {}", a, b);
+        let skew = if b > system { b - system } else { system - b };
+        assert!(skew < Duration::from_millis(100), "{} vs {}", b, system);
+        Ok(())
+    }
+}
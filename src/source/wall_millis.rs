@@ -0,0 +1,42 @@
+use std::time::SystemTime;
+
+use super::{ClockSource, Millis, SharedClockSource, WallTicks};
+use crate::Result;
+
+/// A clock source that returns wall-clock time in whole milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WallMillis;
+/// A time in milliseconds since the unix epoch, as returned by `WallMillis`.
+pub type WallMillisT = WallTicks<Millis>;
+
+impl ClockSource for WallMillis {
+    type Time = WallMillisT;
+    type Delta = std::time::Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for WallMillis {
+    fn now_shared(&self) -> Result<Self::Time> {
+        WallMillisT::from_timespec(SystemTime::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn should_count_milliseconds() {
+        assert_eq!(
+            WallMillisT::of_u64(1_500).duration_since_epoch(),
+            Duration::from_millis(1_500)
+        );
+        assert_eq!(
+            WallMillisT::from_since_epoch(Duration::new(2, 999_999_999)).expect("from"),
+            WallMillisT::of_u64(2 * WallMillisT::TICKS_PER_SEC + WallMillisT::TICKS_PER_SEC - 1)
+        );
+    }
+}
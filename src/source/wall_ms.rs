@@ -1,41 +1,122 @@
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::fmt;
-use std::ops::Sub;
+use std::io;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
 use std::time::{Duration, SystemTime};
 
-#[cfg(feature = "pretty-print")]
-use time::format_description::well_known::Rfc3339;
-
-use super::{ClockSource, NANOS_PER_SEC};
-use crate::{Error, Result, Timestamp};
+use super::{
+    fmt_secs_f64, parse_secs_f64, AsDuration, ClockSource, FromDuration, SharedClockSource,
+    TimeBounds, U64Time, WallNST, NANOS_PER_SEC,
+};
+use crate::{ByteOrdered, Error, Result, Timestamp};
 
 // A clock source that returns wall-clock in 2^(-16)s
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct WallMS;
 /// Representation of our timestamp.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    rkyv(derive(Debug))
+)]
 pub struct WallMST(u64);
 
 impl Timestamp<WallMST> {
-    pub fn to_bytes(&self) -> [u8; 16] {
-        let mut res = [0; 16];
-        res[0..4].copy_from_slice(&self.epoch.to_be_bytes());
-        res[4..12].copy_from_slice(&self.time.0.to_be_bytes());
-        res[12..16].copy_from_slice(&self.count.to_be_bytes());
-        res
+    /// Returns the start of the window of width `bucket` that contains this
+    /// timestamp, with the count reset. The result never follows this
+    /// timestamp, and truncation preserves the order of timestamps.
+    ///
+    /// Panics if `bucket` is zero.
+    pub fn truncate_to(&self, bucket: Duration) -> Self {
+        self.bucket(bucket, false)
     }
 
-    pub fn from_bytes(bytes: [u8; 16]) -> Self {
-        let epoch = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
-        let nanos = u64::from_be_bytes(bytes[4..12].try_into().unwrap());
-        let count = u32::from_be_bytes(bytes[12..16].try_into().unwrap());
+    /// Returns the window boundary nearest to this timestamp, rounding
+    /// halfway cases up, with the count reset. Like `truncate_to`, this
+    /// preserves the order of timestamps.
+    ///
+    /// Panics if `bucket` is zero, or if rounding up overflows.
+    pub fn round_to(&self, bucket: Duration) -> Self {
+        self.bucket(bucket, true)
+    }
+
+    // Buckets in nanoseconds, as widths such as a millisecond are not a
+    // whole number of ticks, so that windows line up with those of
+    // `WallNST`. The boundary is then rounded up to the first tick within
+    // the window, which never follows the original time.
+    fn bucket(&self, bucket: Duration, round: bool) -> Self {
+        let width = bucket.as_nanos();
+        assert!(width > 0, "bucket width must be non-zero");
+        let nanos = self.time.duration_since_epoch().as_nanos();
+        let mut start = nanos - nanos % width;
+        if round && nanos - start >= width - width / 2 {
+            start += width;
+        }
+        let ticks =
+            (start * u128::from(WallMST::TICKS_PER_SEC)).div_ceil(u128::from(NANOS_PER_SEC));
         Timestamp {
-            epoch,
-            time: WallMST(nanos),
-            count,
+            epoch: self.epoch,
+            time: WallMST(u64::try_from(ticks).expect("overflow when rounding time")),
+            count: 0,
         }
     }
+
+    /// Returns the `SystemTime` represented by the physical component of
+    /// this timestamp.
+    pub fn systemtime(&self) -> SystemTime {
+        self.time.as_systemtime()
+    }
+
+    /// Converts to a `WallNST` timestamp, for comparison with nodes using
+    /// that resolution. The time is rounded down to a whole nanosecond, and
+    /// as ticks are longer than nanoseconds, distinct times stay distinct,
+    /// so the order of timestamps is preserved exactly.
+    ///
+    /// Fails with `Error::SupportedTime` for times after 2554, which
+    /// `WallNST` cannot represent.
+    pub fn to_wall_ns(&self) -> Result<Timestamp<WallNST>> {
+        let since_epoch = self.time.duration_since_epoch();
+        let time = WallNST::from_since_epoch(since_epoch)
+            .map_err(|_| Error::SupportedTime(u128::from(self.time.0)))?;
+        Ok(Timestamp {
+            epoch: self.epoch,
+            time,
+            count: self.count,
+        })
+    }
+
+    pub fn write_bytes<W: io::Write>(&self, mut wr: W) -> std::result::Result<(), io::Error> {
+        self.write_ordered_bytes(&mut wr)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 16] {
+        self.to_ordered_bytes()
+    }
+
+    pub fn read_bytes<R: io::Read>(mut r: R) -> std::result::Result<Self, io::Error> {
+        Self::read_ordered_bytes(&mut r)
+    }
+
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self::from_ordered_bytes(&bytes)
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl ArchivedWallMST {
+    /// Returns the archived time, which can be read in place without
+    /// deserializing it.
+    pub fn to_native(&self) -> WallMST {
+        WallMST(self.0.to_native())
+    }
 }
 
 impl WallMST {
@@ -43,11 +124,7 @@ impl WallMST {
     pub const TICKS_PER_SEC: u64 = 1 << 16;
     /// Returns the `Duration` since the unix epoch.
     pub fn duration_since_epoch(self) -> Duration {
-        let secs = self.0 / Self::TICKS_PER_SEC;
-        let minor_ticks = self.0 % Self::TICKS_PER_SEC;
-        let nsecs = minor_ticks * NANOS_PER_SEC / Self::TICKS_PER_SEC;
-        assert!(nsecs < 1_000_000_000, "Internal arithmetic error");
-        Duration::new(secs, nsecs.try_into().expect("internal error"))
+        ticks_to_duration(self.0)
     }
 
     /// Returns a `SystemTime` representing this timestamp.
@@ -66,29 +143,85 @@ impl WallMST {
     pub fn from_since_epoch(since_epoch: Duration) -> Result<Self> {
         let ticks: u128 = u128::from(Self::TICKS_PER_SEC)
             .checked_mul(since_epoch.as_nanos())
-            .ok_or_else(|| Error::SupportedTime(since_epoch.as_nanos()))?
+            .ok_or(Error::SupportedTime(since_epoch.as_nanos()))?
             / u128::from(NANOS_PER_SEC);
         Ok(WallMST(ticks.try_into()?))
     }
 
+    /// Returns the time elapsed from `earlier` to `self`, or `None` if
+    /// `earlier` is after `self`.
+    pub fn checked_sub(self, earlier: Self) -> Option<Duration> {
+        self.0.checked_sub(earlier.0).map(ticks_to_duration)
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or zero if
+    /// `earlier` is after `self`.
+    pub fn saturating_sub(self, earlier: Self) -> Duration {
+        self.checked_sub(earlier).unwrap_or_default()
+    }
+
+    /// Returns the time `d` after `self`, rounded up to a whole tick, or
+    /// `None` if that is not representable. Rounding up ensures that this
+    /// inverts `checked_sub`.
+    pub fn checked_add_duration(self, d: Duration) -> Option<Self> {
+        self.0.checked_add(duration_to_ticks(d)?).map(WallMST)
+    }
+
+    /// Returns the time `d` before `self`, rounded down to a whole tick, or
+    /// `None` if that is not representable.
+    pub fn checked_sub_duration(self, d: Duration) -> Option<Self> {
+        self.0.checked_sub(duration_to_ticks(d)?).map(WallMST)
+    }
+
     /// Returns the number of ticks since the unix epoch.
     pub fn as_u64(self) -> u64 {
         self.0
     }
 
     /// Builds a WallMST from the number of ticks since the unix epoch.
-    pub fn of_u64(val: u64) -> Self {
+    pub const fn of_u64(val: u64) -> Self {
         WallMST(val)
     }
 }
 
+fn ticks_to_duration(ticks: u64) -> Duration {
+    let secs = ticks / WallMST::TICKS_PER_SEC;
+    let minor_ticks = ticks % WallMST::TICKS_PER_SEC;
+    let nsecs = minor_ticks * NANOS_PER_SEC / WallMST::TICKS_PER_SEC;
+    assert!(nsecs < 1_000_000_000, "Internal arithmetic error");
+    Duration::new(secs, nsecs.try_into().expect("internal error"))
+}
+
+// Rounds up, so that adding the result to a time rounds the sum up.
+fn duration_to_ticks(d: Duration) -> Option<u64> {
+    let ticks =
+        (d.as_nanos() * u128::from(WallMST::TICKS_PER_SEC)).div_ceil(u128::from(NANOS_PER_SEC));
+    u64::try_from(ticks).ok()
+}
+
+impl Add<Duration> for WallMST {
+    type Output = WallMST;
+    /// Panics if the result is not representable; see `checked_add_duration`.
+    fn add(self, d: Duration) -> Self::Output {
+        self.checked_add_duration(d)
+            .expect("overflow when adding duration to time")
+    }
+}
+
+impl Sub<Duration> for WallMST {
+    type Output = WallMST;
+    /// Panics if the result is not representable; see `checked_sub_duration`.
+    fn sub(self, d: Duration) -> Self::Output {
+        self.checked_sub_duration(d)
+            .expect("overflow when subtracting duration from time")
+    }
+}
+
 impl Sub for WallMST {
     type Output = Duration;
+    /// Panics if `rhs` is after `self`; see `checked_sub`.
     fn sub(self, rhs: Self) -> Self::Output {
-        let nanos = (self.0 - rhs.0)
-            .checked_mul(NANOS_PER_SEC / Self::TICKS_PER_SEC)
-            .expect("inside time range");
-        Duration::from_nanos(nanos)
+        self.checked_sub(rhs).expect("subtraction underflow")
     }
 }
 
@@ -96,26 +229,54 @@ impl ClockSource for WallMS {
     type Time = WallMST;
     type Delta = Duration;
     fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for WallMS {
+    fn now_shared(&self) -> Result<Self::Time> {
         WallMST::from_timespec(SystemTime::now())
     }
 }
 
+impl AsDuration for WallMST {
+    fn as_duration(&self) -> Duration {
+        self.duration_since_epoch()
+    }
+}
+
+impl FromDuration for WallMST {
+    fn from_duration(since_epoch: Duration) -> Result<Self> {
+        WallMST::from_since_epoch(since_epoch)
+    }
+}
+
+impl TimeBounds for WallMST {
+    const MIN: Self = WallMST(0);
+    const MAX: Self = WallMST(u64::MAX);
+}
+
+impl U64Time for WallMST {
+    fn as_u64(self) -> u64 {
+        self.0
+    }
+    fn of_u64(val: u64) -> Self {
+        WallMST(val)
+    }
+}
+
 impl fmt::Display for WallMST {
-    #[cfg(not(feature = "pretty-print"))]
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(fmt, "{}", self.duration_since_epoch().as_secs_f64())
+        fmt_secs_f64(self.duration_since_epoch(), fmt)
     }
+}
 
-    #[cfg(feature = "pretty-print")]
-    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let ts = self.as_systemtime();
-        let st = time::OffsetDateTime::from(ts);
-        write!(
-            fmt,
-            "{}.{:09}Z",
-            st.format(&Rfc3339).expect("format"),
-            st.nanosecond(),
-        )
+impl FromStr for WallMST {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        WallMST::MIN
+            .checked_add_duration(parse_secs_f64(s)?)
+            .ok_or_else(|| Error::Parse(s.to_owned()))
     }
 }
 
@@ -124,6 +285,8 @@ mod tests {
     use super::WallMST;
     use crate::tests::timestamps;
     use crate::Timestamp;
+    use std::io::Cursor;
+    use std::time::Duration;
     use suppositions::generators::*;
 
     use suppositions::*;
@@ -142,6 +305,16 @@ mod tests {
         });
     }
 
+    #[test]
+    fn should_round_trip_via_stream() {
+        property(timestamps(wallclocks2())).check(|ts| {
+            let mut bs = Vec::new();
+            ts.write_bytes(&mut bs).expect("write_bytes");
+            let ts2 = Timestamp::<WallMST>::read_bytes(Cursor::new(&bs)).expect("read_bytes");
+            ts == ts2
+        });
+    }
+
     #[test]
     fn should_round_trip_via_timespec() {
         // We expect millisecond precision, so ensure we're within ± 0.5ms
@@ -183,4 +356,151 @@ mod tests {
             ta.cmp(&tb) == ba.cmp(&bb)
         })
     }
+
+    #[test]
+    fn streamed_repr_should_order_as_timestamps() {
+        property((timestamps(wallclocks2()), timestamps(wallclocks2()))).check(|(ta, tb)| {
+            let mut ba = Vec::new();
+            let mut bb = Vec::new();
+            ta.write_bytes(&mut ba).expect("write_bytes");
+            tb.write_bytes(&mut bb).expect("write_bytes");
+            ta.cmp(&tb) == ba.cmp(&bb)
+        })
+    }
+
+    #[test]
+    fn checked_sub_should_refuse_underflow() {
+        property((wallclocks2(), wallclocks2())).check(|(ta, tb)| {
+            assert_eq!(ta.checked_sub(tb).is_some(), ta >= tb);
+            assert_eq!(
+                ta.saturating_sub(tb),
+                ta.checked_sub(tb).unwrap_or_default()
+            );
+        })
+    }
+
+    #[test]
+    fn add_duration_should_invert_sub() {
+        property((wallclocks2(), wallclocks2())).check(|(ta, tb)| {
+            let (lo, hi) = if ta <= tb { (ta, tb) } else { (tb, ta) };
+            let d = hi.checked_sub(lo).expect("sub");
+            assert_eq!(lo.checked_add_duration(d), Some(hi), "{:?}", d);
+        })
+    }
+
+    #[test]
+    fn checked_add_duration_should_refuse_overflow() {
+        let max = WallMST(u64::MAX);
+        assert_eq!(max.checked_add_duration(Duration::from_secs(1)), None);
+        assert_eq!(max.checked_add_duration(Duration::ZERO), Some(max));
+    }
+
+    #[test]
+    fn sub_duration_should_invert_add() {
+        property((wallclocks2(), u64s())).check(|(t, nanos)| {
+            let d = Duration::from_nanos(nanos);
+            if let Some(later) = t.checked_add_duration(d) {
+                assert_eq!(later - d, t);
+                assert!(later.duration_since_epoch() >= t.duration_since_epoch() + d);
+            }
+        })
+    }
+
+    #[test]
+    fn hex_should_order_as_timestamps() {
+        property((timestamps(wallclocks2()), timestamps(wallclocks2())))
+            .check(|(ta, tb)| ta.cmp(&tb) == format!("{:x}", ta).cmp(&format!("{:x}", tb)))
+    }
+
+    #[test]
+    fn should_round_trip_via_display() {
+        // RFC3339 dates are limited to four-digit years.
+        let wallclocks = u64s().map(|t| WallMST(t % (253_402_300_799 << 16)));
+        property(timestamps(wallclocks)).check(|ts| {
+            let s = ts.to_string();
+            let parsed = s.parse::<Timestamp<WallMST>>().expect("parse");
+            // Seconds are displayed as an `f64`, so are only as precise.
+            let error = parsed.time.0.abs_diff(ts.time.0);
+            assert!(error <= (ts.time.0 >> 52) + 1, "{} -> {}", s, parsed);
+            assert_eq!((parsed.epoch, parsed.count), (ts.epoch, ts.count));
+        })
+    }
+
+    #[test]
+    fn wall_ns_conversion_should_preserve_order() {
+        let wallclocks = u64s().map(|t| WallMST(t % (1 << 50)));
+        property((timestamps(wallclocks.clone()), timestamps(wallclocks))).check(|(ta, tb)| {
+            let na = ta.to_wall_ns().expect("to_wall_ns");
+            let nb = tb.to_wall_ns().expect("to_wall_ns");
+            ta.cmp(&tb) == na.cmp(&nb) && na.to_wall_ms() == ta
+        })
+    }
+
+    #[test]
+    fn wall_ns_conversion_should_refuse_overflow() {
+        let ts = Timestamp::new(0, WallMST(u64::MAX), 0);
+        assert!(matches!(
+            ts.to_wall_ns(),
+            Err(crate::Error::SupportedTime(_))
+        ));
+    }
+
+    #[test]
+    fn truncation_should_align_to_bucket() {
+        let bucket = Duration::from_secs(1);
+        property(timestamps(wallclocks2())).check(|ts| {
+            let truncated = ts.truncate_to(bucket);
+            assert!(truncated <= ts);
+            assert_eq!(truncated.time.0 % WallMST::TICKS_PER_SEC, 0);
+            assert_eq!(
+                truncated.time.0 / WallMST::TICKS_PER_SEC,
+                ts.time.0 / WallMST::TICKS_PER_SEC
+            );
+        })
+    }
+
+    #[test]
+    fn truncation_should_agree_with_wall_ns() {
+        for bucket in [Duration::from_millis(1), Duration::from_millis(10)] {
+            let width = bucket.as_nanos();
+            property(timestamps(wallclocks2())).check(|ts| {
+                let truncated = ts.truncate_to(bucket);
+                assert!(truncated <= ts);
+                let nanos = |ts: Timestamp<WallMST>| ts.time.duration_since_epoch().as_nanos();
+                assert_eq!(nanos(truncated) / width, nanos(ts) / width);
+                if let Ok(ns) = ts.to_wall_ns() {
+                    assert_eq!(ns.truncate_to(bucket).to_wall_ms(), truncated);
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn should_truncate_to_wall_clock_boundaries() {
+        let ts = |ticks| Timestamp::new(0, WallMST(ticks), 3);
+        let ms = Duration::from_millis(1);
+        assert_eq!(
+            ts(65_536).truncate_to(ms),
+            Timestamp {
+                count: 0,
+                ..ts(65_536)
+            }
+        );
+        // 1.5ms is 98.304 ticks; 1ms starts at 65.536 ticks.
+        assert_eq!(ts(98).truncate_to(ms), Timestamp { count: 0, ..ts(66) });
+        assert_eq!(
+            ts(99).round_to(ms),
+            Timestamp {
+                count: 0,
+                ..ts(132)
+            }
+        );
+        assert_eq!(
+            ts(65_600).truncate_to(Duration::from_millis(10)),
+            Timestamp {
+                count: 0,
+                ..ts(65_536)
+            }
+        );
+    }
 }
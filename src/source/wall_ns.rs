@@ -1,20 +1,31 @@
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::fmt;
 use std::io;
-use std::ops::Sub;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
 use std::time::{Duration, SystemTime};
 
-#[cfg(feature = "pretty-print")]
-use time::format_description::well_known::Rfc3339;
-
-use super::ClockSource;
-use crate::{Result, Timestamp};
+use super::{
+    bucket_ticks, fmt_secs_f64, parse_secs_f64, AsDuration, ClockSource, FromDuration,
+    SharedClockSource, TimeBounds, U64Time, WallMST,
+};
+use crate::{ByteOrdered, Error, Result, Timestamp};
 
 /// A clock source that returns wall-clock in nanoseconds.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct WallNS;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    rkyv(derive(Debug))
+)]
 pub struct WallNST(u64);
 
 impl WallNST {
@@ -42,12 +53,43 @@ impl WallNST {
     }
 
     /// Returns time in nanoseconds since the unix epoch.
-    pub fn of_u64(val: u64) -> Self {
+    pub const fn of_u64(val: u64) -> Self {
         WallNST(val)
     }
 
-    fn of_nanos(nanos: u64) -> Self {
-        WallNST(nanos)
+    /// Returns the time elapsed from `earlier` to `self`, or `None` if
+    /// `earlier` is after `self`.
+    pub fn checked_sub(self, earlier: Self) -> Option<Duration> {
+        self.0.checked_sub(earlier.0).map(Duration::from_nanos)
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or zero if
+    /// `earlier` is after `self`.
+    pub fn saturating_sub(self, earlier: Self) -> Duration {
+        self.checked_sub(earlier).unwrap_or_default()
+    }
+
+    /// Returns the time `d` after `self`, or `None` if that is not
+    /// representable.
+    pub fn checked_add_duration(self, d: Duration) -> Option<Self> {
+        let nanos = u64::try_from(d.as_nanos()).ok()?;
+        self.0.checked_add(nanos).map(WallNST)
+    }
+
+    /// Returns the time `d` before `self`, or `None` if that is not
+    /// representable.
+    pub fn checked_sub_duration(self, d: Duration) -> Option<Self> {
+        let nanos = u64::try_from(d.as_nanos()).ok()?;
+        self.0.checked_sub(nanos).map(WallNST)
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl ArchivedWallNST {
+    /// Returns the archived time, which can be read in place without
+    /// deserializing it.
+    pub fn to_native(&self) -> WallNST {
+        WallNST(self.0.to_native())
     }
 }
 
@@ -59,61 +101,144 @@ impl Sub for WallNST {
     }
 }
 
+impl Add<Duration> for WallNST {
+    type Output = WallNST;
+    /// Panics if the result is not representable; see `checked_add_duration`.
+    fn add(self, d: Duration) -> Self::Output {
+        self.checked_add_duration(d)
+            .expect("overflow when adding duration to time")
+    }
+}
+
+impl Sub<Duration> for WallNST {
+    type Output = WallNST;
+    /// Panics if the result is not representable; see `checked_sub_duration`.
+    fn sub(self, d: Duration) -> Self::Output {
+        self.checked_sub_duration(d)
+            .expect("overflow when subtracting duration from time")
+    }
+}
+
 impl ClockSource for WallNS {
     type Time = WallNST;
     type Delta = Duration;
     fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for WallNS {
+    fn now_shared(&self) -> Result<Self::Time> {
         WallNST::from_timespec(SystemTime::now())
     }
 }
 
+impl AsDuration for WallNST {
+    fn as_duration(&self) -> Duration {
+        self.duration_since_epoch()
+    }
+}
+
+impl FromDuration for WallNST {
+    fn from_duration(since_epoch: Duration) -> Result<Self> {
+        WallNST::from_since_epoch(since_epoch)
+    }
+}
+
+impl TimeBounds for WallNST {
+    const MIN: Self = WallNST(0);
+    const MAX: Self = WallNST(u64::MAX);
+}
+
+impl U64Time for WallNST {
+    fn as_u64(self) -> u64 {
+        self.0
+    }
+    fn of_u64(val: u64) -> Self {
+        WallNST(val)
+    }
+}
+
 impl fmt::Display for WallNST {
-    #[cfg(not(feature = "pretty-print"))]
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(fmt, "{}", self.duration_since_epoch().as_secs_f64())
+        fmt_secs_f64(self.duration_since_epoch(), fmt)
     }
+}
 
-    #[cfg(feature = "pretty-print")]
-    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let st = time::OffsetDateTime::from(self.as_systemtime());
-        write!(
-            fmt,
-            "{}.{:09}Z",
-            st.format(&Rfc3339).expect("format"),
-            st.nanosecond(),
-        )
+impl FromStr for WallNST {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        WallNST::from_since_epoch(parse_secs_f64(s)?)
     }
 }
 
 impl Timestamp<WallNST> {
+    /// Returns the start of the window of width `bucket` that contains this
+    /// timestamp, with the count reset. The result never follows this
+    /// timestamp, and truncation preserves the order of timestamps.
+    ///
+    /// Panics if `bucket` is zero.
+    pub fn truncate_to(&self, bucket: Duration) -> Self {
+        self.bucket(bucket, false)
+    }
+
+    /// Returns the window boundary nearest to this timestamp, rounding
+    /// halfway cases up, with the count reset. Like `truncate_to`, this
+    /// preserves the order of timestamps.
+    ///
+    /// Panics if `bucket` is zero, or if rounding up overflows.
+    pub fn round_to(&self, bucket: Duration) -> Self {
+        self.bucket(bucket, true)
+    }
+
+    fn bucket(&self, bucket: Duration, round: bool) -> Self {
+        let bucket = u64::try_from(bucket.as_nanos()).unwrap_or(u64::MAX);
+        Timestamp {
+            epoch: self.epoch,
+            time: WallNST(bucket_ticks(self.time.0, bucket, round)),
+            count: 0,
+        }
+    }
+
+    /// Returns the `SystemTime` represented by the physical component of
+    /// this timestamp.
+    pub fn systemtime(&self) -> SystemTime {
+        self.time.as_systemtime()
+    }
+
+    /// Converts to a `WallMST` timestamp, for comparison with nodes using
+    /// that resolution. The time is rounded up to a whole tick; if that
+    /// loses precision, the count is reset, as it only orders events at
+    /// the original time. Conversion therefore never reverses the order of
+    /// two timestamps, though distinct timestamps may become equal.
+    ///
+    /// This inverts `Timestamp::<WallMST>::to_wall_ns`, and cannot overflow.
+    pub fn to_wall_ms(&self) -> Timestamp<WallMST> {
+        let time = WallMST::of_u64(0)
+            .checked_add_duration(self.time.duration_since_epoch())
+            .expect("WallNST within WallMST's range");
+        let exact = time.duration_since_epoch() == self.time.duration_since_epoch();
+        Timestamp {
+            epoch: self.epoch,
+            time,
+            count: if exact { self.count } else { 0 },
+        }
+    }
+
     pub fn write_bytes<W: io::Write>(&self, mut wr: W) -> std::result::Result<(), io::Error> {
-        wr.write_all(&self.to_bytes())?;
-        Ok(())
+        self.write_ordered_bytes(&mut wr)
     }
 
     pub fn to_bytes(&self) -> [u8; 16] {
-        let mut res = [0; 16];
-        res[0..4].copy_from_slice(&self.epoch.to_be_bytes());
-        res[4..12].copy_from_slice(&self.time.0.to_be_bytes());
-        res[12..16].copy_from_slice(&self.count.to_be_bytes());
-        res
+        self.to_ordered_bytes()
     }
 
     pub fn read_bytes<R: io::Read>(mut r: R) -> std::result::Result<Self, io::Error> {
-        let mut buf = [0u8; 16];
-        r.read_exact(&mut buf)?;
-        Ok(Self::from_bytes(buf))
+        Self::read_ordered_bytes(&mut r)
     }
 
     pub fn from_bytes(bytes: [u8; 16]) -> Self {
-        let epoch = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
-        let nanos = u64::from_be_bytes(bytes[4..12].try_into().unwrap());
-        let count = u32::from_be_bytes(bytes[12..16].try_into().unwrap());
-        Timestamp {
-            epoch,
-            time: WallNST::of_nanos(nanos),
-            count,
-        }
+        Self::from_ordered_bytes(&bytes)
     }
 }
 /// Serialization for the previous version.
@@ -196,7 +321,7 @@ mod tests {
     use suppositions::*;
 
     fn wallclocks_ns() -> Box<dyn GeneratorObject<Item = WallNST>> {
-        u64s().map(WallNST::of_nanos).boxed()
+        u64s().map(WallNST::of_u64).boxed()
     }
 
     #[test]
@@ -204,7 +329,7 @@ mod tests {
         property(timestamps(wallclocks_ns())).check(|ts| {
             let mut bs = Vec::new();
             ts.write_bytes(&mut bs).expect("write_bytes");
-            let ts2 = Timestamp::read_bytes(Cursor::new(&bs)).expect("read_bytes");
+            let ts2 = Timestamp::<WallNST>::read_bytes(Cursor::new(&bs)).expect("read_bytes");
             // println!("{:?}\t{:?}", ts == ts2, bs);
             ts == ts2
         });
@@ -229,6 +354,143 @@ mod tests {
         })
     }
 
+    #[test]
+    fn should_round_trip_via_display() {
+        property(timestamps(wallclocks_ns())).check(|ts| {
+            let s = ts.to_string();
+            let parsed = s.parse::<Timestamp<WallNST>>().expect("parse");
+            // Seconds are displayed as an `f64`, so are only as precise.
+            let error = parsed.time.0.abs_diff(ts.time.0);
+            assert!(error <= (ts.time.0 >> 52) + 1, "{} -> {}", s, parsed);
+            assert_eq!((parsed.epoch, parsed.count), (ts.epoch, ts.count));
+        })
+    }
+
+    #[cfg(not(feature = "pretty-print"))]
+    #[test]
+    fn should_display_seconds_as_float() {
+        assert_eq!(WallNST(1_500_000_000).to_string(), "1.5");
+        assert_eq!(
+            WallNST(1_558_805_131_923_316_000).to_string(),
+            "1558805131.923316"
+        );
+    }
+
+    #[test]
+    fn alternate_display_should_be_rfc3339() {
+        let ts = Timestamp {
+            epoch: 1,
+            time: WallNST(1_558_805_131_923_316_000),
+            count: 2,
+        };
+        assert_eq!(format!("{:#}", ts), "1:2019-05-25T17:25:31.923316000Z+2");
+        assert_eq!(
+            format!("{:#}", WallNST(0)),
+            "1970-01-01T00:00:00.000000000Z"
+        );
+        assert_eq!(format!("{:x}", ts), "0000000115a1fd0232585d2000000002");
+    }
+
+    #[cfg(feature = "pretty-print")]
+    #[test]
+    fn rfc3339_should_match_time_crate() {
+        use time::format_description::well_known::Rfc3339;
+        property(wallclocks_ns()).check(|t| {
+            let parsed = time::OffsetDateTime::parse(&format!("{:#}", t), &Rfc3339).expect("parse");
+            assert_eq!(parsed, time::OffsetDateTime::from(t.as_systemtime()));
+        })
+    }
+
+    #[test]
+    fn bucketing_should_preserve_order() {
+        let bucket = Duration::from_millis(10);
+        let times = || u64s().map(|t| WallNST(t >> 1));
+        property((timestamps(times()), timestamps(times()))).check(|(ta, tb)| {
+            for (a, b) in [(ta, tb), (tb, ta)].iter() {
+                if a <= b {
+                    assert!(a.truncate_to(bucket) <= b.truncate_to(bucket));
+                    assert!(a.round_to(bucket) <= b.round_to(bucket));
+                }
+            }
+            assert!(ta.truncate_to(bucket) <= ta);
+            assert_eq!(ta.truncate_to(bucket).time.0 % 10_000_000, 0);
+            assert_eq!(ta.round_to(bucket).count, 0);
+        })
+    }
+
+    #[test]
+    fn should_round_to_nearest_bucket() {
+        let ts = |nanos| Timestamp {
+            epoch: 0,
+            time: WallNST(nanos),
+            count: 3,
+        };
+        let bucket = Duration::from_nanos(10);
+        assert_eq!(ts(14).round_to(bucket), Timestamp { count: 0, ..ts(10) });
+        assert_eq!(ts(15).round_to(bucket), Timestamp { count: 0, ..ts(20) });
+        assert_eq!(ts(19).truncate_to(bucket), Timestamp { count: 0, ..ts(10) });
+    }
+
+    #[test]
+    fn should_parse_fractional_seconds() {
+        assert_eq!(
+            "1.5".parse::<WallNST>().expect("parse"),
+            WallNST(1_500_000_000)
+        );
+        assert_eq!(
+            "2".parse::<WallNST>().expect("parse"),
+            WallNST(2_000_000_000)
+        );
+        assert_eq!(
+            "1.0000000001".parse::<WallNST>().expect("parse"),
+            WallNST(1_000_000_000)
+        );
+        assert!("-1".parse::<WallNST>().is_err());
+        assert!("inf".parse::<WallNST>().is_err());
+    }
+
+    #[test]
+    fn checked_arithmetic_should_not_wrap() {
+        property((wallclocks_ns(), wallclocks_ns())).check(|(ta, tb)| match ta.checked_sub(tb) {
+            Some(d) => assert_eq!(tb.checked_add_duration(d), Some(ta)),
+            None => {
+                assert!(ta < tb);
+                assert_eq!(ta.saturating_sub(tb), Duration::ZERO);
+            }
+        })
+    }
+
+    #[test]
+    fn wall_ms_conversion_should_not_reverse_order() {
+        property((timestamps(wallclocks_ns()), timestamps(wallclocks_ns()))).check(|(ta, tb)| {
+            let (lo, hi) = if ta <= tb { (ta, tb) } else { (tb, ta) };
+            let (mlo, mhi) = (lo.to_wall_ms(), hi.to_wall_ms());
+            assert!(mlo <= mhi, "{:?} <= {:?}", mlo, mhi);
+            assert!(mlo.time.duration_since_epoch() >= lo.time.duration_since_epoch());
+        })
+    }
+
+    #[test]
+    fn wall_ms_conversion_should_reset_count_when_inexact() {
+        let exact = Timestamp::new(0, WallNST::of_u64(500_000_000), 3);
+        assert_eq!(
+            exact.to_wall_ms(),
+            Timestamp::new(0, WallMST::of_u64(1 << 15), 3)
+        );
+        let inexact = Timestamp::new(0, WallNST::of_u64(500_000_001), 3);
+        assert_eq!(
+            inexact.to_wall_ms(),
+            Timestamp::new(0, WallMST::of_u64((1 << 15) + 1), 0)
+        );
+    }
+
+    #[test]
+    fn checked_add_duration_should_refuse_overflow() {
+        let max = WallNST::of_u64(u64::MAX);
+        assert_eq!(max.checked_add_duration(Duration::from_nanos(1)), None);
+        assert_eq!(WallNST::of_u64(0).checked_add_duration(Duration::MAX), None);
+    }
+
     #[cfg(feature = "serialization")]
     mod serde {
         use super::*;
@@ -270,4 +532,35 @@ mod tests {
             )
         }
     }
+
+    #[cfg(feature = "rkyv")]
+    #[test]
+    fn should_read_archived_timestamps_in_place() {
+        use crate::ArchivedTimestamp;
+        use rkyv::rancor;
+
+        let ts = Timestamp::new(3, WallNST::of_u64(1_558_805_131_923_316_000), 7);
+        let bytes = rkyv::to_bytes::<rancor::Error>(&ts).expect("serialize");
+        let archived =
+            rkyv::access::<ArchivedTimestamp<WallNST>, rancor::Error>(&bytes).expect("access");
+        assert_eq!(archived.epoch, 3);
+        assert_eq!(archived.time.to_native(), ts.time);
+        assert_eq!(archived.count, 7);
+        let back =
+            rkyv::deserialize::<Timestamp<WallNST>, rancor::Error>(archived).expect("deserialize");
+        assert_eq!(back, ts);
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn borsh_layout_should_be_stable() {
+        let ts = Timestamp::new(1, WallNST::of_u64(0x0203_0405_0607_0809), 10);
+        let bytes = borsh::to_vec(&ts).expect("serialize");
+        // Little-endian epoch, nanoseconds and count.
+        assert_eq!(bytes, [1, 0, 0, 0, 9, 8, 7, 6, 5, 4, 3, 2, 10, 0, 0, 0]);
+        assert_eq!(
+            borsh::from_slice::<Timestamp<WallNST>>(&bytes).expect("deserialize"),
+            ts
+        );
+    }
 }
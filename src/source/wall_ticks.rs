@@ -0,0 +1,325 @@
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use super::func::ticks_to_duration;
+use super::{
+    bucket_ticks, fmt_since_epoch, parse_since_epoch, AsDuration, FromDuration, TickUnit,
+    TimeBounds, U64Time, NANOS_PER_SEC,
+};
+use crate::{ByteOrdered, Error, Result, Timestamp};
+
+/// A time in whole ticks of the unit `U` since the unix epoch. See `WallUST`
+/// and `WallMillisT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialization", serde(transparent, bound = ""))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schemars", schemars(bound = ""))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct WallTicks<U> {
+    ticks: u64,
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    #[cfg_attr(feature = "borsh", borsh(skip))]
+    unit: PhantomData<U>,
+}
+
+impl<U> WallTicks<U> {
+    /// Returns time in ticks since the unix epoch.
+    pub const fn as_u64(self) -> u64 {
+        self.ticks
+    }
+
+    /// Returns time in ticks since the unix epoch.
+    pub const fn of_u64(val: u64) -> Self {
+        WallTicks {
+            ticks: val,
+            unit: PhantomData,
+        }
+    }
+}
+
+impl<U: TickUnit> WallTicks<U> {
+    /// The number of ticks per second.
+    pub const TICKS_PER_SEC: u64 = NANOS_PER_SEC / U::NANOS_PER_TICK;
+
+    /// Returns the `Duration` since the unix epoch.
+    pub fn duration_since_epoch(self) -> Duration {
+        ticks_to_duration::<U>(self.ticks)
+    }
+
+    /// Returns a `SystemTime` representing this timestamp.
+    pub fn as_systemtime(self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + self.duration_since_epoch()
+    }
+
+    /// Returns a time representing the `SystemTime`, truncated to a whole
+    /// tick.
+    pub fn from_timespec(t: SystemTime) -> Result<Self> {
+        let since_epoch = t.duration_since(SystemTime::UNIX_EPOCH)?;
+        Self::from_since_epoch(since_epoch)
+    }
+
+    /// Returns a time from a `Duration` since the unix epoch, truncated to a
+    /// whole tick. Fails if the time isn't representable in 64 bits.
+    pub fn from_since_epoch(since_epoch: Duration) -> Result<Self> {
+        let ticks = since_epoch.as_nanos() / u128::from(U::NANOS_PER_TICK);
+        Ok(Self::of_u64(ticks.try_into()?))
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or `None` if
+    /// `earlier` is after `self`.
+    pub fn checked_sub(self, earlier: Self) -> Option<Duration> {
+        let ticks = self.ticks.checked_sub(earlier.ticks)?;
+        Some(ticks_to_duration::<U>(ticks))
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or zero if
+    /// `earlier` is after `self`.
+    pub fn saturating_sub(self, earlier: Self) -> Duration {
+        self.checked_sub(earlier).unwrap_or_default()
+    }
+
+    /// Returns the time `d` after `self`, rounded up to a whole tick, or
+    /// `None` if that is not representable.
+    pub fn checked_add_duration(self, d: Duration) -> Option<Self> {
+        let ticks = self.ticks.checked_add(duration_to_ticks::<U>(d)?)?;
+        Some(Self::of_u64(ticks))
+    }
+
+    /// Returns the time `d` before `self`, rounded down to a whole tick, or
+    /// `None` if that is not representable.
+    pub fn checked_sub_duration(self, d: Duration) -> Option<Self> {
+        let ticks = self.ticks.checked_sub(duration_to_ticks::<U>(d)?)?;
+        Some(Self::of_u64(ticks))
+    }
+}
+
+// Rounds up, so that adding the result to a time rounds the sum up.
+fn duration_to_ticks<U: TickUnit>(d: Duration) -> Option<u64> {
+    let per_tick = u128::from(U::NANOS_PER_TICK);
+    u64::try_from(d.as_nanos().div_ceil(per_tick)).ok()
+}
+
+impl<U: TickUnit> Sub for WallTicks<U> {
+    type Output = Duration;
+    /// Panics if `rhs` is after `self`; see `checked_sub`.
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs).expect("subtraction underflow")
+    }
+}
+
+impl<U: TickUnit> Add<Duration> for WallTicks<U> {
+    type Output = WallTicks<U>;
+    /// Panics if the result is not representable; see `checked_add_duration`.
+    fn add(self, d: Duration) -> Self::Output {
+        self.checked_add_duration(d)
+            .expect("overflow when adding duration to time")
+    }
+}
+
+impl<U: TickUnit> Sub<Duration> for WallTicks<U> {
+    type Output = WallTicks<U>;
+    /// Panics if the result is not representable; see `checked_sub_duration`.
+    fn sub(self, d: Duration) -> Self::Output {
+        self.checked_sub_duration(d)
+            .expect("overflow when subtracting duration from time")
+    }
+}
+
+impl<U: TickUnit> AsDuration for WallTicks<U> {
+    fn as_duration(&self) -> Duration {
+        ticks_to_duration::<U>(self.ticks)
+    }
+}
+
+impl<U: TickUnit> FromDuration for WallTicks<U> {
+    fn from_duration(since_epoch: Duration) -> Result<Self> {
+        Self::from_since_epoch(since_epoch)
+    }
+}
+
+impl<U> TimeBounds for WallTicks<U> {
+    const MIN: Self = WallTicks::of_u64(0);
+    const MAX: Self = WallTicks::of_u64(u64::MAX);
+}
+
+impl<U: Copy> U64Time for WallTicks<U> {
+    fn as_u64(self) -> u64 {
+        self.ticks
+    }
+    fn of_u64(val: u64) -> Self {
+        WallTicks::of_u64(val)
+    }
+}
+
+impl<U: TickUnit> fmt::Display for WallTicks<U> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_since_epoch(ticks_to_duration::<U>(self.ticks), fmt)
+    }
+}
+
+impl<U: TickUnit> FromStr for WallTicks<U> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_since_epoch(parse_since_epoch(s)?)
+    }
+}
+
+impl<U: TickUnit + Ord + Copy> Timestamp<WallTicks<U>> {
+    /// Returns the start of the window of width `bucket` that contains this
+    /// timestamp, with the count reset. The result never follows this
+    /// timestamp, and truncation preserves the order of timestamps.
+    ///
+    /// Panics if `bucket` is zero.
+    pub fn truncate_to(&self, bucket: Duration) -> Self {
+        self.bucket(bucket, false)
+    }
+
+    /// Returns the window boundary nearest to this timestamp, rounding
+    /// halfway cases up, with the count reset. Like `truncate_to`, this
+    /// preserves the order of timestamps.
+    ///
+    /// Panics if `bucket` is zero, or if rounding up overflows.
+    pub fn round_to(&self, bucket: Duration) -> Self {
+        self.bucket(bucket, true)
+    }
+
+    fn bucket(&self, bucket: Duration, round: bool) -> Self {
+        let bucket = duration_to_ticks::<U>(bucket).unwrap_or(u64::MAX);
+        Timestamp {
+            epoch: self.epoch,
+            time: WallTicks::of_u64(bucket_ticks(self.time.ticks, bucket, round)),
+            count: 0,
+        }
+    }
+
+    /// Returns the `SystemTime` represented by the physical component of
+    /// this timestamp.
+    pub fn systemtime(&self) -> SystemTime {
+        self.time.as_systemtime()
+    }
+
+    pub fn write_bytes<W: io::Write>(&self, mut wr: W) -> std::result::Result<(), io::Error> {
+        self.write_ordered_bytes(&mut wr)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 16] {
+        self.to_ordered_bytes()
+    }
+
+    pub fn read_bytes<R: io::Read>(mut r: R) -> std::result::Result<Self, io::Error> {
+        Self::read_ordered_bytes(&mut r)
+    }
+
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self::from_ordered_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::{Micros, Millis};
+    use crate::tests::timestamps;
+    use std::io::Cursor;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    fn wallclocks<U: TickUnit + 'static>() -> Box<dyn GeneratorObject<Item = WallTicks<U>>> {
+        u64s().map(WallTicks::of_u64).boxed()
+    }
+
+    fn should_round_trip_via_key<U: TickUnit + Ord + Copy + fmt::Debug + 'static>() {
+        property(timestamps(wallclocks::<U>())).check(|ts| {
+            let mut bs = Vec::new();
+            ts.write_bytes(&mut bs).expect("write_bytes");
+            Timestamp::<WallTicks<U>>::read_bytes(Cursor::new(&bs)).expect("read_bytes") == ts
+        });
+    }
+
+    #[test]
+    fn should_round_trip_micros_via_key() {
+        should_round_trip_via_key::<Micros>()
+    }
+
+    #[test]
+    fn should_round_trip_millis_via_key() {
+        should_round_trip_via_key::<Millis>()
+    }
+
+    fn byte_repr_should_order_as_timestamps<U: TickUnit + Ord + Copy + fmt::Debug + 'static>() {
+        property((timestamps(wallclocks::<U>()), timestamps(wallclocks::<U>())))
+            .check(|(ta, tb)| ta.cmp(&tb) == ta.to_bytes().cmp(&tb.to_bytes()))
+    }
+
+    #[test]
+    fn micros_byte_repr_should_order_as_timestamps() {
+        byte_repr_should_order_as_timestamps::<Micros>()
+    }
+
+    #[test]
+    fn millis_byte_repr_should_order_as_timestamps() {
+        byte_repr_should_order_as_timestamps::<Millis>()
+    }
+
+    fn should_round_trip_via_display<U: TickUnit + Ord + Copy + fmt::Debug + 'static>() {
+        // RFC3339 dates are limited to four-digit years.
+        let max = 253_402_300_799 * WallTicks::<U>::TICKS_PER_SEC;
+        let times = u64s().map(move |t| WallTicks::<U>::of_u64(t % max));
+        property(timestamps(times)).check(|ts| {
+            let s = ts.to_string();
+            assert_eq!(
+                s.parse::<Timestamp<WallTicks<U>>>().expect("parse"),
+                ts,
+                "{}",
+                s
+            );
+        })
+    }
+
+    #[test]
+    fn should_round_trip_micros_via_display() {
+        should_round_trip_via_display::<Micros>()
+    }
+
+    #[test]
+    fn should_round_trip_millis_via_display() {
+        should_round_trip_via_display::<Millis>()
+    }
+
+    fn add_duration_should_invert_sub<U: TickUnit + Ord + Copy + fmt::Debug + 'static>() {
+        property((wallclocks::<U>(), wallclocks::<U>())).check(|(ta, tb)| {
+            let (lo, hi) = if ta <= tb { (ta, tb) } else { (tb, ta) };
+            let d = hi - lo;
+            assert_eq!(lo.checked_add_duration(d), Some(hi), "{:?}", d);
+        })
+    }
+
+    #[test]
+    fn micros_add_duration_should_invert_sub() {
+        add_duration_should_invert_sub::<Micros>()
+    }
+
+    #[test]
+    fn millis_add_duration_should_invert_sub() {
+        add_duration_should_invert_sub::<Millis>()
+    }
+
+    #[cfg(feature = "serialization")]
+    #[test]
+    fn should_round_trip_via_serde() {
+        property(timestamps(wallclocks::<Millis>())).check(|ts| {
+            let s = serde_json::to_string(&ts).expect("to-json");
+            let ts2: Timestamp<WallTicks<Millis>> = serde_json::from_str(&s).expect("from-json");
+            assert_eq!(ts, ts2, "{}", s);
+        });
+    }
+}
@@ -0,0 +1,42 @@
+use std::time::SystemTime;
+
+use super::{ClockSource, Micros, SharedClockSource, WallTicks};
+use crate::Result;
+
+/// A clock source that returns wall-clock time in whole microseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WallUS;
+/// A time in microseconds since the unix epoch, as returned by `WallUS`.
+pub type WallUST = WallTicks<Micros>;
+
+impl ClockSource for WallUS {
+    type Time = WallUST;
+    type Delta = std::time::Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for WallUS {
+    fn now_shared(&self) -> Result<Self::Time> {
+        WallUST::from_timespec(SystemTime::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn should_count_microseconds() {
+        assert_eq!(
+            WallUST::of_u64(1_500).duration_since_epoch(),
+            Duration::from_micros(1_500)
+        );
+        assert_eq!(
+            WallUST::from_since_epoch(Duration::new(2, 999_999_999)).expect("from"),
+            WallUST::of_u64(2 * WallUST::TICKS_PER_SEC + WallUST::TICKS_PER_SEC - 1)
+        );
+    }
+}
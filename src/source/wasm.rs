@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use web_sys::wasm_bindgen::{JsCast, JsValue};
+use web_sys::Performance;
+
+use super::{ClockSource, SharedClockSource, WallNST};
+use crate::{Error, Result};
+
+/// A clock source for browsers and other JavaScript hosts, where
+/// `SystemTime::now()` is unavailable on `wasm32-unknown-unknown`. Readings
+/// are taken from `Date.now()`, so have millisecond resolution, but are
+/// given as `WallNST` so that timestamps can be exchanged with servers using
+/// `WallNS`.
+///
+/// Only available on `wasm32`, with the `wasm` feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WallJS;
+
+impl ClockSource for WallJS {
+    type Time = WallNST;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for WallJS {
+    fn now_shared(&self) -> Result<Self::Time> {
+        // `Date.now()` returns whole milliseconds; float to integer casts
+        // saturate, so a clock set before 1970 reads as the epoch.
+        let millis = js_sys::Date::now() as u64;
+        WallNST::from_since_epoch(Duration::from_millis(millis))
+    }
+}
+
+/// A clock source for browsers and other JavaScript hosts that reads
+/// `performance.timeOrigin + performance.now()`. Unlike `WallJS`, this is
+/// monotonic within a page (or worker), so does not jump backwards if the
+/// user changes their operating system's clock mid-session, and has
+/// sub-millisecond resolution where the host allows it.
+///
+/// Over a long session, readings may drift from `Date.now()`.
+///
+/// Only available on `wasm32`, with the `wasm` feature.
+#[derive(Debug, Clone)]
+pub struct WallPerf {
+    performance: Performance,
+    origin: f64,
+}
+
+impl WallPerf {
+    /// Creates a source using the global `performance` object. Fails with
+    /// `Error::PerformanceUnavailable` if the host does not provide one.
+    pub fn new() -> Result<Self> {
+        let performance =
+            js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("performance"))
+                .ok()
+                .and_then(|p| p.dyn_into::<Performance>().ok())
+                .ok_or(Error::PerformanceUnavailable)?;
+        let origin = performance.time_origin();
+        Ok(WallPerf {
+            performance,
+            origin,
+        })
+    }
+}
+
+impl ClockSource for WallPerf {
+    type Time = WallNST;
+    type Delta = Duration;
+    fn now(&mut self) -> Result<Self::Time> {
+        self.now_shared()
+    }
+}
+
+impl SharedClockSource for WallPerf {
+    fn now_shared(&self) -> Result<Self::Time> {
+        let millis = self.origin + self.performance.now();
+        // Float to integer casts saturate.
+        Ok(WallNST::of_u64((millis * 1_000_000.0) as u64))
+    }
+}
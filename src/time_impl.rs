@@ -0,0 +1,125 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+use crate::{Error, Result, Timestamp, WallMST, WallNST};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+// Returns the time since the unix epoch, failing for earlier times.
+fn since_epoch(dt: OffsetDateTime) -> Result<Duration> {
+    let nanos = u128::try_from(dt.unix_timestamp_nanos())?;
+    Ok(Duration::new(
+        u64::try_from(nanos / NANOS_PER_SEC)?,
+        (nanos % NANOS_PER_SEC) as u32,
+    ))
+}
+
+fn to_offset_datetime(since_epoch: Duration) -> Result<OffsetDateTime> {
+    let nanos = since_epoch.as_nanos();
+    OffsetDateTime::from_unix_timestamp_nanos(i128::try_from(nanos)?)
+        .map_err(|_| Error::SupportedTime(nanos))
+}
+
+/// Fails if `dt` is before the unix epoch, or after 2554.
+impl TryFrom<OffsetDateTime> for WallNST {
+    type Error = Error;
+    fn try_from(dt: OffsetDateTime) -> Result<Self> {
+        WallNST::from_since_epoch(since_epoch(dt)?)
+    }
+}
+
+impl From<WallNST> for OffsetDateTime {
+    fn from(t: WallNST) -> Self {
+        to_offset_datetime(t.duration_since_epoch()).expect("WallNST within time's range")
+    }
+}
+
+/// Discards the epoch and count.
+impl From<Timestamp<WallNST>> for OffsetDateTime {
+    fn from(ts: Timestamp<WallNST>) -> Self {
+        ts.time.into()
+    }
+}
+
+/// Rounds up to a whole tick, so that converting a `WallMST` to an
+/// `OffsetDateTime` and back is lossless. Fails if `dt` is before the unix
+/// epoch.
+impl TryFrom<OffsetDateTime> for WallMST {
+    type Error = Error;
+    fn try_from(dt: OffsetDateTime) -> Result<Self> {
+        let since_epoch = since_epoch(dt)?;
+        WallMST::of_u64(0)
+            .checked_add_duration(since_epoch)
+            .ok_or(Error::SupportedTime(since_epoch.as_nanos()))
+    }
+}
+
+/// Fails with `Error::SupportedTime` for times beyond the range of the time
+/// crate.
+impl TryFrom<WallMST> for OffsetDateTime {
+    type Error = Error;
+    fn try_from(t: WallMST) -> Result<Self> {
+        to_offset_datetime(t.duration_since_epoch())
+    }
+}
+
+/// Discards the epoch and count.
+impl TryFrom<Timestamp<WallMST>> for OffsetDateTime {
+    type Error = Error;
+    fn try_from(ts: Timestamp<WallMST>) -> Result<Self> {
+        OffsetDateTime::try_from(ts.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    #[test]
+    fn wall_ns_should_round_trip_via_offset_datetime() {
+        property(u64s()).check(|nanos| {
+            let t = WallNST::of_u64(nanos);
+            WallNST::try_from(OffsetDateTime::from(t)).expect("from-datetime") == t
+        });
+    }
+
+    #[test]
+    fn wall_ms_should_round_trip_via_offset_datetime() {
+        property(u64s()).check(|ticks| {
+            let t = WallMST::of_u64(ticks >> 16);
+            let dt = OffsetDateTime::try_from(t).expect("to-datetime");
+            WallMST::try_from(dt).expect("from-datetime") == t
+        });
+    }
+
+    #[test]
+    fn should_drop_count_when_converting_timestamps() -> Result<()> {
+        let t = WallNST::of_u64(1_719_847_926_000_000_001);
+        let dt = OffsetDateTime::from(Timestamp::new(2, t, 3));
+        assert_eq!(dt.unix_timestamp_nanos(), 1_719_847_926_000_000_001);
+        let t = WallMST::of_u64((1_719_847_926 << 16) + (1 << 15));
+        let dt = OffsetDateTime::try_from(Timestamp::new(2, t, 3))?;
+        assert_eq!(dt.unix_timestamp_nanos(), 1_719_847_926_500_000_000);
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_times_out_of_range() {
+        let before_epoch = OffsetDateTime::from_unix_timestamp(-1).expect("datetime");
+        assert!(matches!(
+            WallNST::try_from(before_epoch),
+            Err(Error::FromInt(_))
+        ));
+        assert!(WallMST::try_from(before_epoch).is_err());
+        let far_future = OffsetDateTime::from_unix_timestamp(1 << 36).expect("datetime");
+        assert!(WallNST::try_from(far_future).is_err());
+        assert!(matches!(
+            OffsetDateTime::try_from(WallMST::of_u64(u64::MAX)),
+            Err(Error::SupportedTime(_))
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::{Duration, Instant};
+
+use crate::{AsDuration, Clock, ClockSource, OffsetLimiter, Result, Timestamp};
+
+impl<S: ClockSource> Clock<S>
+where
+    S::Time: AsDuration,
+    S::Delta: AsDuration,
+{
+    /// As `wait_until`, but sleeps on the tokio timer rather than blocking
+    /// the thread, so may be used for commit-wait or lease expiry from
+    /// within async tasks.
+    pub async fn wait_until_async(
+        &mut self,
+        ts: &Timestamp<S::Time>,
+        deadline: Instant,
+    ) -> Result<Duration> {
+        let error = self.src.uncertainty().map(|e| e.as_duration());
+        self.wait_until_with_async(ts, error.unwrap_or_default(), deadline)
+            .await
+    }
+
+    /// As `wait_until_with`, but sleeps on the tokio timer.
+    pub async fn wait_until_with_async(
+        &mut self,
+        ts: &Timestamp<S::Time>,
+        error: Duration,
+        deadline: Instant,
+    ) -> Result<Duration> {
+        let start = Instant::now();
+        while let Some(remaining) = self.commit_wait_remaining(ts, error, deadline)? {
+            tokio::time::sleep(remaining).await;
+        }
+        Ok(start.elapsed())
+    }
+}
+
+impl<S: ClockSource> OffsetLimiter<S>
+where
+    S::Time: AsDuration,
+    S::Delta: AsDuration + Copy,
+{
+    /// As `wait_until`, but sleeps on the tokio timer rather than blocking
+    /// the thread.
+    pub async fn wait_until_async(
+        &mut self,
+        ts: &Timestamp<S::Time>,
+        deadline: Instant,
+    ) -> Result<Duration> {
+        let error = self.error_bound().as_duration();
+        self.clock.wait_until_with_async(ts, error, deadline).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, ManualClock, ManualT, MockClock, U64Time, WallNS};
+
+    fn soon() -> Instant {
+        Instant::now() + Duration::from_secs(10)
+    }
+
+    #[tokio::test]
+    async fn should_wait_until_after_timestamp_and_error() -> Result<()> {
+        let mut clock = Clock::new(MockClock::stepping(0, 1))?.with_max_diff(5);
+        let ts = clock.now()?;
+        clock.wait_until_async(&ts, soon()).await?;
+        assert!(clock.inner().src.time() > ts.time.as_u64() + 6);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_sleep_for_wall_clocks() -> Result<()> {
+        let mut clock = Clock::new(WallNS)?;
+        let ts = clock.now()?;
+        let waited = clock
+            .wait_until_with_async(&ts, Duration::from_millis(20), soon())
+            .await?;
+        assert!(waited >= Duration::from_millis(15), "{:?}", waited);
+        assert!(clock.now()?.time - ts.time > Duration::from_millis(20));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_give_up_on_stalled_sources_at_deadline() -> Result<()> {
+        let mut clock = Clock::new(ManualClock::new(10))?;
+        let deadline = Instant::now() + Duration::from_millis(20);
+        let res = clock
+            .wait_until_async(&Timestamp::new(0, ManualT::from(20), 0), deadline)
+            .await;
+        assert!(
+            matches!(res, Err(Error::DeadlineExceeded { .. })),
+            "{:?}",
+            res
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::convert::TryFrom;
+
+use crate::packed::check_width;
+use crate::{Error, Result, Timestamp, WallMST};
+
+// The number of low bits of an `NTP64` that uhlc uses for its counter.
+const COUNT_BITS: u32 = uhlc::CSIZE as u32;
+// How far a `WallMST` time, with 16 bits of fractional seconds, is shifted
+// to line up with the 32 fractional bits of an `NTP64`.
+const TIME_SHIFT: u32 = 16;
+
+/// A uhlc `NTP64` time is 32.32 fixed point seconds since the unix epoch,
+/// with uhlc's own 4 bit logical counter in the lowest bits of the fraction.
+/// The `WallMST` time fills the bits above the fraction's low 16, and the
+/// count the lowest 4, so conversions in this direction preserve order.
+impl TryFrom<Timestamp<WallMST>> for uhlc::NTP64 {
+    type Error = Error;
+    /// Fails with `Error::PackOverflow` if the epoch is not zero, the time
+    /// does not fit in 48 bits, or the count is more than 15.
+    fn try_from(ts: Timestamp<WallMST>) -> Result<Self> {
+        check_width("epoch", u64::from(ts.epoch), 0)?;
+        let time = check_width("time", ts.time.as_u64(), 64 - TIME_SHIFT)?;
+        let count = check_width("count", u64::from(ts.count), COUNT_BITS)?;
+        Ok(uhlc::NTP64(time << TIME_SHIFT | count))
+    }
+}
+
+/// Takes the count from uhlc's counter, and truncates the time to a whole
+/// `WallMST` tick, discarding the fraction bits in between. So times within
+/// the same tick may not keep their order relative to each other.
+impl From<uhlc::NTP64> for Timestamp<WallMST> {
+    fn from(time: uhlc::NTP64) -> Self {
+        let time = time.as_u64();
+        Timestamp {
+            epoch: 0,
+            time: WallMST::of_u64(time >> TIME_SHIFT),
+            count: (time & ((1 << COUNT_BITS) - 1)) as u32,
+        }
+    }
+}
+
+impl Timestamp<WallMST> {
+    /// Returns the equivalent uhlc timestamp, issued by the node `id`.
+    pub fn to_uhlc(&self, id: uhlc::ID) -> Result<uhlc::Timestamp> {
+        Ok(uhlc::Timestamp::new(uhlc::NTP64::try_from(*self)?, id))
+    }
+
+    /// Returns the time of a uhlc timestamp, in epoch zero. The node id is
+    /// discarded, so timestamps from different nodes at the same time will
+    /// compare equal.
+    pub fn from_uhlc(ts: &uhlc::Timestamp) -> Self {
+        Timestamp::from(*ts.get_time())
+    }
+
+    /// Parses uhlc's `<time>/<id>` text form, as written by its `Display`
+    /// implementation, returning the time and the issuing node's id.
+    pub fn parse_uhlc(s: &str) -> Result<(Self, uhlc::ID)> {
+        let ts = s
+            .parse::<uhlc::Timestamp>()
+            .map_err(|_| Error::Parse(s.to_owned()))?;
+        Ok((Timestamp::from_uhlc(&ts), *ts.get_id()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    // Times that fall on a whole `WallMST` tick, as ours do.
+    fn ntp64s() -> Box<dyn GeneratorObject<Item = uhlc::NTP64>> {
+        let between = ((1 << TIME_SHIFT) - 1) ^ ((1 << COUNT_BITS) - 1);
+        u64s().map(move |t| uhlc::NTP64(t & !between)).boxed()
+    }
+
+    #[test]
+    fn should_round_trip_via_ntp64() {
+        property(ntp64s()).check(|time| {
+            let ts = Timestamp::<WallMST>::from(time);
+            uhlc::NTP64::try_from(ts).expect("to-ntp64") == time
+        });
+    }
+
+    #[test]
+    fn should_preserve_order() {
+        property((ntp64s(), ntp64s())).check(|(a, b)| {
+            let (ta, tb) = (Timestamp::<WallMST>::from(a), Timestamp::from(b));
+            a.cmp(&b) == ta.cmp(&tb)
+        });
+    }
+
+    #[test]
+    fn should_match_uhlc_times() {
+        let hlc = uhlc::HLC::default();
+        let ts = hlc.new_timestamp();
+        let ours = Timestamp::from_uhlc(&ts);
+        let secs = ts.get_time().to_duration().as_secs();
+        assert_eq!(ours.time.duration_since_epoch().as_secs(), secs);
+        assert_eq!(ours.count, 0);
+    }
+
+    #[test]
+    fn should_share_counter_with_uhlc() -> Result<()> {
+        const SECS: u64 = 1_719_848_326;
+        // A clock a second behind the timestamp, so that uhlc must advance
+        // its counter rather than its physical time.
+        let hlc = uhlc::HLCBuilder::new()
+            .with_clock(|| uhlc::NTP64((SECS - 1) << 32))
+            .with_max_delta(std::time::Duration::from_secs(2))
+            .build();
+        let ts = Timestamp::new(0, WallMST::of_u64(SECS << 16), 3);
+        hlc.update_with_timestamp(&ts.to_uhlc(*hlc.get_id())?)
+            .expect("update");
+        let next = Timestamp::from_uhlc(&hlc.new_timestamp());
+        assert_eq!(next, Timestamp { count: 5, ..ts });
+        Ok(())
+    }
+
+    #[test]
+    fn should_round_trip_via_uhlc_string() -> Result<()> {
+        let id = uhlc::ID::try_from([0x33]).expect("id");
+        let ts = Timestamp::new(0, WallMST::of_u64(1_719_848_326 << 16), 5);
+        let s = ts.to_uhlc(id)?.to_string();
+        assert_eq!(s, format!("{}/33", (1_719_848_326u64 << 32) | 5));
+        assert_eq!(Timestamp::parse_uhlc(&s)?, (ts, id));
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_unrepresentable_timestamps() {
+        let id = uhlc::ID::try_from([0x33]).expect("id");
+        let ts = Timestamp::new(1, WallMST::of_u64(0), 0);
+        assert!(matches!(
+            ts.to_uhlc(id),
+            Err(Error::PackOverflow { field: "epoch", .. })
+        ));
+        let ts = Timestamp::new(0, WallMST::of_u64(0), 16);
+        assert!(matches!(
+            ts.to_uhlc(id),
+            Err(Error::PackOverflow { field: "count", .. })
+        ));
+        assert!(Timestamp::<WallMST>::parse_uhlc("nonsense").is_err());
+    }
+}
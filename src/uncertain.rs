@@ -0,0 +1,289 @@
+use std::cmp::Ordering;
+use std::ops::Sub;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{AsDuration, Clock, ClockSource, Error, OffsetLimiter, Result, Timestamp};
+
+/// A timestamp along with a bound on how far its physical time may be from
+/// true time, in the style of Spanner's TrueTime.
+///
+/// HLC timestamps order events consistently, but two events that happened
+/// on different nodes within the error bound of each other may be ordered
+/// either way. These comparisons only report an ordering when the
+/// uncertainty intervals do not overlap, which allows eg: commit-wait.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UncertainTimestamp<T, D> {
+    /// The timestamp as issued by the clock.
+    pub timestamp: Timestamp<T>,
+    /// The greatest distance between the timestamp's physical time and true
+    /// time.
+    pub error: D,
+}
+
+impl<T, D> UncertainTimestamp<T, D>
+where
+    T: Ord + Copy + Sub<Output = D>,
+    D: Ord + Copy + Sub<Output = D>,
+{
+    /// Returns true if `self` certainly happened before `other`.
+    ///
+    /// Timestamps from an earlier epoch always happen before those from a
+    /// later one. Within an epoch, the uncertainty intervals must not
+    /// overlap.
+    pub fn before(&self, other: &Self) -> bool {
+        let (a, b) = (&self.timestamp, &other.timestamp);
+        match a.epoch.cmp(&b.epoch) {
+            Ordering::Less => true,
+            Ordering::Greater => false,
+            // Subtract the errors from the gap one at a time, rather than
+            // adding them, as their sum may overflow.
+            Ordering::Equal => {
+                a.time < b.time && {
+                    let gap = b.time - a.time;
+                    gap > self.error && gap - self.error > other.error
+                }
+            }
+        }
+    }
+
+    /// Returns true if `self` certainly happened after `other`.
+    pub fn after(&self, other: &Self) -> bool {
+        other.before(self)
+    }
+
+    /// Returns true if neither timestamp is certainly before the other.
+    pub fn maybe_concurrent(&self, other: &Self) -> bool {
+        !self.before(other) && !self.after(other)
+    }
+}
+
+impl<S: ClockSource> Clock<S>
+where
+    S::Delta: Default,
+{
+    /// Creates a unique monotonic timestamp, along with the error bound
+    /// reported by the clock source, or zero if it reports none.
+    pub fn now_uncertain(&mut self) -> Result<UncertainTimestamp<S::Time, S::Delta>> {
+        let error = self.src.uncertainty().unwrap_or_default();
+        Ok(UncertainTimestamp {
+            timestamp: self.now()?,
+            error,
+        })
+    }
+}
+
+impl<S: ClockSource> Clock<S>
+where
+    S::Time: AsDuration,
+    S::Delta: AsDuration,
+{
+    /// Blocks until the clock source's physical time is after that of `ts`
+    /// by more than the error bound reported by the source, returning how
+    /// long we waited. This is Spanner's commit-wait: once it returns, no
+    /// node whose clock is within that bound can issue a timestamp before
+    /// `ts`. The epoch and logical counter of `ts` are ignored.
+    ///
+    /// Returns `Error::DeadlineExceeded` if the source has still not got
+    /// there by `deadline`, as will happen with a source that does not
+    /// follow real time, such as a `ManualClock` that nobody moves.
+    pub fn wait_until(&mut self, ts: &Timestamp<S::Time>, deadline: Instant) -> Result<Duration> {
+        let error = self.src.uncertainty().map(|e| e.as_duration());
+        self.wait_until_with(ts, error.unwrap_or_default(), deadline)
+    }
+
+    /// As `wait_until`, but waiting out the given error bound.
+    pub fn wait_until_with(
+        &mut self,
+        ts: &Timestamp<S::Time>,
+        error: Duration,
+        deadline: Instant,
+    ) -> Result<Duration> {
+        let start = Instant::now();
+        while let Some(remaining) = self.commit_wait_remaining(ts, error, deadline)? {
+            thread::sleep(remaining);
+        }
+        Ok(start.elapsed())
+    }
+
+    // Returns how long to sleep for before the source's physical time may
+    // be after that of `ts` plus `error`, or `None` if it already is. The
+    // sleep is cut short at `deadline`, after which we give up.
+    pub(crate) fn commit_wait_remaining(
+        &mut self,
+        ts: &Timestamp<S::Time>,
+        error: Duration,
+        deadline: Instant,
+    ) -> Result<Option<Duration>> {
+        let target = ts.time.as_duration().saturating_add(error);
+        let now = self.read_pt()?.time.as_duration();
+        if now > target {
+            return Ok(None);
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(Error::DeadlineExceeded { target });
+        }
+        // Sleep for at least a nanosecond, so that we move past `target`.
+        Ok(Some((target - now + Duration::from_nanos(1)).min(left)))
+    }
+}
+
+impl<S: ClockSource> OffsetLimiter<S>
+where
+    S::Time: AsDuration,
+    S::Delta: AsDuration + Copy,
+{
+    /// Creates a unique monotonic timestamp, along with an error bound of
+    /// the configured maximum offset, or the one reported by the clock
+    /// source, whichever is greater.
+    pub fn now_uncertain(&mut self) -> Result<UncertainTimestamp<S::Time, S::Delta>> {
+        let error = self.error_bound();
+        Ok(UncertainTimestamp {
+            timestamp: self.now()?,
+            error,
+        })
+    }
+
+    /// Blocks until the clock source's physical time is after that of `ts`
+    /// by more than the error bound used by `now_uncertain`, returning how
+    /// long we waited. See `Clock::wait_until`.
+    pub fn wait_until(&mut self, ts: &Timestamp<S::Time>, deadline: Instant) -> Result<Duration> {
+        let error = self.error_bound().as_duration();
+        self.clock.wait_until_with(ts, error, deadline)
+    }
+
+    // The greater of the configured maximum offset, and the error bound
+    // reported by the clock source.
+    pub(crate) fn error_bound(&self) -> S::Delta {
+        match self.inner().src.uncertainty() {
+            Some(e) => e.max(self.policy.max_forward),
+            None => self.policy.max_forward,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::{ManualClock, ManualT, MockClock, U64Time, WallNS};
+    use std::sync::Arc;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    fn uncertain(epoch: u32, time: u64, error: u64) -> UncertainTimestamp<ManualT, u64> {
+        UncertainTimestamp {
+            timestamp: Timestamp {
+                epoch,
+                time: time.into(),
+                count: 0,
+            },
+            error,
+        }
+    }
+
+    #[test]
+    fn should_only_order_disjoint_intervals() {
+        assert!(uncertain(0, 10, 2).before(&uncertain(0, 15, 2)));
+        assert!(uncertain(0, 15, 2).after(&uncertain(0, 10, 2)));
+        assert!(uncertain(0, 10, 3).maybe_concurrent(&uncertain(0, 15, 2)));
+        assert!(uncertain(0, 100, 50).before(&uncertain(1, 0, 50)));
+    }
+
+    #[test]
+    fn should_not_order_when_errors_overflow() {
+        let (lo, hi) = (uncertain(0, 0, u64::MAX), uncertain(0, u64::MAX, u64::MAX));
+        assert!(lo.maybe_concurrent(&hi));
+        assert!(!uncertain(0, 0, 1).before(&uncertain(0, u64::MAX, u64::MAX)));
+        assert!(uncertain(0, 0, 1).before(&uncertain(0, u64::MAX, u64::MAX - 2)));
+    }
+
+    #[test]
+    fn should_be_exactly_one_of_before_after_or_concurrent() {
+        let errors = || u32s().map(u64::from);
+        property((
+            timestamps(manualts()),
+            errors(),
+            timestamps(manualts()),
+            errors(),
+        ))
+        .check(|(a, ea, b, eb)| {
+            let a = UncertainTimestamp {
+                timestamp: a,
+                error: ea,
+            };
+            let b = UncertainTimestamp {
+                timestamp: b,
+                error: eb,
+            };
+            let results = [a.before(&b), a.after(&b), a.maybe_concurrent(&b)];
+            assert_eq!(results.iter().filter(|r| **r).count(), 1, "{:?}", results);
+        });
+    }
+
+    #[test]
+    fn limiter_should_use_max_offset_as_error() -> Result<()> {
+        let mut clock = Clock::new(ManualClock::new(10))?.with_max_diff(5);
+        assert_eq!(clock.now_uncertain()?.error, 5);
+        let mut clock = Clock::new(ManualClock::new(10))?;
+        assert_eq!(clock.now_uncertain()?.error, 0);
+        Ok(())
+    }
+
+    fn soon() -> Instant {
+        Instant::now() + Duration::from_secs(10)
+    }
+
+    #[test]
+    fn should_wait_until_after_timestamp_and_error() -> Result<()> {
+        let mut clock = Clock::new(MockClock::stepping(0, 1))?.with_max_diff(5);
+        let ts = clock.now()?;
+        clock.wait_until(&ts, soon())?;
+        assert!(clock.inner().src.time() > ts.time.as_u64() + 6);
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_wait_for_past_timestamps() -> Result<()> {
+        let mut clock = Clock::new(ManualClock::new(10))?;
+        let waited = clock.wait_until(&Timestamp::new(0, ManualT::from(5), 0), soon())?;
+        assert!(waited < Duration::from_millis(100), "{:?}", waited);
+        Ok(())
+    }
+
+    #[test]
+    fn should_give_up_on_stalled_sources_at_deadline() -> Result<()> {
+        let mut clock = Clock::new(ManualClock::new(10))?;
+        let deadline = Instant::now() + Duration::from_millis(20);
+        let res = clock.wait_until(&Timestamp::new(0, ManualT::from(20), 0), deadline);
+        assert!(
+            matches!(res, Err(Error::DeadlineExceeded { target }) if target == Duration::from_nanos(20)),
+            "{:?}",
+            res
+        );
+        assert!(Instant::now() >= deadline);
+        Ok(())
+    }
+
+    #[test]
+    fn should_read_through_the_clock() -> Result<()> {
+        let src = Arc::new(ManualClock::new(100));
+        let mut clock = Clock::new(src.clone())?;
+        clock.now()?;
+        src.set_time(50);
+        clock.wait_until(&Timestamp::new(0, ManualT::from(10), 0), soon())?;
+        assert_eq!(clock.metrics().regressions, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn should_sleep_for_wall_clocks() -> Result<()> {
+        let mut clock = Clock::new(WallNS)?;
+        let ts = clock.now()?;
+        let waited = clock.wait_until_with(&ts, Duration::from_millis(20), soon())?;
+        assert!(waited >= Duration::from_millis(15), "{:?}", waited);
+        assert!(clock.now()?.time - ts.time > Duration::from_millis(20));
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::Timestamp;
+
+/// Tracks the latest timestamp acknowledged by each peer, so that we can
+/// tell when every peer has seen everything up to some point, eg: to decide
+/// when tombstones or old MVCC versions may be garbage collected.
+///
+/// Peers that have not acknowledged anything within the staleness timeout
+/// are presumed to have failed, and no longer hold back the watermark; they
+/// are counted again once they next acknowledge something.
+#[derive(Debug, Clone)]
+pub struct WatermarkTracker<T, Id: Ord> {
+    peers: BTreeMap<Id, Peer<T>>,
+    timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+struct Peer<T> {
+    acked: Option<Timestamp<T>>,
+    heard_at: Instant,
+}
+
+impl<T: Ord + Copy, Id: Ord> WatermarkTracker<T, Id> {
+    /// Returns a tracker with no peers, which considers a peer stale once
+    /// `timeout` has passed without an acknowledgement from it.
+    pub fn new(timeout: Duration) -> Self {
+        WatermarkTracker {
+            peers: BTreeMap::new(),
+            timeout,
+        }
+    }
+
+    /// Returns the staleness timeout.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Adds `peer`, which holds the watermark back until it acknowledges
+    /// something, or becomes stale. Does nothing if it is already known.
+    pub fn add_peer(&mut self, peer: Id) {
+        self.add_peer_at(peer, Instant::now())
+    }
+
+    /// As `add_peer`, but as if done at `now`.
+    pub fn add_peer_at(&mut self, peer: Id, now: Instant) {
+        self.peers.entry(peer).or_insert(Peer {
+            acked: None,
+            heard_at: now,
+        });
+    }
+
+    /// Forgets `peer`, returning the latest timestamp it acknowledged.
+    pub fn remove_peer(&mut self, peer: &Id) -> Option<Timestamp<T>> {
+        self.peers.remove(peer).and_then(|p| p.acked)
+    }
+
+    /// Records that `peer` has seen everything up to `timestamp`, adding it
+    /// if required. Acknowledgements older than one we already hold still
+    /// count as a sign of life, but do not move the peer's watermark back.
+    pub fn ack(&mut self, peer: Id, timestamp: Timestamp<T>) {
+        self.ack_at(peer, timestamp, Instant::now())
+    }
+
+    /// As `ack`, but as if received at `now`.
+    pub fn ack_at(&mut self, peer: Id, timestamp: Timestamp<T>, now: Instant) {
+        let entry = self.peers.entry(peer).or_insert(Peer {
+            acked: None,
+            heard_at: now,
+        });
+        entry.acked = entry.acked.max(Some(timestamp));
+        entry.heard_at = entry.heard_at.max(now);
+    }
+
+    /// Returns the latest timestamp acknowledged by `peer`.
+    pub fn acked(&self, peer: &Id) -> Option<&Timestamp<T>> {
+        self.peers.get(peer).and_then(|p| p.acked.as_ref())
+    }
+
+    /// Returns the latest timestamp that every live peer has acknowledged,
+    /// or `None` if there are no live peers, or one of them has not
+    /// acknowledged anything yet.
+    pub fn stable_upto(&self) -> Option<Timestamp<T>> {
+        self.stable_upto_at(Instant::now())
+    }
+
+    /// As `stable_upto`, but judging staleness as of `now`.
+    pub fn stable_upto_at(&self, now: Instant) -> Option<Timestamp<T>> {
+        let mut live = self
+            .peers
+            .values()
+            .filter(|p| !self.is_stale(p, now))
+            .map(|p| p.acked)
+            .peekable();
+        live.peek()?;
+        live.min().flatten()
+    }
+
+    /// Iterates over the peers that are stale as of `now`.
+    pub fn stale_peers_at(&self, now: Instant) -> impl Iterator<Item = &Id> {
+        self.peers
+            .iter()
+            .filter(move |(_, p)| self.is_stale(p, now))
+            .map(|(id, _)| id)
+    }
+
+    /// Forgets every peer that is stale as of `now`, returning how many
+    /// were removed.
+    pub fn remove_stale_at(&mut self, now: Instant) -> usize {
+        let before = self.peers.len();
+        let timeout = self.timeout;
+        self.peers
+            .retain(|_, p| now.saturating_duration_since(p.heard_at) < timeout);
+        before - self.peers.len()
+    }
+
+    /// Returns the number of peers tracked, whether stale or not.
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Returns true if no peers are tracked.
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    fn is_stale(&self, peer: &Peer<T>, now: Instant) -> bool {
+        now.saturating_duration_since(peer.heard_at) >= self.timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::ManualT;
+    use suppositions::generators::*;
+    use suppositions::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn ts(t: u64) -> Timestamp<ManualT> {
+        Timestamp::new(0, ManualT::from(t), 0)
+    }
+
+    #[test]
+    fn should_be_minimum_over_peers() {
+        property(vecs((u8s(), timestamps(manualts()))).filter(|acks| !acks.is_empty())).check(
+            |acks| {
+                let now = Instant::now();
+                let mut tracker = WatermarkTracker::new(TIMEOUT);
+                for &(peer, ts) in acks.iter() {
+                    tracker.ack_at(peer, ts, now);
+                }
+                let stable = tracker.stable_upto_at(now).expect("stable");
+                tracker
+                    .peers
+                    .values()
+                    .all(|p| p.acked.is_some_and(|acked| stable <= acked))
+                    && tracker.peers.values().any(|p| p.acked == Some(stable))
+            },
+        );
+    }
+
+    #[test]
+    fn should_not_move_backwards_for_a_peer() {
+        let now = Instant::now();
+        let mut tracker = WatermarkTracker::new(TIMEOUT);
+        tracker.ack_at("a", ts(5), now);
+        tracker.ack_at("a", ts(3), now);
+        assert_eq!(tracker.acked(&"a"), Some(&ts(5)));
+        assert_eq!(tracker.stable_upto_at(now), Some(ts(5)));
+    }
+
+    #[test]
+    fn should_be_held_back_by_silent_peers_until_stale() {
+        let start = Instant::now();
+        let mut tracker = WatermarkTracker::new(TIMEOUT);
+        tracker.add_peer_at("b", start);
+        tracker.ack_at("a", ts(5), start);
+        assert_eq!(tracker.stable_upto_at(start), None);
+
+        let later = start + TIMEOUT;
+        tracker.ack_at("a", ts(7), later);
+        assert_eq!(tracker.stable_upto_at(later), Some(ts(7)));
+        assert_eq!(
+            tracker.stale_peers_at(later).collect::<Vec<_>>(),
+            vec![&"b"]
+        );
+
+        tracker.ack_at("b", ts(6), later);
+        assert_eq!(tracker.stable_upto_at(later), Some(ts(6)));
+    }
+
+    #[test]
+    fn should_have_no_watermark_when_all_peers_are_stale() {
+        let start = Instant::now();
+        let mut tracker = WatermarkTracker::new(TIMEOUT);
+        tracker.ack_at("a", ts(5), start);
+        assert_eq!(tracker.stable_upto_at(start + TIMEOUT), None);
+        assert_eq!(tracker.remove_stale_at(start + TIMEOUT), 1);
+        assert!(tracker.is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+//! A self-describing binary format for single timestamps.
+//!
+//! Every encoding starts with the magic byte `WIRE_MAGIC` and a version byte,
+//! so that readers can tell which encoding follows, and refuse anything else
+//! with `Error::UnsupportedWireFormat`, rather than misreading it:
+//!
+//! * `WireVersion::Fixed` is followed by the 16 byte order-preserving
+//!   encoding of `ByteOrdered`.
+//! * `WireVersion::Varint` is followed by the epoch, time ticks and count as
+//!   LEB128 varints, which is usually shorter.
+//!
+//! With the `serialization` feature, `deserialize_any_version` reads both the
+//! current and the previous (v1) serde shapes of `Timestamp<WallNST>`.
+
+use std::convert::TryFrom;
+use std::io;
+
+use crate::codec::{read_varint, write_varint};
+use crate::{ByteOrdered, Error, Result, Timestamp, U64Time};
+
+/// The first byte of every wire encoding.
+pub const WIRE_MAGIC: u8 = 0xC7;
+
+/// The versions of the wire format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WireVersion {
+    /// The fixed-width 16 byte encoding.
+    Fixed = 1,
+    /// The variable-width encoding.
+    Varint = 2,
+}
+
+impl WireVersion {
+    /// The version written by `write`.
+    pub const CURRENT: WireVersion = WireVersion::Varint;
+
+    fn of_u8(version: u8) -> Option<Self> {
+        match version {
+            1 => Some(WireVersion::Fixed),
+            2 => Some(WireVersion::Varint),
+            _ => None,
+        }
+    }
+}
+
+/// Writes `ts` in the current version of the wire format.
+pub fn write<W: io::Write, T: U64Time>(wr: W, ts: &Timestamp<T>) -> Result<()> {
+    write_version(wr, ts, WireVersion::CURRENT)
+}
+
+/// Writes `ts` in the given version of the wire format, eg: for peers that
+/// only understand an older version.
+pub fn write_version<W: io::Write, T: U64Time>(
+    mut wr: W,
+    ts: &Timestamp<T>,
+    version: WireVersion,
+) -> Result<()> {
+    wr.write_all(&[WIRE_MAGIC, version as u8])?;
+    match version {
+        WireVersion::Fixed => ts.write_ordered_bytes(wr)?,
+        WireVersion::Varint => {
+            write_varint(&mut wr, u64::from(ts.epoch))?;
+            write_varint(&mut wr, ts.time.as_u64())?;
+            write_varint(&mut wr, u64::from(ts.count))?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a timestamp written in any supported version of the wire format.
+pub fn read<R: io::Read, T: U64Time>(mut rd: R) -> Result<Timestamp<T>> {
+    let mut header = [0u8; 2];
+    rd.read_exact(&mut header)?;
+    let [magic, version] = header;
+    let version = WireVersion::of_u8(version)
+        .filter(|_| magic == WIRE_MAGIC)
+        .ok_or(Error::UnsupportedWireFormat { magic, version })?;
+    match version {
+        WireVersion::Fixed => Ok(Timestamp::read_ordered_bytes(rd)?),
+        WireVersion::Varint => Ok(Timestamp {
+            epoch: u32::try_from(read_varint(&mut rd)?)?,
+            time: T::of_u64(read_varint(&mut rd)?),
+            count: u32::try_from(read_varint(&mut rd)?)?,
+        }),
+    }
+}
+
+/// Returns `ts` in the current version of the wire format.
+pub fn to_vec<T: U64Time>(ts: &Timestamp<T>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write(&mut buf, ts).expect("writing to a Vec cannot fail");
+    buf
+}
+
+/// Decodes a timestamp from `bytes`, which must hold exactly one encoded
+/// timestamp.
+pub fn from_slice<T: U64Time>(mut bytes: &[u8]) -> Result<Timestamp<T>> {
+    let ts = read(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(Error::Parse(format!(
+            "{} trailing bytes after timestamp",
+            bytes.len()
+        )));
+    }
+    Ok(ts)
+}
+
+#[cfg(feature = "serialization")]
+pub use self::serde_any::deserialize_any_version;
+
+#[cfg(feature = "serialization")]
+mod serde_any {
+    use std::fmt;
+
+    use serde::de;
+
+    use crate::{Timestamp, WallNST};
+
+    // A `WallNST` in either the current newtype shape, or the v1 shape,
+    // which is a single element tuple struct.
+    struct AnyWallNST(WallNST);
+
+    impl<'de> de::Deserialize<'de> for AnyWallNST {
+        fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct Visitor;
+            impl<'de> de::Visitor<'de> for Visitor {
+                type Value = AnyWallNST;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a v1 or v2 WallNS clock value")
+                }
+
+                fn visit_u64<E: de::Error>(self, v: u64) -> Result<AnyWallNST, E> {
+                    Ok(AnyWallNST(WallNST::of_u64(v)))
+                }
+
+                fn visit_seq<V: de::SeqAccess<'de>>(
+                    self,
+                    mut seq: V,
+                ) -> Result<AnyWallNST, V::Error> {
+                    let v = seq
+                        .next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                    Ok(AnyWallNST(WallNST::of_u64(v)))
+                }
+
+                fn visit_newtype_struct<D: de::Deserializer<'de>>(
+                    self,
+                    deserializer: D,
+                ) -> Result<AnyWallNST, D::Error> {
+                    deserializer.deserialize_any(self)
+                }
+            }
+            deserializer.deserialize_any(Visitor)
+        }
+    }
+
+    /// Deserializes a `Timestamp<WallNST>` written in either the current or
+    /// the v1 serde shape, for use with `#[serde(deserialize_with = ...)]`.
+    /// This requires a self-describing format, such as JSON.
+    pub fn deserialize_any_version<'de, D: de::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Timestamp<WallNST>, D::Error> {
+        let ts: Timestamp<AnyWallNST> = de::Deserialize::deserialize(deserializer)?;
+        Ok(Timestamp::new(ts.epoch, ts.time.0, ts.count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{manualts, timestamps};
+    use crate::ManualT;
+    use suppositions::*;
+
+    #[test]
+    fn should_round_trip_all_versions() {
+        property(timestamps(manualts())).check(|ts| {
+            for version in [WireVersion::Fixed, WireVersion::Varint] {
+                let mut buf = Vec::new();
+                write_version(&mut buf, &ts, version).expect("write");
+                assert_eq!(buf[..2], [WIRE_MAGIC, version as u8]);
+                assert_eq!(from_slice::<ManualT>(&buf).expect("read"), ts);
+            }
+        })
+    }
+
+    #[test]
+    fn should_refuse_unknown_versions() {
+        let mut buf = to_vec(&Timestamp::new(1, ManualT::from(2), 3));
+        buf[1] = 9;
+        assert!(matches!(
+            from_slice::<ManualT>(&buf),
+            Err(Error::UnsupportedWireFormat {
+                magic: WIRE_MAGIC,
+                version: 9
+            })
+        ));
+        buf[0] = 0;
+        buf[1] = WireVersion::CURRENT as u8;
+        assert!(matches!(
+            from_slice::<ManualT>(&buf),
+            Err(Error::UnsupportedWireFormat { magic: 0, .. })
+        ));
+    }
+
+    #[cfg(feature = "serialization")]
+    #[test]
+    fn should_deserialize_both_serde_shapes() {
+        use crate::WallNST;
+
+        #[derive(Deserialize)]
+        struct Msg {
+            #[serde(deserialize_with = "deserialize_any_version")]
+            ts: Timestamp<WallNST>,
+        }
+        let expected = Timestamp::new(0, WallNST::of_u64(1_558_805_131_923_316_000), 4);
+        for json in [
+            r#"{"ts": [0, 1558805131923316000, 4]}"#,
+            r#"{"ts": [0, [1558805131923316000], 4]}"#,
+        ] {
+            let msg: Msg = serde_json::from_str(json).expect(json);
+            assert_eq!(msg.ts, expected);
+        }
+    }
+}
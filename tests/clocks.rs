@@ -1,4 +1,14 @@
-use hybrid_clocks::{Clock, ManualClock, ManualT, Result, Timestamp};
+use std::cmp::Ordering;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use hybrid_clocks::{
+    AdaptiveOffsetPolicy, BoxedClockSource, Clock, ClockSource, Error, ManualClock, ManualT,
+    MockClock, ObservationOutcome, OffsetPolicy, OffsetStats, Result, SharedClock, SkewedSource,
+    Timestamp, TimestampDiff, WallMST, WallNST,
+};
 use suppositions::generators::*;
 use suppositions::*;
 
@@ -6,7 +16,7 @@ fn observing(
     clock: &mut Clock<ManualClock>,
     msg: &Timestamp<ManualT>,
 ) -> Result<Timestamp<ManualT>> {
-    clock.observe(msg);
+    clock.observe(msg)?;
     clock.now()
 }
 
@@ -91,7 +101,7 @@ fn fig_6_proc_2_b() -> Result<()> {
         epoch: 0,
         time: 1.into(),
         count: 0,
-    });
+    })?;
 
     clock.set_time(2);
     assert_eq!(
@@ -268,6 +278,253 @@ fn should_apply_configured_epoch() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn now_infallible_should_match_now() -> Result<()> {
+    let mut a = Clock::manual(5)?;
+    let mut b = Clock::manual(5)?;
+    for t in &[5, 7, 6] {
+        a.set_time(*t);
+        b.set_time(*t);
+        assert_eq!(a.now_infallible(), b.now()?);
+    }
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "logical counter exhausted")]
+fn now_infallible_should_panic_on_exhaustion() {
+    let mut clock = Clock::manual(5).expect("clock");
+    clock.set_max_count(1);
+    clock.now_infallible();
+    clock.now_infallible();
+}
+
+#[test]
+fn should_accept_boxed_sources() -> Result<()> {
+    let src: BoxedClockSource<ManualT, u64> = Box::new(ManualClock::new(3));
+    let mut clock = Clock::new(src)?;
+    assert_eq!(
+        clock.now()?,
+        Timestamp {
+            epoch: 0,
+            time: 3.into(),
+            count: 1
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn now_systemtime_should_project_physical_time() -> Result<()> {
+    let before = std::time::SystemTime::now();
+    let (ts, st) = Clock::wall_ns()?.now_systemtime()?;
+    assert_eq!(st, ts.time.as_systemtime());
+    assert!(st >= before);
+
+    let (ts, st) = Clock::wall_ms()?.now_systemtime()?;
+    assert_eq!(st, ts.systemtime());
+    Ok(())
+}
+
+#[test]
+fn skew_window_should_limit_causality() {
+    let ts = |epoch, time: u64| Timestamp {
+        epoch,
+        time: ManualT::from(time),
+        count: 0,
+    };
+    assert!(ts(0, 10).happens_before_within(&ts(0, 16), 5));
+    assert!(!ts(0, 10).happens_before_within(&ts(0, 15), 5));
+    assert!(!ts(0, 16).happens_before_within(&ts(0, 10), 5));
+    assert!(ts(0, 100).happens_before_within(&ts(1, 0), 5));
+
+    assert!(ts(0, 10).definitely_concurrent_within(&ts(0, 15), 5));
+    assert!(ts(0, 15).definitely_concurrent_within(&ts(0, 10), 5));
+    assert!(!ts(0, 10).definitely_concurrent_within(&ts(0, 16), 5));
+    assert!(!ts(0, 10).definitely_concurrent_within(&ts(1, 10), 5));
+}
+
+#[test]
+fn skew_window_should_partition_pairs() {
+    property((timestamps(manualts()), timestamps(manualts()), u64s())).check(|(a, b, skew)| {
+        let before = a.happens_before_within(&b, skew);
+        let after = b.happens_before_within(&a, skew);
+        let concurrent = a.definitely_concurrent_within(&b, skew);
+        assert_eq!(
+            [before, after, concurrent].iter().filter(|x| **x).count(),
+            1,
+            "{} {} within {}",
+            a,
+            b,
+            skew
+        );
+    });
+}
+
+#[test]
+fn adding_duration_should_reset_count() {
+    let ts = Timestamp {
+        epoch: 2,
+        time: WallNST::of_u64(1_000),
+        count: 7,
+    };
+    let expiry = ts + Duration::from_micros(30);
+    assert_eq!(
+        expiry,
+        Timestamp {
+            epoch: 2,
+            time: WallNST::of_u64(31_000),
+            count: 0,
+        }
+    );
+    assert_eq!(
+        expiry - Duration::from_micros(30),
+        Timestamp { count: 0, ..ts }
+    );
+    assert_eq!(ts + Duration::ZERO, ts);
+}
+
+#[test]
+fn adding_duration_should_follow_original() {
+    let ts = Timestamp {
+        epoch: 0,
+        time: WallMST::of_u64(1 << 16),
+        count: u32::MAX,
+    };
+    assert!(ts + Duration::from_nanos(1) > ts);
+    assert!(ts - Duration::from_nanos(1) < ts);
+}
+
+#[test]
+fn diff_should_report_each_component() {
+    let a = Timestamp {
+        epoch: 1,
+        time: WallNST::of_u64(5_000),
+        count: 0,
+    };
+    let b = Timestamp {
+        epoch: 3,
+        time: WallNST::of_u64(2_000),
+        count: 4,
+    };
+    assert_eq!(
+        a.diff(&b),
+        TimestampDiff {
+            epochs: -2,
+            time: Duration::from_micros(3),
+            time_direction: Ordering::Greater,
+            counts: -4,
+        }
+    );
+    assert_eq!(b.diff(&a).time_direction, Ordering::Less);
+    assert_eq!(a.diff(&a).time, Duration::ZERO);
+}
+
+const SENTINEL: Timestamp<WallNST> = Timestamp::new(1, WallNST::of_u64(10), 0);
+
+#[test]
+fn const_timestamps_should_be_usable_in_patterns() {
+    let ts = Timestamp {
+        epoch: 1,
+        time: WallNST::of_u64(10),
+        count: 0,
+    };
+    assert!(matches!(ts, SENTINEL));
+    assert!(Timestamp::<WallMST>::MIN < Timestamp::new(0, WallMST::of_u64(0), 1));
+}
+
+#[test]
+fn bounds_should_contain_all_timestamps() {
+    property(timestamps(manualts())).check(|ts| {
+        assert!(Timestamp::<ManualT>::MIN <= ts);
+        assert!(ts <= Timestamp::<ManualT>::MAX);
+    });
+}
+
+#[test]
+fn should_round_trip_via_display() {
+    property(timestamps(manualts())).check(|ts| {
+        assert_eq!(
+            ts.to_string().parse::<Timestamp<ManualT>>().expect("parse"),
+            ts
+        );
+    });
+    property(timestamps(u64s())).check(|ts| {
+        assert_eq!(ts.to_string().parse::<Timestamp<u64>>().expect("parse"), ts);
+    });
+}
+
+#[test]
+fn should_refuse_to_parse_malformed_timestamps() {
+    for s in &["", "1:2", "1+2", "a:2+3", "1:2+b", "1:2+3+4"] {
+        assert!(
+            matches!(s.parse::<Timestamp<u64>>(), Err(Error::Parse(_))),
+            "{:?}",
+            s
+        );
+    }
+}
+
+#[test]
+fn should_drive_shared_manual_clock_from_other_threads() -> Result<()> {
+    let src = Arc::new(ManualClock::new(10));
+    let clock = SharedClock::new(Clock::new(src.clone())?);
+    let ts = thread::scope(|s| {
+        s.spawn(|| src.set_time(20));
+        s.spawn(|| clock.now()).join().expect("join")
+    })?;
+    assert!(ts.time >= ManualT::from(10));
+    assert_eq!(clock.now()?.time, ManualT::from(20));
+    Ok(())
+}
+
+#[test]
+fn should_reset_count_as_mock_clock_advances() -> Result<()> {
+    let mut clock = Clock::new(MockClock::stepping(100, 10))?;
+    let a = clock.now()?;
+    let b = clock.now()?;
+    assert_eq!((a.time, a.count), (ManualT::from(110), 0));
+    assert_eq!((b.time, b.count), (ManualT::from(120), 0));
+    Ok(())
+}
+
+#[test]
+fn should_refuse_to_move_epoch_backwards() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    clock.try_set_epoch(2)?;
+    let a = clock.now()?;
+
+    assert!(matches!(
+        clock.try_set_epoch(1),
+        Err(Error::EpochRegression {
+            current: 2,
+            requested: 1
+        })
+    ));
+    clock.try_set_epoch(2)?;
+    clock.try_set_epoch(3)?;
+    assert!(clock.now()? > a);
+    Ok(())
+}
+
+#[test]
+fn should_refuse_epoch_behind_observed_timestamp() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    clock.observe(&Timestamp {
+        epoch: 5,
+        time: 1.into(),
+        count: 0,
+    })?;
+    assert!(matches!(
+        clock.try_set_epoch(4),
+        Err(Error::EpochRegression {
+            current: 5,
+            requested: 4
+        })
+    ));
+    Ok(())
+}
+
 #[test]
 fn should_update_via_observed_epochs() -> Result<()> {
     let mut clock0 = Clock::manual(10)?;
@@ -404,6 +661,285 @@ fn should_ignore_clocks_too_far_forward() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn should_report_rejected_offset() -> Result<()> {
+    let mut clock = Clock::new(ManualClock::new(5))?.with_max_diff(10);
+    let msg = Timestamp {
+        epoch: 3,
+        time: 20.into(),
+        count: 1,
+    };
+    match clock.observe(&msg) {
+        Err(Error::OffsetTooGreat {
+            observed,
+            limit,
+            timestamp,
+        }) => {
+            assert_eq!(observed, Duration::from_nanos(15));
+            assert_eq!(limit, Duration::from_nanos(10));
+            assert_eq!(
+                timestamp,
+                Timestamp {
+                    epoch: 3,
+                    time: Duration::from_nanos(20),
+                    count: 1
+                }
+            );
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn should_ignore_clocks_too_far_backward() -> Result<()> {
+    let mut clock = Clock::new(ManualClock::new(100))?
+        .with_max_diff(10)
+        .with_max_diff_backward(50);
+    let old = Timestamp {
+        epoch: 0,
+        time: 49.into(),
+        count: 0,
+    };
+    assert!(matches!(
+        clock.observe(&old),
+        Err(Error::OffsetTooFarBehind { observed, .. }) if observed == Duration::from_nanos(51)
+    ));
+    clock.observe(&Timestamp {
+        time: 50.into(),
+        ..old
+    })?;
+    Ok(())
+}
+
+#[test]
+fn should_refuse_to_issue_after_local_clock_jumps() -> Result<()> {
+    const SECOND: u64 = 1_000_000_000;
+    let mut clock = Clock::new(ManualClock::new(SECOND))?
+        .with_max_diff(10)
+        .with_max_local_jump(SECOND);
+    let before = clock.now()?;
+    clock.inner_mut().set_time(2 * SECOND);
+    clock.now()?;
+    clock.inner_mut().set_time(3600 * SECOND);
+    assert!(matches!(
+        clock.now(),
+        Err(Error::LocalClockJump { limit, .. }) if limit == Duration::from_secs(1)
+    ));
+    assert!(
+        clock.inner().peek()
+            < Timestamp {
+                time: (3600 * SECOND).into(),
+                ..before
+            }
+    );
+
+    clock.set_max_local_jump(Some(SECOND));
+    assert_eq!(clock.now()?.time, ManualT::from(3600 * SECOND));
+    Ok(())
+}
+
+// A source whose times have no `Duration` equivalent.
+#[derive(Debug)]
+struct Signed(i64);
+
+impl ClockSource for Signed {
+    type Time = i64;
+    type Delta = i64;
+    fn now(&mut self) -> Result<i64> {
+        self.0 += 1;
+        Ok(self.0)
+    }
+}
+
+#[test]
+fn limiter_should_issue_timestamps_from_custom_sources() -> Result<()> {
+    let mut clock = Clock::new(Signed(-10))?.with_max_diff(5);
+    let a = clock.now()?;
+    let b = clock.now()?;
+    assert!(a < b, "{} < {}", a, b);
+    Ok(())
+}
+
+// Refuses timestamps more than a fixed number of ticks ahead, reporting
+// each tick as a nanosecond.
+struct MaxTicks(i64);
+
+impl OffsetPolicy<i64, i64> for MaxTicks {
+    fn check(&mut self, ts: &Timestamp<i64>, offset: &i64, direction: Ordering) -> Result<()> {
+        let nanos = |ticks: i64| Duration::from_nanos(ticks.unsigned_abs());
+        if direction == Ordering::Greater && *offset > self.0 {
+            return Err(Error::OffsetTooGreat {
+                observed: nanos(*offset),
+                limit: nanos(self.0),
+                timestamp: Timestamp::new(ts.epoch, nanos(ts.time), ts.count),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn limiter_should_check_custom_sources_with_custom_policies() -> Result<()> {
+    let mut clock = Clock::new(Signed(-10))?.with_offset_policy(MaxTicks(5));
+    clock.observe(&Timestamp::new(0, -5, 0))?;
+    assert!(matches!(
+        clock.observe(&Timestamp::new(0, 10, 0)),
+        Err(Error::OffsetTooGreat { limit, .. }) if limit == Duration::from_nanos(5)
+    ));
+    assert_eq!(clock.stats().rejected, 1);
+    assert_eq!(clock.stats().max_offset_seen, Duration::default());
+    Ok(())
+}
+
+#[test]
+fn should_consult_custom_offset_policy() -> Result<()> {
+    // Refuse anything further ahead than the furthest offset seen so far.
+    let mut furthest = Duration::from_nanos(5);
+    let mut clock = Clock::new(ManualClock::new(100))?.with_offset_policy(
+        move |ts: &Timestamp<Duration>, offset: Duration, direction: Ordering| {
+            if direction == Ordering::Greater && offset > furthest * 2 {
+                return Err(Error::OffsetTooGreat {
+                    observed: offset,
+                    limit: furthest * 2,
+                    timestamp: *ts,
+                });
+            }
+            furthest = furthest.max(offset);
+            Ok(())
+        },
+    );
+    let at = |time: u64| Timestamp {
+        epoch: 0,
+        time: time.into(),
+        count: 0,
+    };
+    clock.observe(&at(108))?;
+    clock.observe(&at(116))?;
+    assert!(matches!(
+        clock.observe(&at(133)),
+        Err(Error::OffsetTooGreat { .. })
+    ));
+    clock.observe(&at(50))?;
+    assert_eq!(clock.stats().rejected, 1);
+    Ok(())
+}
+
+#[test]
+fn adaptive_policy_should_follow_error_bound() -> Result<()> {
+    let bound = Arc::new(AtomicU64::new(10));
+    let reported = bound.clone();
+    let policy = AdaptiveOffsetPolicy::new(
+        move || Ok(Duration::from_nanos(reported.load(AtomicOrdering::SeqCst))),
+        Duration::from_nanos(5),
+        Duration::from_secs(0),
+    );
+    let mut clock = Clock::new(ManualClock::new(100))?.with_offset_policy(policy);
+    let at = |time: u64| Timestamp {
+        epoch: 0,
+        time: time.into(),
+        count: 0,
+    };
+    clock.observe(&at(115))?;
+    assert!(matches!(
+        clock.observe(&at(120)),
+        Err(Error::OffsetTooGreat { limit, .. }) if limit == Duration::from_nanos(15)
+    ));
+    bound.store(100, AtomicOrdering::SeqCst);
+    clock.observe(&at(120))?;
+    Ok(())
+}
+
+#[test]
+fn should_refuse_timestamps_from_skewed_node() -> Result<()> {
+    let mut skewed = Clock::new(SkewedSource::ahead(ManualClock::new(1000), 200))?;
+    let mut local = Clock::new(ManualClock::new(1000))?.with_max_diff(100);
+    let ts = skewed.now()?;
+    assert!(matches!(
+        local.observe(&ts),
+        Err(Error::OffsetTooGreat { observed, .. }) if observed == Duration::from_nanos(200)
+    ));
+    Ok(())
+}
+
+#[test]
+fn should_clamp_clocks_too_far_forward() -> Result<()> {
+    let mut clock = Clock::new(ManualClock::new(5))?.with_max_diff(10);
+    let ahead = Timestamp {
+        epoch: 0,
+        time: 100.into(),
+        count: 3,
+    };
+    let (merged, clamped) = clock.observe_clamped(&ahead)?;
+    assert!(clamped);
+    assert_eq!(
+        merged,
+        Timestamp {
+            time: 15.into(),
+            ..ahead
+        }
+    );
+    assert!(clock.now()? > merged);
+
+    let near = Timestamp {
+        epoch: 0,
+        time: 12.into(),
+        count: 0,
+    };
+    assert_eq!(clock.observe_clamped(&near)?, (near, false));
+    Ok(())
+}
+
+#[test]
+fn should_apply_adjusted_max_offset() -> Result<()> {
+    let mut clock = Clock::new(ManualClock::new(0))?.with_max_diff(10);
+    clock.observe(&Timestamp {
+        epoch: 0,
+        time: 5.into(),
+        count: 0,
+    })?;
+    let ahead = Timestamp {
+        epoch: 0,
+        time: 30.into(),
+        count: 0,
+    };
+    assert!(clock.observe(&ahead).is_err());
+
+    clock.set_max_offset(50);
+    assert_eq!(*clock.max_offset(), 50);
+    clock.observe(&ahead)?;
+    assert!(clock.now()? > ahead);
+
+    clock.set_max_backward_offset(Some(1));
+    assert_eq!(clock.max_backward_offset(), Some(&1));
+    Ok(())
+}
+
+#[test]
+fn should_count_rejected_observations() -> Result<()> {
+    let mut clock = Clock::new(ManualClock::new(100))?
+        .with_max_diff(10)
+        .with_max_diff_backward(50);
+    assert_eq!(clock.stats(), OffsetStats::default());
+
+    let at = |time: u64| Timestamp {
+        epoch: 0,
+        time: time.into(),
+        count: 0,
+    };
+    clock.observe(&at(105))?;
+    assert!(clock.observe(&at(130)).is_err());
+    assert!(clock.observe(&at(10)).is_err());
+    clock.observe_clamped(&at(140))?;
+
+    let stats = clock.stats();
+    assert_eq!(stats.rejected, 2);
+    assert_eq!(stats.clamped, 1);
+    assert_eq!(stats.max_offset_seen, Duration::from_nanos(40));
+    assert!(stats.last_rejection.is_some());
+    Ok(())
+}
+
 #[test]
 fn should_account_for_time_passing_when_checking_max_error() -> Result<()> {
     let src = ManualClock::new(0);
@@ -437,9 +973,168 @@ fn should_observe_past_timestamp() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn should_resume_from_saved_state() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    clock.set_epoch(2);
+    let issued = clock.now_batch(3)?;
+    let state = clock.state();
+
+    let mut restored = Clock::from_state(ManualClock::new(0), state);
+    assert_eq!(restored.state(), state);
+    let t = restored.now()?;
+    assert!(issued.iter().all(|i| *i < t), "{:?} < {:?}", issued, t);
+    assert_eq!(t.epoch, 2);
+    Ok(())
+}
+
+#[test]
+fn should_merge_state_from_another_clock() -> Result<()> {
+    let mut a = Clock::manual(10)?;
+    let mut b = Clock::manual(20)?;
+    b.set_epoch(1);
+    let _ = a.now()?;
+    let from_b = b.now_batch(5)?;
+
+    a.merge_state(b.state());
+    assert_eq!(a.peek(), from_b[4]);
+    let t = a.now()?;
+    assert!(from_b.iter().all(|x| *x < t), "{:?} < {:?}", from_b, t);
+    assert_eq!(
+        t,
+        Timestamp {
+            epoch: 1,
+            time: 20.into(),
+            count: 5
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn merging_older_state_should_not_regress() -> Result<()> {
+    let mut a = Clock::manual(20)?;
+    a.set_epoch(2);
+    let issued = a.now()?;
+    let b = Clock::manual(10)?;
+
+    a.merge_state(b.state());
+    assert_eq!(a.peek(), issued);
+    assert_eq!(a.now()?.epoch, 2);
+    Ok(())
+}
+
+#[test]
+fn should_refuse_to_overflow_counter_when_observing() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    let exhausted = Timestamp {
+        epoch: 0,
+        time: 10.into(),
+        count: u32::MAX,
+    };
+    assert!(matches!(
+        clock.observe(&exhausted),
+        Err(Error::CounterExhausted)
+    ));
+    assert!(matches!(
+        clock.receive(&exhausted),
+        Err(Error::CounterExhausted)
+    ));
+    Ok(())
+}
+
+#[test]
+fn should_refuse_to_overflow_counter_until_time_advances() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    clock.observe(&Timestamp {
+        epoch: 0,
+        time: 10.into(),
+        count: u32::MAX - 2,
+    })?;
+    assert_eq!(clock.now()?.count, u32::MAX);
+    assert!(matches!(clock.now(), Err(Error::CounterExhausted)));
+    assert!(matches!(clock.tick(), Err(Error::CounterExhausted)));
+    assert_eq!(clock.peek().count, u32::MAX);
+
+    clock.set_time(11);
+    assert_eq!(
+        clock.now()?,
+        Timestamp {
+            epoch: 0,
+            time: 11.into(),
+            count: 0
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn should_refuse_batches_that_overflow_counter() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    clock.observe(&Timestamp {
+        epoch: 0,
+        time: 10.into(),
+        count: u32::MAX - 10,
+    })?;
+    assert!(matches!(clock.now_batch(10), Err(Error::CounterExhausted)));
+    assert_eq!(clock.now_batch(9)?.last().map(|t| t.count), Some(u32::MAX));
+    Ok(())
+}
+
+#[test]
+fn should_refuse_counts_beyond_configured_limit() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    clock.set_max_count(5);
+    assert!(matches!(
+        clock.observe(&Timestamp {
+            epoch: 0,
+            time: 10.into(),
+            count: 5,
+        }),
+        Err(Error::CountLimitExceeded { count: 6, limit: 5 })
+    ));
+    assert!(matches!(
+        clock.now_batch(6),
+        Err(Error::CountLimitExceeded { .. })
+    ));
+    assert_eq!(clock.now_batch(5)?.len(), 5);
+    assert!(clock.tick().is_err());
+    assert_eq!(clock.peek().count, 5);
+
+    clock.set_time(11);
+    assert_eq!(clock.now()?.count, 0);
+    Ok(())
+}
+
 #[cfg(feature = "serialization")]
 mod serde {
     use super::*;
+    use hybrid_clocks::{ClockState, WallNS};
+
+    #[test]
+    fn should_round_trip_clock_state_via_serde() {
+        property((u32s(), timestamps(manualts()))).check(|(epoch, last_observed)| {
+            let state = ClockState {
+                epoch,
+                last_observed,
+            };
+            let s = serde_json::to_string(&state).expect("to-json");
+            let state2 = serde_json::from_str(&s).expect("from-json");
+            state == state2
+        });
+    }
+
+    #[test]
+    fn should_restore_clock_via_serde() -> Result<()> {
+        let mut clock = Clock::wall_ns()?;
+        clock.set_epoch(4);
+        let issued = clock.now()?;
+        let s = serde_json::to_string(&clock).expect("to-json");
+        let mut restored: Clock<WallNS> = serde_json::from_str(&s).expect("from-json");
+        assert_eq!(restored.state(), clock.state());
+        assert!(restored.now()? > issued);
+        Ok(())
+    }
 
     #[test]
     fn should_round_trip_via_serde() {
@@ -449,4 +1144,256 @@ mod serde {
             ts == ts2
         });
     }
+
+    #[test]
+    fn should_accept_tuple_and_named_forms() {
+        let expected = Timestamp::new(1, ManualT::from(2), 3);
+        let ts: Timestamp<ManualT> = serde_json::from_str("[1,2,3]").expect("tuple");
+        assert_eq!(ts, expected);
+        let named = serde_json::from_str::<Timestamp<ManualT>>(r#"{"epoch":1,"time":2,"count":3}"#);
+        if cfg!(feature = "serde-named") {
+            assert_eq!(named.expect("named"), expected);
+        } else {
+            assert!(named.is_err());
+        }
+    }
+
+    #[test]
+    fn should_serialize_in_configured_form() {
+        let s = serde_json::to_string(&Timestamp::new(1, ManualT::from(2), 3)).expect("to-json");
+        if cfg!(feature = "serde-named") {
+            assert_eq!(s, r#"{"epoch":1,"time":2,"count":3}"#);
+        } else {
+            assert_eq!(s, "[1,2,3]");
+        }
+    }
+}
+
+#[test]
+fn should_issue_contiguous_batches() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    let batch = clock.now_batch(3)?;
+    assert_eq!(
+        batch,
+        (1..4)
+            .map(|count| Timestamp {
+                epoch: 0,
+                time: 10.into(),
+                count
+            })
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        clock.now()?,
+        Timestamp {
+            epoch: 0,
+            time: 10.into(),
+            count: 4
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn empty_batches_should_not_advance_clock() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    assert!(clock.now_batch(0)?.is_empty());
+    assert_eq!(
+        clock.now()?,
+        Timestamp {
+            epoch: 0,
+            time: 10.into(),
+            count: 1
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn supposedly_batches_are_strictly_increasing() {
+    property((u64s(), timestamps(manualts()), u8s())).check(|(t0, observed, n)| -> Result<()> {
+        let mut clock = Clock::manual(t0)?;
+        clock.observe(&observed)?;
+        let batch = clock.now_batch(n.into())?;
+        assert_eq!(batch.len(), usize::from(n));
+        assert!(batch.windows(2).all(|w| w[0] < w[1]), "{:?}", batch);
+        let after = clock.now()?;
+        assert!(batch.iter().all(|t| *t < after && *t > observed));
+        Ok(())
+    });
+}
+
+#[test]
+fn peek_should_not_advance_clock() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    let issued = clock.now()?;
+    assert_eq!(clock.peek(), issued);
+    assert_eq!(clock.peek(), issued);
+    assert_eq!(
+        clock.now()?,
+        Timestamp {
+            epoch: 0,
+            time: 10.into(),
+            count: 2
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn peek_should_reflect_observed_timestamps() -> Result<()> {
+    let mut clock = Clock::manual(0)?;
+    let observed = Timestamp {
+        epoch: 0,
+        time: 10.into(),
+        count: 3,
+    };
+    clock.observe(&observed)?;
+    assert_eq!(clock.peek(), observed);
+    Ok(())
+}
+
+#[test]
+fn observe_now_should_report_remote_ahead() -> Result<()> {
+    let mut clock = Clock::manual(0)?;
+    let observed = Timestamp {
+        epoch: 0,
+        time: 10.into(),
+        count: 0,
+    };
+    assert_eq!(
+        clock.observe_now(&observed)?,
+        (observed, ObservationOutcome::RemoteAhead)
+    );
+    Ok(())
+}
+
+#[test]
+fn observe_now_should_report_local_ahead() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    let _ = clock.now()?;
+    let (ts, outcome) = clock.observe_now(&Timestamp {
+        epoch: 0,
+        time: 1.into(),
+        count: 0,
+    })?;
+    assert_eq!(outcome, ObservationOutcome::LocalAhead);
+    assert_eq!(
+        ts,
+        Timestamp {
+            epoch: 0,
+            time: 10.into(),
+            count: 2
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn observe_now_should_report_equal() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    let issued = clock.now()?;
+    let (ts, outcome) = clock.observe_now(&issued)?;
+    assert_eq!(outcome, ObservationOutcome::Equal);
+    assert!(ts > issued);
+    Ok(())
+}
+
+#[test]
+fn receive_should_follow_physical_time_past_old_messages() -> Result<()> {
+    let mut clock = Clock::manual(0)?;
+    clock.observe(&Timestamp {
+        epoch: 0,
+        time: 5.into(),
+        count: 3,
+    })?;
+    clock.set_time(20);
+    assert_eq!(
+        clock.receive(&Timestamp {
+            epoch: 0,
+            time: 5.into(),
+            count: 7,
+        })?,
+        Timestamp {
+            epoch: 0,
+            time: 20.into(),
+            count: 0
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn receive_fig_6_proc_1_a() -> Result<()> {
+    let mut clock = Clock::manual(1)?;
+    assert_eq!(
+        clock.receive(&Timestamp {
+            epoch: 0,
+            time: 10.into(),
+            count: 0,
+        })?,
+        Timestamp {
+            epoch: 0,
+            time: 10.into(),
+            count: 1
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn receive_should_take_greatest_count_on_ties() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    let _ = clock.now_batch(4)?;
+    assert_eq!(
+        clock.receive(&Timestamp {
+            epoch: 0,
+            time: 10.into(),
+            count: 2,
+        })?,
+        Timestamp {
+            epoch: 0,
+            time: 10.into(),
+            count: 5
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn supposedly_receive_is_larger_than_observed_and_issued() {
+    property((u64s(), timestamps(manualts()))).check(|(t0, observed)| -> Result<()> {
+        let mut clock = Clock::manual(t0)?;
+        let t1 = clock.now()?;
+        let t2 = clock.receive(&observed)?;
+        assert!(t2 > observed, "{:?} > {:?}", t2, observed);
+        assert!(t2 > t1, "{:?} > {:?}", t2, t1);
+        Ok(())
+    });
+}
+
+#[test]
+fn tick_should_not_read_source() -> Result<()> {
+    let mut clock = Clock::manual(10)?;
+    let t0 = clock.now()?;
+    clock.set_time(20);
+    let t1 = clock.tick()?;
+    assert_eq!(
+        t1,
+        Timestamp {
+            epoch: 0,
+            time: 10.into(),
+            count: 2
+        }
+    );
+    assert!(t1 > t0);
+    assert_eq!(
+        clock.now()?,
+        Timestamp {
+            epoch: 0,
+            time: 20.into(),
+            count: 0
+        }
+    );
+    Ok(())
 }